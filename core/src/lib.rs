@@ -2,7 +2,7 @@ use wasm_bindgen::prelude::*;
 use wasm_bindgen::closure::Closure;
 use wasm_bindgen::JsCast;
 use web_sys::{
-    window, HtmlCanvasElement, HtmlImageElement,
+    window, CanvasRenderingContext2d, HtmlCanvasElement, HtmlImageElement, XmlHttpRequest,
     WebGl2RenderingContext, WebGlProgram, WebGlShader, WebGlBuffer, WebGlTexture,
     WebGlUniformLocation, WebGlVertexArrayObject
 };
@@ -19,13 +19,33 @@ fn cancel_animation_frame(handle: i32) -> Result<(), JsValue> {
     let _ = window.cancel_animation_frame(handle);
     Ok(())
 }
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
 use js_sys::{Array, Float32Array};
 use rstar::{RTree, RTreeObject, AABB};
 use lyon_tessellation::{BuffersBuilder, FillOptions, FillTessellator, FillVertex, VertexBuffers};
 use lyon_path::Path;
 
+mod mvt;
+mod pmtiles;
+mod projection;
+mod terrain;
+mod tile_cache;
+mod tile_scheduler;
+mod tile_source;
+mod tile_store;
+mod wms;
+mod wmts;
+use projection::{projection_from_code, Projection, WebMercator};
+use terrain::{ElevationTile, HillshadeStyle, TerrainEncoding, TerrainStyle};
+use tile_cache::TileCache;
+use tile_scheduler::{TileRequestState, TileScheduler};
+use tile_source::{MemoryTileSource, TileData, TileFormat, TileSource, UrlTemplateSource};
+use tile_store::{BytesCallback, MemoryTileStore, SeedJob, SeedProgress, TileStore};
+use wms::{WmsParams, WmsVersion};
+use wmts::{WmtsOptions, WmtsSource};
+
 
 // Coordinate and spatial data structures
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -35,11 +55,32 @@ struct TileCoord {
     z: u32,
 }
 
-#[derive(Clone)]
-struct Tile {
-    coord: TileCoord,
-    texture: Option<WebGlTexture>,
-    loading: bool,
+impl TileCoord {
+    // Cache and request key; each tile layer gets its own namespace
+    fn key(&self, layer_id: u32) -> String {
+        format!("{}/{}/{}/{}", layer_id, self.z, self.x, self.y)
+    }
+
+    // Bing-style quadkey: one base-4 digit per level, most significant first
+    fn quadkey(&self) -> String {
+        (1..=self.z)
+            .rev()
+            .map(|level| {
+                let mask = 1 << (level - 1);
+                let digit = (self.x & mask != 0) as u8 + 2 * (self.y & mask != 0) as u8;
+                char::from(b'0' + digit)
+            })
+            .collect()
+    }
+}
+
+// One textured quad drawn for a visible tile: the cached texture `key`
+// covers the (unwrapped) tile `region`, sampling `uv` = [u0, v0, u1, v1]
+#[derive(Clone, Debug, PartialEq)]
+struct TileDraw {
+    key: String,
+    region: TileCoord,
+    uv: [f32; 4],
 }
 
 // Spatial index for hit testing
@@ -48,6 +89,12 @@ struct SpatialFeature {
     id: u32,
     bounds: AABB<[f64; 2]>,
     meta: serde_json::Value,
+    // Screen-space triangles a hit has to fall in, for filled areas; empty
+    // for points and lines
+    triangles: Vec<[f64; 2]>,
+    // Screen-space segment a hit has to lie near, for lines; for points the
+    // bounds are enough
+    segment: Option<[[f64; 2]; 2]>,
 }
 
 impl RTreeObject for SpatialFeature {
@@ -74,14 +121,132 @@ struct WebGlState {
     point_vao: WebGlVertexArrayObject,
     line_vao: WebGlVertexArrayObject,
     polygon_vao: WebGlVertexArrayObject,
+    // Image overlays share the tile program, with projective texture coordinates
+    overlay_vao: WebGlVertexArrayObject,
     tile_buffer: WebGlBuffer,
     point_buffer: WebGlBuffer,
     line_buffer: WebGlBuffer,
     polygon_buffer: WebGlBuffer,
+    overlay_buffer: WebGlBuffer,
+}
+
+// Default tile cache budget: 256 standard tiles, or 64 at @2x
+const DEFAULT_TILE_CACHE_TILES: usize = 256;
+const DEFAULT_TILE_CACHE_BYTES: usize = 64 * 1024 * 1024;
+
+// Tile requests in flight at once, and attempts before a tile is given up on
+const DEFAULT_MAX_TILE_REQUESTS: usize = 6;
+const MAX_TILE_ATTEMPTS: u32 = 4;
+
+// Tiles a seed fetches at once, and the most one may cover
+const MAX_SEED_REQUESTS: usize = 4;
+const MAX_SEED_TILES: usize = 100_000;
+// Typical size of a stored tile, for estimates
+const ESTIMATED_TILE_BYTES: usize = 20 * 1024;
+
+// 1x1 transparent GIF; pointing an image at it aborts the pending download
+const EMPTY_IMAGE_URL: &str = "data:image/gif;base64,R0lGODlhAQABAAD/ACwAAAAAAQABAAACADs=";
+
+// State of an image overlay's texture. A load is cancelled by setting its
+// flag, when the overlay is removed or given another image; the texture of
+// the image being replaced is drawn until the load finishes.
+enum OverlayTexture {
+    Loading { cancelled: Rc<Cell<bool>>, previous: Option<WebGlTexture> },
+    Loaded(WebGlTexture),
+    Failed,
+}
+
+// What is loading a tile: an image for raster layers, a request for the
+// protobuf of vector tile layers, or a request to the layer's tile source or
+// the tile store, which is cancelled by setting its flag
+enum TileRequestHandle {
+    Image(HtmlImageElement),
+    Xhr(XmlHttpRequest),
+    Source(Rc<Cell<bool>>),
+}
+
+// A tile to fetch from a URL into the texture or vector tile cache
+struct TileDownload {
+    context: WebGl2RenderingContext,
+    tile_key: String,
+    coord: TileCoord,
+    projection_code: &'static str,
+    vector: bool,
+    terrain: Option<TerrainStyle>,
+    error_tile_url: Option<String>,
+    // Seeded tiles are taken from here rather than downloaded
+    store: Option<Rc<dyn TileStore>>,
+}
+
+impl TileDownload {
+    // Takes the tile from the store if it was seeded there, or downloads it
+    // from `url`, recording the request either way. Stores may answer at
+    // once, so the lookup is recorded as started first.
+    fn start(self, url: String) {
+        let Some(store) = self.store.clone() else {
+            return self.download(url);
+        };
+        let cancelled = Rc::new(Cell::new(false));
+        TILE_REQUESTS.with(|requests| requests.borrow_mut().started(self.tile_key.clone(), TileRequestHandle::Source(cancelled.clone())));
+        let stored_url = url.clone();
+        store.get(&stored_url, Box::new(move |stored| {
+            if cancelled.get() {
+                return;
+            }
+            match stored {
+                Ok(Some(data)) => {
+                    RustyleafMap::load_tile_bytes(&self.context, self.tile_key, self.coord, self.projection_code, self.vector, self.terrain, data)
+                }
+                _ => self.download(url),
+            }
+        }));
+    }
+
+    // Requests the tile from `url` and records what is loading it
+    fn download(self, url: String) {
+        let handle = if self.vector {
+            RustyleafMap::fetch_vector_tile(self.tile_key.clone(), self.coord, self.projection_code, url).map(TileRequestHandle::Xhr)
+        } else {
+            let image = RustyleafMap::load_tile_image(&self.context, self.tile_key.clone(), self.coord, &url, self.error_tile_url, self.terrain);
+            Some(TileRequestHandle::Image(image))
+        };
+        TILE_REQUESTS.with(|requests| match handle {
+            Some(handle) => requests.borrow_mut().started(self.tile_key, handle),
+            None => requests.borrow_mut().failed(&self.tile_key, js_sys::Date::now()),
+        });
+    }
+}
+
+// A decoded vector tile feature, its geometry in [lat, lng]
+struct VectorTileFeature {
+    source_layer: String,
+    id: Option<u64>,
+    properties: serde_json::Value,
+    geometry: mvt::Geometry,
+}
+
+// A vector tile's features as its layer's style draws them: fill triangles
+// grouped by color, then lines and outlines, then points
+struct VectorTileGeometry {
+    fills: Vec<([f32; 4], Vec<[f64; 2]>)>,
+    lines: Vec<LineFeature>,
+    points: Vec<PointFeature>,
 }
 
 thread_local! {
-    static TILE_TEXTURES: RefCell<HashMap<String, WebGlTexture>> = RefCell::new(HashMap::new());
+    static TILE_TEXTURES: RefCell<TileCache<WebGlTexture>> = RefCell::new(TileCache::new(DEFAULT_TILE_CACHE_TILES, DEFAULT_TILE_CACHE_BYTES));
+    // Vector tile layers' tiles, keyed like textures and held to the same budget
+    static VECTOR_TILES: RefCell<TileCache<Rc<Vec<VectorTileFeature>>>> = RefCell::new(TileCache::new(DEFAULT_TILE_CACHE_TILES, DEFAULT_TILE_CACHE_BYTES));
+    // Styled geometry of vector tiles drawn so far, kept for as long as their
+    // features and dropped when the layer's style or opacity changes
+    static VECTOR_TILE_GEOMETRY: RefCell<HashMap<String, Rc<VectorTileGeometry>>> = RefCell::new(HashMap::new());
+    // Heights of terrain layers' tiles, kept for as long as their textures
+    static ELEVATION_TILES: RefCell<HashMap<String, ElevationTile>> = RefCell::new(HashMap::new());
+    // Canvas terrain tiles are drawn on to read their pixels, made on first use
+    static TERRAIN_CANVAS: RefCell<Option<CanvasRenderingContext2d>> = const { RefCell::new(None) };
+    // Image overlays' textures by overlay id
+    static OVERLAY_TEXTURES: RefCell<HashMap<u32, OverlayTexture>> = RefCell::new(HashMap::new());
+    static TILE_REQUESTS: RefCell<TileScheduler<TileRequestHandle>> = RefCell::new(TileScheduler::new(DEFAULT_MAX_TILE_REQUESTS, MAX_TILE_ATTEMPTS));
     static SPATIAL_INDEX: RefCell<RTree<SpatialFeature>> = RefCell::new(RTree::new());
 }
 
@@ -95,15 +260,980 @@ struct MouseState {
     last_x: f64,
     last_y: f64,
     button_down: bool,
+    // Right- or modifier-drag turning the map instead of panning it
+    is_rotating: bool,
+}
+
+// Easing curve applied to camera animation progress
+#[derive(Clone)]
+enum Easing {
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+    Custom(js_sys::Function),
+}
+
+impl Easing {
+    fn from_js(value: &JsValue) -> Result<Easing, JsValue> {
+        if value.is_undefined() || value.is_null() {
+            return Ok(Easing::EaseInOut);
+        }
+        if let Some(function) = value.dyn_ref::<js_sys::Function>() {
+            return Ok(Easing::Custom(function.clone()));
+        }
+        match value.as_string().as_deref() {
+            Some("linear") => Ok(Easing::Linear),
+            Some("easeIn") => Ok(Easing::EaseIn),
+            Some("easeOut") => Ok(Easing::EaseOut),
+            Some("easeInOut") => Ok(Easing::EaseInOut),
+            _ => Err(JsValue::from_str("Easing must be 'linear', 'easeIn', 'easeOut', 'easeInOut' or a function")),
+        }
+    }
+
+    fn apply(&self, t: f64) -> f64 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOut => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    1.0 - (2.0 - 2.0 * t).powi(3) / 2.0
+                }
+            }
+            Easing::Custom(function) => function
+                .call1(&JsValue::null(), &JsValue::from_f64(t))
+                .ok()
+                .and_then(|value| value.as_f64())
+                .filter(|value| value.is_finite())
+                .unwrap_or(t),
+        }
+    }
+}
+
+#[derive(Clone)]
+enum CameraPath {
+    // Center and zoom interpolated independently
+    Ease,
+    // van Wijk & Nuij optimal path: zoom out, travel, zoom back in.
+    // `u1` is zero when only the zoom changes.
+    Fly { rho: f64, r0: f64, w0: f64, u1: f64, length: f64, zoom_direction: f64 },
+}
+
+// Camera transition stepped from the render loop
+#[derive(Clone)]
+struct CameraAnimation {
+    // Centers are world pixels at zoom 0
+    from: (f64, f64),
+    to: (f64, f64),
+    from_zoom: f64,
+    to_zoom: f64,
+    path: CameraPath,
+    start_time: f64,
+    duration: f64,
+    easing: Easing,
+}
+
+impl CameraAnimation {
+    const EASE_DURATION: f64 = 500.0;
+    // Path length travelled per second when fly_to picks its own duration
+    const FLY_SPEED: f64 = 1.2;
+    const FLY_CURVE: f64 = 1.42;
+
+    fn ease(from: (f64, f64), from_zoom: f64, to: (f64, f64), to_zoom: f64, start_time: f64, duration: Option<f64>, easing: Easing) -> CameraAnimation {
+        CameraAnimation {
+            from,
+            to,
+            from_zoom,
+            to_zoom,
+            path: CameraPath::Ease,
+            start_time,
+            duration: duration.unwrap_or(Self::EASE_DURATION),
+            easing,
+        }
+    }
+
+    /// `viewport` is the larger of the canvas dimensions in pixels
+    #[allow(clippy::too_many_arguments)]
+    fn fly(from: (f64, f64), from_zoom: f64, to: (f64, f64), to_zoom: f64, viewport: f64, start_time: f64, duration: Option<f64>, easing: Easing) -> CameraAnimation {
+        let rho = Self::FLY_CURVE;
+        let rho2 = rho * rho;
+        let w0 = viewport.max(1.0);
+        let w1 = w0 * 2f64.powf(from_zoom - to_zoom);
+        // Distance travelled, in pixels at the starting zoom
+        let u1 = (to.0 - from.0).hypot(to.1 - from.1) * 2f64.powf(from_zoom);
+
+        let r = |i: u8| {
+            let (w, sign) = if i == 0 { (w0, 1.0) } else { (w1, -1.0) };
+            let b = (w1 * w1 - w0 * w0 + sign * rho2 * rho2 * u1 * u1) / (2.0 * w * rho2 * u1);
+            ((b * b + 1.0).sqrt() - b).ln()
+        };
+        let r0 = r(0);
+        let length = (r(1) - r0) / rho;
+
+        // With no distance to cover the path degenerates into a pure zoom
+        let (path, length) = if u1 < 1e-6 || !length.is_finite() {
+            let length = (w1 / w0).ln().abs() / rho;
+            let zoom_direction = if w1 < w0 { -1.0 } else { 1.0 };
+            (CameraPath::Fly { rho, r0: 0.0, w0, u1: 0.0, length, zoom_direction }, length)
+        } else {
+            (CameraPath::Fly { rho, r0, w0, u1, length, zoom_direction: 0.0 }, length)
+        };
+
+        CameraAnimation {
+            from,
+            to,
+            from_zoom,
+            to_zoom,
+            path,
+            start_time,
+            duration: duration.unwrap_or(1000.0 * length / Self::FLY_SPEED),
+            easing,
+        }
+    }
+
+    /// Camera center (world pixels at zoom 0) and zoom at `now`, plus whether
+    /// the animation has finished
+    fn frame(&self, now: f64) -> ((f64, f64), f64, bool) {
+        let t = if self.duration > 0.0 {
+            ((now - self.start_time) / self.duration).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+        if t >= 1.0 {
+            return (self.to, self.to_zoom, true);
+        }
+
+        let k = self.easing.apply(t);
+        let (progress, zoom) = match self.path {
+            CameraPath::Ease => (k, self.from_zoom + (self.to_zoom - self.from_zoom) * k),
+            CameraPath::Fly { rho, r0, w0, u1, length, zoom_direction } => {
+                let s = k * length;
+                if u1 == 0.0 {
+                    let scale = (-zoom_direction * rho * s).exp();
+                    (k, self.from_zoom + scale.log2())
+                } else {
+                    let w = r0.cosh() / (r0 + rho * s).cosh();
+                    let u = w0 * ((r0.cosh() * (r0 + rho * s).tanh() - r0.sinh()) / (rho * rho)) / u1;
+                    (u, self.from_zoom - w.log2())
+                }
+            }
+        };
+
+        let center = (
+            self.from.0 + (self.to.0 - self.from.0) * progress,
+            self.from.1 + (self.to.1 - self.from.1) * progress,
+        );
+        (center, zoom, false)
+    }
+}
+
+// Parses a lat/lng box given as [sw_lat, sw_lng, ne_lat, ne_lng]; null or
+// undefined means no bounds. `name` prefixes the error messages.
+fn parse_bounds(bounds_data: &JsValue, name: &str) -> Result<Option<[f64; 4]>, JsValue> {
+    if bounds_data.is_null() || bounds_data.is_undefined() {
+        return Ok(None);
+    }
+
+    let bounds_array = js_sys::Array::from(bounds_data);
+    if bounds_array.length() != 4 {
+        return Err(JsValue::from_str(&format!("{} must be an array of [sw_lat, sw_lng, ne_lat, ne_lng]", name)));
+    }
+
+    let sw_lat = bounds_array.get(0).as_f64().ok_or_else(|| JsValue::from_str("sw_lat must be a number"))?;
+    let sw_lng = bounds_array.get(1).as_f64().ok_or_else(|| JsValue::from_str("sw_lng must be a number"))?;
+    let ne_lat = bounds_array.get(2).as_f64().ok_or_else(|| JsValue::from_str("ne_lat must be a number"))?;
+    let ne_lng = bounds_array.get(3).as_f64().ok_or_else(|| JsValue::from_str("ne_lng must be a number"))?;
+
+    if !(-90.0..=90.0).contains(&sw_lat) || !(-90.0..=90.0).contains(&ne_lat) {
+        return Err(JsValue::from_str(&format!("{} latitudes must be between -90 and 90", name)));
+    }
+    if !(-180.0..=180.0).contains(&sw_lng) || !(-180.0..=180.0).contains(&ne_lng) {
+        return Err(JsValue::from_str(&format!("{} longitudes must be between -180 and 180", name)));
+    }
+    if ne_lat <= sw_lat || ne_lng <= sw_lng {
+        return Err(JsValue::from_str(&format!("{} north-east corner must be north-east of the south-west corner", name)));
+    }
+
+    Ok(Some([sw_lat, sw_lng, ne_lat, ne_lng]))
+}
+
+// Reads WMS options `{ layers, styles, format, transparent, version, params }`;
+// only `layers` is required
+fn parse_wms_options(options: &JsValue) -> Result<WmsParams, JsValue> {
+    let get = |name: &str| js_sys::Reflect::get(options, &JsValue::from_str(name));
+    let layers = get("layers")?
+        .as_string()
+        .ok_or_else(|| JsValue::from_str("WMS layers must be a string of comma-separated layer names"))?;
+
+    let mut wms = WmsParams::new(&layers);
+    if let Some(styles) = get("styles")?.as_string() {
+        wms.styles = styles;
+    }
+    if let Some(format) = get("format")?.as_string() {
+        wms.format = format;
+    }
+    if let Some(transparent) = get("transparent")?.as_bool() {
+        wms.transparent = transparent;
+    }
+    if let Some(version) = get("version")?.as_string() {
+        wms.version = WmsVersion::parse(&version)
+            .ok_or_else(|| JsValue::from_str("WMS version must be \"1.1.1\" or \"1.3.0\""))?;
+    }
+    let params = get("params")?;
+    if params.is_object() {
+        for entry in js_sys::Object::entries(&js_sys::Object::from(params)).iter() {
+            let entry = js_sys::Array::from(&entry);
+            let key = entry.get(0).as_string().unwrap_or_default();
+            let value = entry.get(1);
+            let value = value
+                .as_string()
+                .or_else(|| value.as_f64().map(|v| v.to_string()))
+                .or_else(|| value.as_bool().map(|v| v.to_string()))
+                .ok_or_else(|| JsValue::from_str(&format!("WMS param {} must be a string, number or boolean", key)))?;
+            wms.extra.push((key, value));
+        }
+    }
+    Ok(wms)
+}
+
+// Reads raster adjustments `{ brightnessMin, brightnessMax, contrast,
+// saturation, grayscale, hueRotate, invert }`; missing values leave colors
+// unchanged, and `grayscale: true` stands for a saturation of -1
+fn parse_raster_adjustments(options: &JsValue) -> Result<RasterAdjustments, JsValue> {
+    let mut adjustments = RasterAdjustments::default();
+    if options.is_null() || options.is_undefined() {
+        return Ok(adjustments);
+    }
+    let get = |name: &str| js_sys::Reflect::get(options, &JsValue::from_str(name));
+    let number = |name: &str, range: std::ops::RangeInclusive<f32>| -> Result<Option<f32>, JsValue> {
+        let value = get(name)?;
+        if value.is_undefined() {
+            return Ok(None);
+        }
+        match value.as_f64().map(|value| value as f32) {
+            Some(value) if range.contains(&value) => Ok(Some(value)),
+            _ => Err(JsValue::from_str(&format!("{} must be a number between {} and {}", name, range.start(), range.end()))),
+        }
+    };
+
+    if let Some(value) = number("brightnessMin", 0.0..=1.0)? {
+        adjustments.brightness_min = value;
+    }
+    if let Some(value) = number("brightnessMax", 0.0..=1.0)? {
+        adjustments.brightness_max = value;
+    }
+    if let Some(value) = number("contrast", -1.0..=1.0)? {
+        adjustments.contrast = value;
+    }
+    if let Some(value) = number("saturation", -1.0..=1.0)? {
+        adjustments.saturation = value;
+    }
+    if get("grayscale")?.as_bool() == Some(true) {
+        adjustments.saturation = -1.0;
+    }
+    if let Some(value) = number("hueRotate", -360.0..=360.0)? {
+        adjustments.hue_rotate = value;
+    }
+    if let Some(invert) = get("invert")?.as_bool() {
+        adjustments.invert = invert;
+    }
+    Ok(adjustments)
+}
+
+// An image overlay's image: a URL or an ImageData
+fn parse_overlay_image(image: &JsValue) -> Result<OverlayImage, JsValue> {
+    if let Some(url) = image.as_string() {
+        return Ok(OverlayImage::Url(url));
+    }
+    image
+        .clone()
+        .dyn_into::<web_sys::ImageData>()
+        .map(OverlayImage::Data)
+        .map_err(|_| JsValue::from_str("Overlay image must be a URL or an ImageData"))
+}
+
+// An image overlay's corners from a lat/lng box [sw_lat, sw_lng, ne_lat,
+// ne_lng] or four [lat, lng] corners, top-left first and going clockwise
+fn parse_overlay_corners(corners: &JsValue) -> Result<[(f64, f64); 4], JsValue> {
+    let array = js_sys::Array::from(corners);
+    if array.length() == 4 && array.get(0).as_f64().is_some() {
+        let bounds = parse_bounds(corners, "Overlay bounds")?.ok_or_else(|| JsValue::from_str("Overlay bounds are required"))?;
+        return Ok(ImageOverlay::corners_from_bounds(bounds));
+    }
+    let corner = |index: u32| {
+        let pair = js_sys::Array::from(&array.get(index));
+        match (pair.get(0).as_f64(), pair.get(1).as_f64()) {
+            (Some(lat), Some(lng)) if (-90.0..=90.0).contains(&lat) => Ok((lat, lng)),
+            _ => Err(JsValue::from_str("Overlay corners must be [lat, lng] pairs with latitudes between -90 and 90")),
+        }
+    };
+    if array.length() != 4 {
+        return Err(JsValue::from_str("Overlay corners must be [sw_lat, sw_lng, ne_lat, ne_lng] or four [lat, lng] corners"));
+    }
+    Ok([corner(0)?, corner(1)?, corner(2)?, corner(3)?])
+}
+
+// Reads terrain layer options: `encoding`, "terrain-rgb" (the default) or
+// "terrarium", and the hillshading options of `parse_hillshade_style`
+fn parse_terrain_style(options: &JsValue) -> Result<TerrainStyle, JsValue> {
+    let encoding = if options.is_null() || options.is_undefined() {
+        None
+    } else {
+        js_sys::Reflect::get(options, &JsValue::from_str("encoding"))?.as_string()
+    };
+    let encoding = match encoding {
+        Some(name) => TerrainEncoding::parse(&name)
+            .ok_or_else(|| JsValue::from_str("Terrain encoding must be \"terrain-rgb\" or \"terrarium\""))?,
+        None => TerrainEncoding::TerrainRgb,
+    };
+    Ok(TerrainStyle { encoding, hillshade: parse_hillshade_style(options)? })
+}
+
+// Reads hillshading options `{ azimuth, altitude, exaggeration, colorRamp }`,
+// all optional; `colorRamp` is an array of [elevation, color] stops
+fn parse_hillshade_style(options: &JsValue) -> Result<HillshadeStyle, JsValue> {
+    let mut style = HillshadeStyle::default();
+    if options.is_null() || options.is_undefined() {
+        return Ok(style);
+    }
+    let get = |name: &str| js_sys::Reflect::get(options, &JsValue::from_str(name));
+
+    if let Some(azimuth) = get("azimuth")?.as_f64() {
+        style.azimuth = (azimuth as f32).rem_euclid(360.0);
+    }
+    if let Some(altitude) = get("altitude")?.as_f64() {
+        if !(1.0..=90.0).contains(&altitude) {
+            return Err(JsValue::from_str("Hillshade altitude must be between 1 and 90 degrees"));
+        }
+        style.altitude = altitude as f32;
+    }
+    if let Some(exaggeration) = get("exaggeration")?.as_f64() {
+        if !exaggeration.is_finite() || exaggeration < 0.0 {
+            return Err(JsValue::from_str("Hillshade exaggeration must be a non-negative number"));
+        }
+        style.exaggeration = exaggeration as f32;
+    }
+    let color_ramp = get("colorRamp")?;
+    if !color_ramp.is_null() && !color_ramp.is_undefined() {
+        for stop in js_sys::Array::from(&color_ramp).iter() {
+            let stop = js_sys::Array::from(&stop);
+            let (elevation, color) = (stop.get(0).as_f64(), stop.get(1).as_string());
+            let (Some(elevation), Some(color)) = (elevation, color) else {
+                return Err(JsValue::from_str("Color ramp stops must be [elevation, color] pairs"));
+            };
+            style.color_ramp.push((elevation as f32, parse_css_color(&color)));
+        }
+        style.color_ramp.sort_by(|a, b| a.0.total_cmp(&b.0));
+    }
+    Ok(style)
+}
+
+// Picks a layer from WMTS capabilities using the options `{ layer, style,
+// tileMatrixSet, format }`, all optional
+fn parse_wmts(capabilities: &str, options: &JsValue) -> Result<WmtsSource, JsValue> {
+    let mut wmts_options = WmtsOptions::default();
+    if options.is_object() {
+        let get = |name: &str| js_sys::Reflect::get(options, &JsValue::from_str(name)).map(|value| value.as_string());
+        wmts_options.layer = get("layer")?;
+        wmts_options.style = get("style")?;
+        wmts_options.tile_matrix_set = get("tileMatrixSet")?;
+        wmts_options.format = get("format")?;
+    }
+    WmtsSource::from_capabilities(capabilities, &wmts_options).map_err(|e| JsValue::from_str(&e))
+}
+
+// Vector tile style rules from JS; `null` or `undefined` draws everything in
+// the default style
+fn parse_vector_style(style: &JsValue) -> Result<Vec<VectorStyleRule>, JsValue> {
+    let style: serde_json::Value = if style.is_null() || style.is_undefined() {
+        serde_json::Value::Null
+    } else {
+        serde_wasm_bindgen::from_value(style.clone())?
+    };
+    parse_vector_tile_style(&style).map_err(|e| JsValue::from_str(&e))
+}
+
+// Reads archive ranges from a URL with HTTP range requests
+struct HttpRangeFetch {
+    url: String,
+}
+
+impl pmtiles::RangeFetch for HttpRangeFetch {
+    fn fetch(&self, offset: u64, length: u64, done: pmtiles::FetchCallback) {
+        let xhr = match XmlHttpRequest::new() {
+            Ok(xhr) => xhr,
+            Err(_) => return done(Err("Failed to create request".to_string())),
+        };
+        let range = format!("bytes={}-{}", offset, offset + length.max(1) - 1);
+        if xhr.open("GET", &self.url).and_then(|_| xhr.set_request_header("Range", &range)).is_err() {
+            return done(Err(format!("Failed to request {}", self.url)));
+        }
+        xhr.set_response_type(web_sys::XmlHttpRequestResponseType::Arraybuffer);
+
+        let xhr_clone = xhr.clone();
+        let url = self.url.clone();
+        let mut done = Some(done);
+        // Called on load and on network errors alike
+        let onload_closure = Closure::wrap(Box::new(move || {
+            let Some(done) = done.take() else {
+                return;
+            };
+            let data = || xhr_clone.response().map(|response| js_sys::Uint8Array::new(&response).to_vec()).unwrap_or_default();
+            match xhr_clone.status().unwrap_or(0) {
+                206 => done(Ok(data())),
+                // The server ignored the range and sent the whole archive
+                200 => {
+                    let data = data();
+                    let start = (offset as usize).min(data.len());
+                    let end = (offset.saturating_add(length) as usize).min(data.len());
+                    done(Ok(data[start..end].to_vec()))
+                }
+                status => done(Err(format!("HTTP {} for {}", status, url))),
+            }
+        }) as Box<dyn FnMut()>);
+        xhr.set_onload(Some(onload_closure.as_ref().unchecked_ref()));
+        xhr.set_onerror(Some(onload_closure.as_ref().unchecked_ref()));
+        onload_closure.forget();
+
+        let _ = xhr.send();
+    }
+}
+
+// Reads archive ranges with a JS function `(offset, length) => bytes`,
+// returning an ArrayBuffer or Uint8Array, or a Promise of one
+struct JsRangeFetch {
+    function: js_sys::Function,
+}
+
+impl pmtiles::RangeFetch for JsRangeFetch {
+    fn fetch(&self, offset: u64, length: u64, done: pmtiles::FetchCallback) {
+        let result = self.function.call2(&JsValue::NULL, &JsValue::from_f64(offset as f64), &JsValue::from_f64(length as f64));
+        when_settled(result, Box::new(move |value| {
+            done(value.map(|value| js_sys::Uint8Array::new(&value).to_vec()).map_err(|error| format!("Range fetch failed: {}", error)))
+        }));
+    }
+}
+
+// Calls `done` with what a JS call returned, once settled if it is a Promise
+fn when_settled(result: Result<JsValue, JsValue>, done: Box<dyn FnOnce(Result<JsValue, String>)>) {
+    let promise = match result {
+        Ok(value) => js_sys::Promise::resolve(&value),
+        Err(error) => return done(Err(format!("{:?}", error))),
+    };
+
+    let done = Rc::new(RefCell::new(Some(done)));
+    let done_clone = done.clone();
+    let on_fulfilled = Closure::wrap(Box::new(move |value: JsValue| {
+        if let Some(done) = done.borrow_mut().take() {
+            done(Ok(value));
+        }
+    }) as Box<dyn FnMut(JsValue)>);
+    let on_rejected = Closure::wrap(Box::new(move |error: JsValue| {
+        if let Some(done) = done_clone.borrow_mut().take() {
+            done(Err(format!("{:?}", error)));
+        }
+    }) as Box<dyn FnMut(JsValue)>);
+    let _ = promise.then2(&on_fulfilled, &on_rejected);
+    on_fulfilled.forget();
+    on_rejected.forget();
+}
+
+// A tile store implemented in JS: an object with `get(key)`, returning the
+// stored Uint8Array or undefined, and `put(key, bytes)`, either of which may
+// return a Promise
+struct JsTileStore {
+    store: JsValue,
+    get: js_sys::Function,
+    put: js_sys::Function,
+}
+
+impl TileStore for JsTileStore {
+    fn get(&self, key: &str, done: BytesCallback) {
+        let result = self.get.call1(&self.store, &JsValue::from_str(key));
+        when_settled(result, Box::new(move |value| {
+            done(value.map(|value| {
+                (!value.is_null() && !value.is_undefined()).then(|| js_sys::Uint8Array::new(&value).to_vec())
+            }))
+        }));
+    }
+
+    fn put(&self, key: &str, data: Vec<u8>, done: tile_store::PutCallback) {
+        let result = self.put.call2(&self.store, &JsValue::from_str(key), &js_sys::Uint8Array::from(data.as_slice()));
+        when_settled(result, Box::new(move |value| done(value.map(|_| ()))));
+    }
+}
+
+fn parse_tile_store(store: &JsValue) -> Result<Option<Rc<dyn TileStore>>, JsValue> {
+    if store.is_null() || store.is_undefined() {
+        return Ok(None);
+    }
+    // Keeps tiles for the session only, e.g. where IndexedDB is unavailable
+    if store.as_string().as_deref() == Some("memory") {
+        return Ok(Some(Rc::new(MemoryTileStore::default())));
+    }
+    let method = |name: &str| {
+        js_sys::Reflect::get(store, &JsValue::from_str(name))
+            .ok()
+            .and_then(|method| method.dyn_into::<js_sys::Function>().ok())
+            .ok_or_else(|| JsValue::from_str("Tile store must have get and put functions"))
+    };
+    Ok(Some(Rc::new(JsTileStore { store: store.clone(), get: method("get")?, put: method("put")? })))
+}
+
+// Downloads a tile for seeding; servers answer 404 for tiles they don't have
+fn fetch_tile_bytes(url: &str, done: BytesCallback) {
+    let xhr = match XmlHttpRequest::new() {
+        Ok(xhr) => xhr,
+        Err(_) => return done(Err("Failed to create request".to_string())),
+    };
+    if xhr.open("GET", url).is_err() {
+        return done(Err(format!("Failed to request {}", url)));
+    }
+    xhr.set_response_type(web_sys::XmlHttpRequestResponseType::Arraybuffer);
+
+    let xhr_clone = xhr.clone();
+    let url = url.to_string();
+    let mut done = Some(done);
+    // Called on load and on network errors alike
+    let onload_closure = Closure::wrap(Box::new(move || {
+        let Some(done) = done.take() else {
+            return;
+        };
+        match xhr_clone.status().unwrap_or(0) {
+            status if (200..300).contains(&status) => {
+                done(Ok(Some(xhr_clone.response().map(|response| js_sys::Uint8Array::new(&response).to_vec()).unwrap_or_default())))
+            }
+            404 => done(Ok(None)),
+            status => done(Err(format!("HTTP {} for {}", status, url))),
+        }
+    }) as Box<dyn FnMut()>);
+    xhr.set_onload(Some(onload_closure.as_ref().unchecked_ref()));
+    xhr.set_onerror(Some(onload_closure.as_ref().unchecked_ref()));
+    onload_closure.forget();
+
+    let _ = xhr.send();
+}
+
+// Seed progress as `{ total, stored, skipped, failed, bytes, finished,
+// cancelled }`
+fn seed_progress_to_js(progress: &SeedProgress) -> JsValue {
+    let obj = js_sys::Object::new();
+    for (name, value) in [
+        ("total", JsValue::from_f64(progress.total as f64)),
+        ("stored", JsValue::from_f64(progress.stored as f64)),
+        ("skipped", JsValue::from_f64(progress.skipped as f64)),
+        ("failed", JsValue::from_f64(progress.failed as f64)),
+        ("bytes", JsValue::from_f64(progress.bytes as f64)),
+        ("finished", JsValue::from_bool(progress.finished)),
+        ("cancelled", JsValue::from_bool(progress.cancelled)),
+    ] {
+        let _ = js_sys::Reflect::set(&obj, &JsValue::from_str(name), &value);
+    }
+    obj.into()
+}
+
+// Opens the PMTiles archive at `url`, read with `fetch` if that is a function
+// and with HTTP range requests if it is `null` or `undefined`
+fn open_pmtiles(url: &str, fetch: &JsValue) -> Result<pmtiles::Archive, JsValue> {
+    let fetch: Box<dyn pmtiles::RangeFetch> = if fetch.is_null() || fetch.is_undefined() {
+        Box::new(HttpRangeFetch { url: url.to_string() })
+    } else if let Some(function) = fetch.dyn_ref::<js_sys::Function>() {
+        Box::new(JsRangeFetch { function: function.clone() })
+    } else {
+        return Err(JsValue::from_str("PMTiles fetch must be a function"));
+    };
+    Ok(pmtiles::Archive::new(fetch))
+}
+
+// An in-memory source from the rows of an MBTiles `tiles` table, each `{
+// zoom_level, tile_column, tile_row, tile_data }` or an array in that order,
+// and the `metadata` table as an object of names to values
+fn parse_mbtiles(rows: &JsValue, metadata: &JsValue) -> Result<MemoryTileSource, JsValue> {
+    let column = |row: &JsValue, index: u32, name: &str| -> Result<JsValue, JsValue> {
+        if Array::is_array(row) {
+            Ok(Array::from(row).get(index))
+        } else {
+            js_sys::Reflect::get(row, &JsValue::from_str(name))
+        }
+    };
+    let number = |value: JsValue, name: &str| {
+        value
+            .as_f64()
+            .filter(|value| *value >= 0.0 && value.fract() == 0.0)
+            .map(|value| value as u32)
+            .ok_or_else(|| JsValue::from_str(&format!("MBTiles {} must be a non-negative integer", name)))
+    };
+    if !Array::is_array(rows) {
+        return Err(JsValue::from_str("MBTiles rows must be an array"));
+    }
+    let rows = Array::from(rows)
+        .iter()
+        .map(|row| {
+            let data = column(&row, 3, "tile_data")?;
+            if !data.is_instance_of::<js_sys::Uint8Array>() && !data.is_instance_of::<js_sys::ArrayBuffer>() {
+                return Err(JsValue::from_str("MBTiles tile_data must be a Uint8Array or ArrayBuffer"));
+            }
+            Ok((
+                number(column(&row, 0, "zoom_level")?, "zoom_level")?,
+                number(column(&row, 1, "tile_column")?, "tile_column")?,
+                number(column(&row, 2, "tile_row")?, "tile_row")?,
+                js_sys::Uint8Array::new(&data).to_vec(),
+            ))
+        })
+        .collect::<Result<Vec<_>, JsValue>>()?;
+
+    let mut values = std::collections::HashMap::new();
+    if metadata.is_object() {
+        for entry in js_sys::Object::entries(metadata.unchecked_ref()).iter() {
+            let entry = Array::from(&entry);
+            let value = entry.get(1);
+            let value = value.as_string().or_else(|| value.as_f64().map(|number| number.to_string()));
+            if let (Some(name), Some(value)) = (entry.get(0).as_string(), value) {
+                values.insert(name, value);
+            }
+        }
+    }
+    MemoryTileSource::from_mbtiles(rows, &values).map_err(|e| JsValue::from_str(&e))
+}
+
+// Features of a decoded vector tile, with the tile's unit square mapped onto
+// its place in the world
+fn vector_tile_features(layers: Vec<mvt::Layer>, coord: &TileCoord, projection: &dyn Projection) -> Vec<VectorTileFeature> {
+    let tiles_across = (1u64 << coord.z) as f64;
+    let to_lat_lng = |[x, y]: [f64; 2]| {
+        let (lat, lng) = projection.unproject((coord.x as f64 + x) / tiles_across, (coord.y as f64 + y) / tiles_across);
+        [lat, lng]
+    };
+    let mut features = Vec::new();
+    for layer in layers {
+        for feature in layer.features {
+            features.push(VectorTileFeature {
+                source_layer: layer.name.clone(),
+                id: feature.id,
+                properties: serde_json::Value::Object(feature.properties),
+                geometry: feature.geometry.map_vertices(to_lat_lng),
+            });
+        }
+    }
+    features
+}
+
+// Styles a vector tile's features with its layer's style and opacity
+fn vector_tile_geometry(layer: &TileLayer, features: &[VectorTileFeature]) -> VectorTileGeometry {
+    let with_opacity = |[r, g, b, a]: [f32; 4]| [r, g, b, a * layer.opacity];
+    // Fill triangles grouped by color, one draw call each
+    let mut fills: Vec<([f32; 4], Vec<[f64; 2]>)> = Vec::new();
+    let mut lines = Vec::new();
+    let mut points = Vec::new();
+    for feature in features {
+        let rule = match layer.vector_style(feature) {
+            Some(rule) => rule,
+            None => continue,
+        };
+        let line = |path: &Vec<[f64; 2]>, color: [f32; 4]| LineFeature {
+            points: path.clone(),
+            color: with_opacity(color),
+            width: rule.line_width,
+            meta: serde_json::Value::Null,
+        };
+        match &feature.geometry {
+            mvt::Geometry::Points(coords) => {
+                if let Some(color) = rule.point_color {
+                    points.extend(coords.iter().map(|&[lat, lng]| PointFeature {
+                        lat,
+                        lng,
+                        size: rule.point_size,
+                        color: with_opacity(color),
+                        meta: serde_json::Value::Null,
+                    }));
+                }
+            }
+            mvt::Geometry::Lines(paths) => {
+                if let Some(color) = rule.line_color {
+                    lines.extend(paths.iter().map(|path| line(path, color)));
+                }
+            }
+            mvt::Geometry::Polygons { triangles, outlines } => {
+                if let Some(color) = rule.polygon_color.map(with_opacity) {
+                    match fills.iter_mut().find(|(fill_color, _)| *fill_color == color) {
+                        Some((_, fill)) => fill.extend_from_slice(triangles),
+                        None => fills.push((color, triangles.clone())),
+                    }
+                }
+                if let Some(color) = rule.line_color {
+                    lines.extend(outlines.iter().map(|path| line(path, color)));
+                }
+            }
+        }
+    }
+
+    VectorTileGeometry { fills, lines, points }
+}
+
+/// Color adjustments applied to a raster layer's tiles as they are drawn
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RasterAdjustments {
+    // Output levels black and white are mapped to, 0 to 1
+    brightness_min: f32,
+    brightness_max: f32,
+    // -1 (flat grey) to 1, 0 leaving colors unchanged
+    contrast: f32,
+    // -1 (grayscale) to 1, 0 leaving colors unchanged
+    saturation: f32,
+    // Rotation of hues in degrees
+    hue_rotate: f32,
+    // Inverts lightness, keeping hues, for dark themes
+    invert: bool,
+}
+
+impl Default for RasterAdjustments {
+    fn default() -> Self {
+        RasterAdjustments {
+            brightness_min: 0.0,
+            brightness_max: 1.0,
+            contrast: 0.0,
+            saturation: 0.0,
+            hue_rotate: 0.0,
+            invert: false,
+        }
+    }
+}
+
+impl RasterAdjustments {
+    /// Fraction of the way colors move toward their grey level; negative
+    /// moves them away
+    fn saturation_factor(&self) -> f32 {
+        if self.saturation > 0.0 {
+            1.0 - 1.0 / (1.001 - self.saturation)
+        } else {
+            -self.saturation
+        }
+    }
+
+    /// Scale of colors around mid grey
+    fn contrast_factor(&self) -> f32 {
+        if self.contrast > 0.0 {
+            1.0 / (1.001 - self.contrast)
+        } else {
+            1.0 + self.contrast
+        }
+    }
+
+    /// Column-major matrix rotating colors about the grey axis by the hue
+    /// rotation; inverted colors are turned half way round to keep their hues
+    fn hue_matrix(&self) -> [f32; 9] {
+        let degrees = self.hue_rotate + if self.invert { 180.0 } else { 0.0 };
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let diagonal = cos + (1.0 - cos) / 3.0;
+        let ahead = (1.0 - cos) / 3.0 + sin / 3f32.sqrt();
+        let behind = (1.0 - cos) / 3.0 - sin / 3f32.sqrt();
+        [diagonal, ahead, behind, behind, diagonal, ahead, ahead, behind, diagonal]
+    }
+}
+
+// What an image overlay shows
+#[derive(Clone)]
+enum OverlayImage {
+    Url(String),
+    Data(web_sys::ImageData),
+}
+
+/// An image placed on the map by its corners, drawn with the tile layers
+#[derive(Clone)]
+pub struct ImageOverlay {
+    // Taken from the tile layers' ids, so the two stack in the order added
+    id: u32,
+    image: OverlayImage,
+    // (lat, lng) of the image's top-left, top-right, bottom-right and
+    // bottom-left corners
+    corners: [(f64, f64); 4],
+    opacity: f32,
+    visible: bool,
+    z_index: i32,
+}
+
+impl ImageOverlay {
+    /// Corners of an image covering a lat/lng box [sw_lat, sw_lng, ne_lat, ne_lng]
+    fn corners_from_bounds(bounds: [f64; 4]) -> [(f64, f64); 4] {
+        let [sw_lat, sw_lng, ne_lat, ne_lng] = bounds;
+        [(ne_lat, sw_lng), (ne_lat, ne_lng), (sw_lat, ne_lng), (sw_lat, sw_lng)]
+    }
+
+    /// Projective weights `q` of a quad's corners, given in the same order as
+    /// `corners`: texture coordinates scaled by them keep a quad that is not
+    /// a parallelogram from bending along its diagonal. All 1 if the
+    /// diagonals don't cross.
+    fn texture_weights(quad: [(f64, f64); 4]) -> [f64; 4] {
+        // Where the diagonal top-left to bottom-right crosses top-right to bottom-left
+        let [p0, p1, p2, p3] = quad;
+        let (d1, d2) = ((p2.0 - p0.0, p2.1 - p0.1), (p3.0 - p1.0, p3.1 - p1.1));
+        let denominator = d1.0 * d2.1 - d1.1 * d2.0;
+        if denominator.abs() < f64::EPSILON {
+            return [1.0; 4];
+        }
+        let t = ((p1.0 - p0.0) * d2.1 - (p1.1 - p0.1) * d2.0) / denominator;
+        let u = ((p1.0 - p0.0) * d1.1 - (p1.1 - p0.1) * d1.0) / denominator;
+        if !(0.0..=1.0).contains(&t) || !(0.0..=1.0).contains(&u) {
+            return [1.0; 4];
+        }
+        let crossing = (p0.0 + d1.0 * t, p0.1 + d1.1 * t);
+        let distance = |p: (f64, f64)| ((p.0 - crossing.0).powi(2) + (p.1 - crossing.1).powi(2)).sqrt();
+        let d = quad.map(distance);
+        let weight = |i: usize| {
+            let opposite = d[(i + 2) % 4];
+            if opposite > 0.0 { (d[i] + opposite) / opposite } else { 1.0 }
+        };
+        [weight(0), weight(1), weight(2), weight(3)]
+    }
+}
+
+// A layer drawn in the tile pass; tile layers and image overlays stack
+// together by z-index, then in the order they were added
+#[derive(Clone)]
+enum StackedLayer {
+    Tile(Box<TileLayer>),
+    Overlay(ImageOverlay),
 }
 
 // Layer types for the map
 #[derive(Clone)]
 pub struct TileLayer {
+    // Assigned by the map; namespaces the layer's tiles in the cache
+    id: u32,
     url_template: String,
     subdomains: Vec<String>,
     max_zoom: u32,
     min_zoom: u32,
+    opacity: f32,
+    // Color adjustments for raster tiles
+    adjustments: RasterAdjustments,
+    visible: bool,
+    // Layers with a higher z-index draw on top; ties keep insertion order
+    z_index: i32,
+    // Rows numbered from the south, as in TMS services
+    tms: bool,
+    // Size of the layer's tiles in CSS pixels, a power of two
+    tile_size: u32,
+    // Added to the zoom level in tile URLs
+    zoom_offset: i32,
+    // Shown in place of tiles that could not be loaded
+    error_tile_url: Option<String>,
+    // Coverage area [sw_lat, sw_lng, ne_lat, ne_lng]; nothing is requested outside it
+    bounds: Option<[f64; 4]>,
+    // Set for WMS layers, whose `url_template` is then the service URL
+    wms: Option<WmsParams>,
+    // Set for WMTS layers, which take their tile URLs from it
+    wmts: Option<WmtsSource>,
+    // Set for vector tile layers, whose tiles are drawn with these rules
+    vector: Option<Vec<VectorStyleRule>>,
+    // Set for terrain layers, whose tiles are decoded into heights and drawn
+    // hillshaded
+    terrain: Option<TerrainStyle>,
+    // Set for layers whose tiles come from a source other than their URL,
+    // such as a PMTiles archive; they are vector tile layers if the source
+    // holds vector tiles
+    source: Option<Rc<dyn TileSource>>,
+}
+
+impl TileLayer {
+    fn new(url_template: &str) -> TileLayer {
+        TileLayer {
+            id: 0,
+            url_template: url_template.to_string(),
+            subdomains: vec!["a".to_string(), "b".to_string(), "c".to_string()],
+            max_zoom: 18,
+            min_zoom: 0,
+            opacity: 1.0,
+            adjustments: RasterAdjustments::default(),
+            visible: true,
+            z_index: 0,
+            tms: false,
+            tile_size: 256,
+            zoom_offset: 0,
+            error_tile_url: None,
+            bounds: None,
+            wms: None,
+            wmts: None,
+            vector: None,
+            terrain: None,
+            source: None,
+        }
+    }
+
+    /// Makes this a WMTS layer, taking tile size, zoom range and coverage
+    /// from the chosen tile matrix set and layer
+    fn set_wmts(&mut self, wmts: WmtsSource) {
+        self.tile_size = wmts.tile_size;
+        let shift = self.zoom_shift();
+        // The coarsest level is also drawn, scaled down, at lower zooms
+        self.min_zoom = if wmts.min_level == 0 { 0 } else { (wmts.min_level as i32 + shift).max(0) as u32 };
+        self.max_zoom = (wmts.max_level() as i32 + shift).max(0) as u32;
+        if wmts.bounds.is_some() {
+            self.bounds = wmts.bounds;
+        }
+        self.wmts = Some(wmts);
+    }
+
+    /// Makes this a layer taking its tiles from `source`; vector tiles are
+    /// drawn in the default style until another is set
+    fn set_source(&mut self, source: Rc<dyn TileSource>) {
+        self.source = Some(source);
+        if self.vector.is_none() {
+            self.vector = Some(vec![VectorStyleRule::default()]);
+        }
+    }
+
+    // Whether the layer's tiles are vector tiles. Until a source knows its
+    // format, e.g. before an archive's header has been read, nothing is drawn.
+    fn is_vector(&self) -> bool {
+        let source_format = self.source.as_ref().map(|source| source.format());
+        self.vector.is_some() && source_format.is_none_or(|format| format == Some(TileFormat::Mvt))
+    }
+
+    // Coverage area, if the layer has one or its source gives one
+    fn coverage_bounds(&self) -> Option<[f64; 4]> {
+        self.bounds.or_else(|| self.source.as_ref()?.bounds())
+    }
+
+    // Style rule a vector tile feature is drawn with, if any
+    fn vector_style(&self, feature: &VectorTileFeature) -> Option<&VectorStyleRule> {
+        self.vector.as_ref()?.iter().find(|rule| rule.matches(feature))
+    }
+
+    // Whether the layer's tiles depend on the pixel ratio
+    fn uses_retina_tiles(&self) -> bool {
+        self.wms.is_some() || self.url_template.contains("{r}")
+    }
+
+    /// Levels by which the layer's tiles are coarser than 256px tiles at the
+    /// same zoom, e.g. 1 for 512px tiles
+    fn zoom_shift(&self) -> i32 {
+        self.tile_size.trailing_zeros() as i32 - 8
+    }
+
+    /// Map zooms the layer has tiles for: `min_zoom..=max_zoom`, narrowed to
+    /// the levels its source holds, where known
+    fn zoom_range(&self) -> (u32, u32) {
+        match self.source.as_ref().and_then(|source| source.zoom_range()) {
+            Some((min_level, max_level)) => {
+                let zoom = |level: u32| (level as i32 + self.zoom_shift()).max(0) as u32;
+                (self.min_zoom.max(zoom(min_level)), self.max_zoom.min(zoom(max_level)))
+            }
+            None => (self.min_zoom, self.max_zoom),
+        }
+    }
+
+    /// Tile levels (2^z tiles across the world) spanned by the layer's
+    /// `zoom_range`
+    fn tile_zoom_range(&self) -> (u32, u32) {
+        let level = |zoom: u32| (zoom as i32 - self.zoom_shift()).max(0) as u32;
+        let (min_zoom, max_zoom) = self.zoom_range();
+        (level(min_zoom), level(max_zoom))
+    }
 }
 
 #[derive(Clone)]
@@ -201,6 +1331,188 @@ impl Default for GeoJSONStyle {
     }
 }
 
+// Styling for vector tile features. The first rule whose source layer and
+// property filter match a feature decides how it is drawn; features no rule
+// matches, and geometries whose color the rule leaves unset, aren't drawn.
+#[derive(Clone, Debug, PartialEq)]
+struct VectorStyleRule {
+    // Vector tile layer the rule applies to; any layer if unset
+    source_layer: Option<String>,
+    // Property values a feature must have; an array allows any of its values
+    filter: serde_json::Map<String, serde_json::Value>,
+    point_color: Option<[f32; 4]>,
+    point_size: f32,
+    line_color: Option<[f32; 4]>,
+    line_width: f32,
+    polygon_color: Option<[f32; 4]>,
+}
+
+impl Default for VectorStyleRule {
+    // Draws everything in the default GeoJSON style
+    fn default() -> Self {
+        let style = GeoJSONStyle::default();
+        Self {
+            source_layer: None,
+            filter: serde_json::Map::new(),
+            point_color: Some(style.point_color),
+            point_size: style.point_size,
+            line_color: Some(style.line_color),
+            line_width: style.line_width,
+            polygon_color: Some(style.polygon_color),
+        }
+    }
+}
+
+impl VectorStyleRule {
+    fn matches(&self, feature: &VectorTileFeature) -> bool {
+        self.source_layer.as_ref().is_none_or(|layer| *layer == feature.source_layer)
+            && self.filter.iter().all(|(key, wanted)| {
+                let value = &feature.properties[key.as_str()];
+                match wanted {
+                    serde_json::Value::Array(options) => options.iter().any(|option| property_matches(option, value)),
+                    _ => property_matches(wanted, value),
+                }
+            })
+    }
+}
+
+// Numbers compare by value, so a filter's 2 matches a property decoded as 2.0
+fn property_matches(wanted: &serde_json::Value, value: &serde_json::Value) -> bool {
+    match (wanted.as_f64(), value.as_f64()) {
+        (Some(wanted), Some(value)) => wanted == value,
+        _ => wanted == value,
+    }
+}
+
+// Style rules from `[{ sourceLayer, filter, pointColor, pointSize, lineColor,
+// lineWidth, polygonColor }]`; without rules every feature is drawn in the
+// default GeoJSON style
+fn parse_vector_tile_style(style: &serde_json::Value) -> Result<Vec<VectorStyleRule>, String> {
+    let rules = match style {
+        serde_json::Value::Null => return Ok(vec![VectorStyleRule::default()]),
+        serde_json::Value::Array(rules) if rules.is_empty() => return Ok(vec![VectorStyleRule::default()]),
+        serde_json::Value::Array(rules) => rules,
+        _ => return Err("Vector tile style must be an array of rules".to_string()),
+    };
+    let defaults = VectorStyleRule::default();
+    rules
+        .iter()
+        .map(|rule| {
+            let rule = rule.as_object().ok_or("Vector tile style rules must be objects")?;
+            let color = |name: &str| rule.get(name).and_then(|c| c.as_str()).map(parse_css_color);
+            let number = |name: &str, default: f32| rule.get(name).and_then(|n| n.as_f64()).map_or(default, |n| n as f32);
+            let filter = match rule.get("filter") {
+                None | Some(serde_json::Value::Null) => serde_json::Map::new(),
+                Some(serde_json::Value::Object(filter)) => filter.clone(),
+                Some(_) => return Err("Vector tile style filter must be an object".to_string()),
+            };
+            Ok(VectorStyleRule {
+                source_layer: rule.get("sourceLayer").and_then(|l| l.as_str()).map(str::to_string),
+                filter,
+                point_color: color("pointColor"),
+                point_size: number("pointSize", defaults.point_size),
+                line_color: color("lineColor"),
+                line_width: number("lineWidth", defaults.line_width),
+                polygon_color: color("polygonColor"),
+            })
+        })
+        .collect()
+}
+
+// Appends a line segment as two triangles `width` CSS pixels wide, with
+// position and color per vertex. Screen coordinates are CSS pixels, so lines
+// keep their width at any pixel ratio, unlike `gl.LINES`, which is one device
+// pixel wide.
+fn push_line_segment(vertex_data: &mut Vec<f32>, start: (f64, f64), end: (f64, f64), width: f32, color: [f32; 4]) {
+    let length = (end.0 - start.0).hypot(end.1 - start.1);
+    if length == 0.0 {
+        return;
+    }
+    // Half the width along the segment's normal
+    let scale = width as f64 / 2.0 / length;
+    let (nx, ny) = (-(end.1 - start.1) * scale, (end.0 - start.0) * scale);
+    let corners = [(start.0 + nx, start.1 + ny), (start.0 - nx, start.1 - ny), (end.0 + nx, end.1 + ny), (end.0 - nx, end.1 - ny)];
+    for corner in [0, 1, 2, 2, 1, 3] {
+        let (x, y) = corners[corner];
+        vertex_data.extend_from_slice(&[x as f32, y as f32, color[0], color[1], color[2], color[3]]);
+    }
+}
+
+// Distance from `p` to the closest point of the segment from `start` to `end`
+fn distance_to_segment(p: [f64; 2], start: [f64; 2], end: [f64; 2]) -> f64 {
+    let (dx, dy) = (end[0] - start[0], end[1] - start[1]);
+    let length_squared = dx * dx + dy * dy;
+    let t = if length_squared == 0.0 { 0.0 } else { (((p[0] - start[0]) * dx + (p[1] - start[1]) * dy) / length_squared).clamp(0.0, 1.0) };
+    (p[0] - start[0] - t * dx).hypot(p[1] - start[1] - t * dy)
+}
+
+// Whether `p` lies inside (or on the edge of) the triangle `abc`
+fn point_in_triangle(p: [f64; 2], triangle: &[[f64; 2]]) -> bool {
+    let cross = |a: [f64; 2], b: [f64; 2]| (b[0] - a[0]) * (p[1] - a[1]) - (b[1] - a[1]) * (p[0] - a[0]);
+    let (d1, d2, d3) = (cross(triangle[0], triangle[1]), cross(triangle[1], triangle[2]), cross(triangle[2], triangle[0]));
+    let has_negative = d1 < 0.0 || d2 < 0.0 || d3 < 0.0;
+    let has_positive = d1 > 0.0 || d2 > 0.0 || d3 > 0.0;
+    !(has_negative && has_positive)
+}
+
+// Parse CSS color string to RGBA array
+fn parse_css_color(color_str: &str) -> [f32; 4] {
+    let s = color_str.trim().to_lowercase();
+    // Hex #RRGGBB or #RRGGBBAA
+    if let Some(stripped) = s.strip_prefix('#') {
+        if stripped.len() == 6 {
+            if let Ok(val) = u32::from_str_radix(stripped, 16) {
+                let r = ((val >> 16) & 0xff) as f32 / 255.0;
+                let g = ((val >> 8) & 0xff) as f32 / 255.0;
+                let b = (val & 0xff) as f32 / 255.0;
+                return [r, g, b, 1.0];
+            }
+        } else if stripped.len() == 8 {
+            if let Ok(val) = u32::from_str_radix(stripped, 16) {
+                let r = ((val >> 24) & 0xff) as f32 / 255.0;
+                let g = ((val >> 16) & 0xff) as f32 / 255.0;
+                let b = ((val >> 8) & 0xff) as f32 / 255.0;
+                let a = (val & 0xff) as f32 / 255.0;
+                return [r, g, b, a];
+            }
+        } else if stripped.len() == 3 {
+            // #RGB
+            let r = u8::from_str_radix(&stripped[0..1], 16).unwrap_or(0);
+            let g = u8::from_str_radix(&stripped[1..2], 16).unwrap_or(0);
+            let b = u8::from_str_radix(&stripped[2..3], 16).unwrap_or(0);
+            return [
+                (r as f32) / 15.0,
+                (g as f32) / 15.0,
+                (b as f32) / 15.0,
+                1.0,
+            ];
+        } else if stripped.len() == 4 {
+            // #RGBA
+            let r = u8::from_str_radix(&stripped[0..1], 16).unwrap_or(0);
+            let g = u8::from_str_radix(&stripped[1..2], 16).unwrap_or(0);
+            let b = u8::from_str_radix(&stripped[2..3], 16).unwrap_or(0);
+            let a = u8::from_str_radix(&stripped[3..4], 16).unwrap_or(15);
+            return [
+                (r as f32) / 15.0,
+                (g as f32) / 15.0,
+                (b as f32) / 15.0,
+                (a as f32) / 15.0,
+            ];
+        }
+    }
+    match s.as_str() {
+        "red" => [1.0, 0.0, 0.0, 1.0],
+        "green" => [0.0, 1.0, 0.0, 1.0],
+        "blue" => [0.0, 0.0, 1.0, 1.0],
+        "white" => [1.0, 1.0, 1.0, 1.0],
+        "black" => [0.0, 0.0, 0.0, 1.0],
+        "yellow" => [1.0, 1.0, 0.0, 1.0],
+        "magenta" => [1.0, 0.0, 1.0, 1.0],
+        "cyan" => [0.0, 1.0, 1.0, 1.0],
+        _ => [0.0, 0.0, 0.0, 1.0],
+    }
+}
+
 // WebGL support information for compatibility checking
 #[wasm_bindgen]
 pub struct WebGlSupportInfo {
@@ -305,19 +1617,44 @@ impl WebGlSupportInfo {
     }
 }
 
+// Steepest camera tilt; with the camera distance below the horizon never
+// comes into view, so the visible ground stays finite
+const MAX_PITCH: f64 = 60.0;
+
 #[wasm_bindgen]
 pub struct RustyleafMap {
     width: u32,
     height: u32,
+    // Device pixels per CSS pixel. Width, height and everything drawn are in
+    // CSS pixels; only the drawing buffer is scaled by this.
+    pixel_ratio: f64,
     center_lat: f64,
     center_lng: f64,
     zoom: f64,
+    // Compass direction at the top of the viewport, degrees clockwise from north
+    bearing: f64,
+    // Camera tilt away from straight down, in degrees
+    pitch: f64,
+    // Maps lat/lng onto the normalized world square; fixed at construction
+    projection: Box<dyn Projection>,
+    // Zoom constraints applied to every view change
+    min_zoom: f64,
+    max_zoom: f64,
+    // Region the viewport must stay inside: [sw_lat, sw_lng, ne_lat, ne_lng]
+    max_bounds: Option<[f64; 4]>,
+    // 0.0 lets drags overshoot max bounds freely, 1.0 makes the bounds solid
+    max_bounds_viscosity: f64,
+    // Repeat tiles and vector layers east and west of the canonical world
+    render_world_copies: bool,
+    // World-pixel x shift of the world copy currently being drawn
+    world_copy_offset: f64,
     canvas: Option<HtmlCanvasElement>,
     gl_state: Option<WebGlState>,
-    tiles: HashMap<String, Tile>,
     tile_size: u32,
-    requested: HashSet<String>,
-    tile_layer: Option<TileLayer>,
+    // Raster layers in insertion order
+    tile_layers: Vec<TileLayer>,
+    next_tile_layer_id: u32,
+    image_overlays: Vec<ImageOverlay>,
     point_layers: Vec<PointLayer>,
     line_layers: Vec<LineLayer>,
     polygon_layers: Vec<PolygonLayer>,
@@ -329,21 +1666,25 @@ pub struct RustyleafMap {
     drag_accumulated_x: f64,
     drag_accumulated_y: f64,
     has_momentum: bool,
+    camera_animation: Option<CameraAnimation>,
     animation_frame: Option<i32>,
     // Performance monitoring
     frame_count: u32,
     last_frame_time: f64,
     // Event callbacks - store as boxed functions to allow removal
-    move_callbacks: Vec<Box<js_sys::Function>>,
-    zoom_callbacks: Vec<Box<js_sys::Function>>,
-    click_callbacks: Vec<Box<js_sys::Function>>,
-    hover_callbacks: Vec<Box<js_sys::Function>>,
-    mousedown_callbacks: Vec<Box<js_sys::Function>>,
-    mouseup_callbacks: Vec<Box<js_sys::Function>>,
-    contextmenu_callbacks: Vec<Box<js_sys::Function>>,
-    keydown_callbacks: Vec<Box<js_sys::Function>>,
-    keyup_callbacks: Vec<Box<js_sys::Function>>,
-    dragend_callbacks: Vec<Box<js_sys::Function>>,
+    move_callbacks: Vec<js_sys::Function>,
+    zoom_callbacks: Vec<js_sys::Function>,
+    click_callbacks: Vec<js_sys::Function>,
+    hover_callbacks: Vec<js_sys::Function>,
+    mousedown_callbacks: Vec<js_sys::Function>,
+    mouseup_callbacks: Vec<js_sys::Function>,
+    contextmenu_callbacks: Vec<js_sys::Function>,
+    keydown_callbacks: Vec<js_sys::Function>,
+    keyup_callbacks: Vec<js_sys::Function>,
+    dragend_callbacks: Vec<js_sys::Function>,
+    moveend_callbacks: Vec<js_sys::Function>,
+    // Where tiles seeded for offline use are kept
+    tile_store: Option<Rc<dyn TileStore>>,
 }
 
 #[wasm_bindgen]
@@ -353,15 +1694,25 @@ impl RustyleafMap {
         RustyleafMap {
             width,
             height,
+            pixel_ratio: 1.0,
             center_lat: 48.8566,  // Paris latitude
             center_lng: 2.3522,  // Paris longitude
+            bearing: 0.0,
+            pitch: 0.0,
+            projection: Box::new(WebMercator),
             zoom: 2.0,
+            min_zoom: 0.0,
+            max_zoom: 18.0,
+            max_bounds: None,
+            max_bounds_viscosity: 1.0,
+            render_world_copies: false,
+            world_copy_offset: 0.0,
             canvas: None,
             gl_state: None,
-            tiles: HashMap::new(),
             tile_size: 256,
-            requested: HashSet::new(),
-            tile_layer: None,
+            tile_layers: Vec::new(),
+            next_tile_layer_id: 0,
+            image_overlays: Vec::new(),
             point_layers: Vec::new(),
             line_layers: Vec::new(),
             polygon_layers: Vec::new(),
@@ -371,12 +1722,14 @@ impl RustyleafMap {
                 last_x: 0.0,
                 last_y: 0.0,
                 button_down: false,
+                is_rotating: false,
             },
             drag_velocity: (0.0, 0.0),
             last_drag_time: 0.0,
             drag_accumulated_x: 0.0,
             drag_accumulated_y: 0.0,
             has_momentum: false,
+            camera_animation: None,
             animation_frame: None,
             frame_count: 0,
             last_frame_time: 0.0,
@@ -391,13 +1744,40 @@ impl RustyleafMap {
             keydown_callbacks: Vec::new(),
             keyup_callbacks: Vec::new(),
             dragend_callbacks: Vec::new(),
+            moveend_callbacks: Vec::new(),
+            tile_store: None,
         }
     }
 
+    /// Creates a map in the projection with the given EPSG code: "EPSG:3857"
+    /// (the default), "EPSG:4326", "EPSG:3413" (Arctic) or "EPSG:3031" (Antarctic).
+    #[wasm_bindgen]
+    pub fn with_projection(width: u32, height: u32, code: &str) -> Result<RustyleafMap, JsValue> {
+        let projection = projection_from_code(code)
+            .ok_or_else(|| JsValue::from_str(&format!("Unsupported projection: {}", code)))?;
+        let mut map = RustyleafMap::new(width, height);
+
+        // Polar projections don't reach the default center; start on the pole instead
+        if !projection.wraps() {
+            let (lat, lng) = projection.unproject(0.5, 0.5);
+            map.center_lat = lat;
+            map.center_lng = lng;
+        }
+        map.projection = projection;
+        Ok(map)
+    }
+
+    #[wasm_bindgen]
+    pub fn get_projection(&self) -> String {
+        self.projection.code().to_string()
+    }
+
     pub fn set_view(&mut self, lat: f64, lng: f64, zoom: f64) {
+        self.stop_camera_animation();
         self.center_lat = lat;
         self.center_lng = lng;
-        self.zoom = zoom;
+        self.zoom = self.clamp_zoom(zoom);
+        self.constrain_view();
         self.load_visible_tiles();
         self.schedule_render();
 
@@ -412,8 +1792,7 @@ impl RustyleafMap {
 
     fn apply_drag(&mut self, delta_x: f64, delta_y: f64) {
         // Convert pixel drag to lat/lng change
-        let zoom = self.zoom.round() as u32;
-        let meters_per_pixel = self.meters_per_pixel(zoom);
+        let meters_per_pixel = self.meters_per_pixel(self.zoom);
         
         // More accurate coordinate transformation
         let lat_change = delta_y * meters_per_pixel / 111000.0; // meters to degrees
@@ -430,10 +1809,9 @@ impl RustyleafMap {
         self.load_visible_tiles();
     }
     
-    fn meters_per_pixel(&self, zoom: u32) -> f64 {
+    fn meters_per_pixel(&self, zoom: f64) -> f64 {
         let circumference = 40075016.686; // Earth's circumference in meters
-        let height = 1u32 << zoom;
-        circumference / (height as f64 * self.tile_size as f64)
+        circumference / self.world_size(zoom)
     }
     
     fn apply_momentum(&mut self) {
@@ -472,7 +1850,15 @@ impl RustyleafMap {
 
         // Apply momentum movement with smooth deceleration
         if momentum_x.abs() > 0.05 || momentum_y.abs() > 0.05 {
-            self.pan(momentum_x, momentum_y);
+            let (applied_x, applied_y) = self.pan_by_pixels(momentum_x, momentum_y);
+
+            // Max bounds absorb the part of the fling they resisted
+            if momentum_x != 0.0 {
+                self.drag_velocity.0 *= (applied_x / momentum_x).clamp(0.0, 1.0);
+            }
+            if momentum_y != 0.0 {
+                self.drag_velocity.1 *= (applied_y / momentum_y).clamp(0.0, 1.0);
+            }
         }
 
         // Check if momentum should stop
@@ -499,66 +1885,16 @@ impl RustyleafMap {
     }
 
     fn cleanup_old_tiles(&mut self) {
-        let current_zoom = self.zoom.round() as i32;
-        let max_cache_size = 20; // Ultra aggressive cleanup for tiny memory footprint
-        
-        TILE_TEXTURES.with(|store| {
-            let mut textures = store.borrow_mut();
-            
-            // Remove tiles that are too far from current zoom or too old
-            let keys_to_remove: Vec<String> = textures.iter()
-                .filter(|(key, _)| {
-                    let parts: Vec<&str> = key.split('/').collect();
-                    if parts.len() == 3 {
-                        if let Ok(zoom) = parts[0].parse::<i32>() {
-                            // Remove tiles not at current zoom level only
-                            zoom != current_zoom
-                        } else {
-                            true // Remove malformed keys
-                        }
-                    } else {
-                        true // Remove malformed keys
-                    }
-                })
-                .map(|(key, _)| key.clone())
-                .collect();
-            
-            for key in keys_to_remove {
-                textures.remove(&key);
-            }
-            
-            // If still too many tiles, remove oldest ones
-            if textures.len() > max_cache_size {
-                let mut keys: Vec<String> = textures.keys().cloned().collect();
-                keys.truncate(max_cache_size);
-                
-                let to_remove: Vec<String> = textures.keys()
-                    .filter(|k| !keys.contains(k))
-                    .cloned()
-                    .collect();
-                
-                for key in to_remove {
-                    textures.remove(&key);
-                }
+        // Least recently used tiles go first; the ones on screen are pinned
+        let evicted = TILE_TEXTURES.with(|store| store.borrow_mut().evict());
+        for (key, texture) in evicted {
+            if let Some(ref gl_state) = self.gl_state {
+                gl_state.context.delete_texture(Some(&texture));
             }
-        });
-        
-        // Clean up requested set to prevent memory bloat
-        if self.requested.len() > 50 {
-            let current_zoom = self.zoom.round() as u32;
-            self.requested.retain(|key| {
-                let parts: Vec<&str> = key.split('/').collect();
-                if parts.len() == 3 {
-                    if let Ok(zoom) = parts[0].parse::<u32>() {
-                        // Only keep requests for current zoom level
-                        zoom == current_zoom
-                    } else {
-                        false
-                    }
-                } else {
-                    false
-                }
-            });
+            ELEVATION_TILES.with(|tiles| tiles.borrow_mut().remove(&key));
+        }
+        for (key, _) in VECTOR_TILES.with(|store| store.borrow_mut().evict()) {
+            VECTOR_TILE_GEOMETRY.with(|geometry| geometry.borrow_mut().remove(&key));
         }
     }
 
@@ -587,8 +1923,9 @@ impl RustyleafMap {
             .dyn_into::<HtmlCanvasElement>()
             .map_err(|_| JsValue::from_str("Element is not a canvas"))?;
 
-        canvas.set_width(self.width);
-        canvas.set_height(self.height);
+        let (buffer_width, buffer_height) = self.drawing_buffer_size();
+        canvas.set_width(buffer_width);
+        canvas.set_height(buffer_height);
 
         let context = canvas
             .get_context("webgl2")?
@@ -615,11 +1952,13 @@ impl RustyleafMap {
         let point_vao = context.create_vertex_array().ok_or_else(|| JsValue::from_str("Failed to create point VAO"))?;
         let line_vao = context.create_vertex_array().ok_or_else(|| JsValue::from_str("Failed to create line VAO"))?;
         let polygon_vao = context.create_vertex_array().ok_or_else(|| JsValue::from_str("Failed to create polygon VAO"))?;
+        let overlay_vao = context.create_vertex_array().ok_or_else(|| JsValue::from_str("Failed to create overlay VAO"))?;
 
         let tile_buffer = context.create_buffer().ok_or_else(|| JsValue::from_str("Failed to create tile buffer"))?;
         let point_buffer = context.create_buffer().ok_or_else(|| JsValue::from_str("Failed to create point buffer"))?;
         let line_buffer = context.create_buffer().ok_or_else(|| JsValue::from_str("Failed to create line buffer"))?;
         let polygon_buffer = context.create_buffer().ok_or_else(|| JsValue::from_str("Failed to create polygon buffer"))?;
+        let overlay_buffer = context.create_buffer().ok_or_else(|| JsValue::from_str("Failed to create overlay buffer"))?;
 
         // Setup tile VAO with fixed attribute indices (matched via bind_attrib_location)
         context.bind_vertex_array(Some(&tile_vao));
@@ -631,6 +1970,15 @@ impl RustyleafMap {
         context.vertex_attrib_pointer_with_i32(pos_loc, 2, WebGl2RenderingContext::FLOAT, false, 16, 0);
         context.vertex_attrib_pointer_with_i32(tex_loc, 2, WebGl2RenderingContext::FLOAT, false, 16, 8);
 
+        // Setup overlay VAO: position and projective texture coordinates,
+        // stride = 24 bytes (6 floats: 2+4)
+        context.bind_vertex_array(Some(&overlay_vao));
+        context.bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, Some(&overlay_buffer));
+        context.enable_vertex_attrib_array(pos_loc);
+        context.enable_vertex_attrib_array(tex_loc);
+        context.vertex_attrib_pointer_with_i32(pos_loc, 2, WebGl2RenderingContext::FLOAT, false, 24, 0);
+        context.vertex_attrib_pointer_with_i32(tex_loc, 4, WebGl2RenderingContext::FLOAT, false, 24, 8);
+
         // Setup point VAO with fixed attribute indices (matched via bind_attrib_location)
         context.bind_vertex_array(Some(&point_vao));
         context.bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, Some(&point_buffer));
@@ -649,10 +1997,12 @@ impl RustyleafMap {
             point_vao,
             line_vao,
             polygon_vao,
+            overlay_vao,
             tile_buffer,
             point_buffer,
             line_buffer,
             polygon_buffer,
+            overlay_buffer,
         });
 
         Ok(())
@@ -665,9 +2015,11 @@ impl RustyleafMap {
             WebGl2RenderingContext::VERTEX_SHADER,
             r#"
             attribute vec2 a_position;
-            attribute vec2 a_texCoord;
+            // Tiles give (u, v), taken as (u, v, 0, 1); image overlays give
+            // (u * q, v * q, 0, q)
+            attribute vec4 a_texCoord;
             uniform mat4 u_matrix;
-            varying vec2 v_texCoord;
+            varying vec4 v_texCoord;
 
             void main() {
                 vec4 position = u_matrix * vec4(a_position, 0.0, 1.0);
@@ -683,10 +2035,23 @@ impl RustyleafMap {
             r#"
             precision mediump float;
             uniform sampler2D u_texture;
-            varying vec2 v_texCoord;
+            uniform float u_opacity;
+            uniform float u_invert;
+            uniform mat3 u_hue_matrix;
+            uniform float u_saturation_factor;
+            uniform float u_contrast_factor;
+            uniform vec2 u_brightness;
+            varying vec4 v_texCoord;
 
             void main() {
-                gl_FragColor = texture2D(u_texture, v_texCoord);
+                vec4 color = texture2D(u_texture, v_texCoord.xy / v_texCoord.w);
+                vec3 rgb = mix(color.rgb, 1.0 - color.rgb, u_invert);
+                rgb = clamp(u_hue_matrix * rgb, 0.0, 1.0);
+                float average = (rgb.r + rgb.g + rgb.b) / 3.0;
+                rgb += (average - rgb) * u_saturation_factor;
+                rgb = clamp((rgb - 0.5) * u_contrast_factor + 0.5, 0.0, 1.0);
+                rgb = mix(vec3(u_brightness.x), vec3(u_brightness.y), rgb);
+                gl_FragColor = vec4(rgb, color.a * u_opacity);
             }
             "#,
         )?;
@@ -707,11 +2072,13 @@ impl RustyleafMap {
             attribute float a_size;
             attribute vec4 a_color;
             uniform mat4 u_matrix;
+            uniform float u_pixel_ratio;
             varying vec4 v_color;
 
             void main() {
                 gl_Position = u_matrix * vec4(a_position, 0.0, 1.0);
-                gl_PointSize = a_size;
+                // Sizes are in CSS pixels; gl_PointSize is in device pixels
+                gl_PointSize = a_size * u_pixel_ratio;
                 v_color = a_color;
             }
             "#,
@@ -884,6 +2251,11 @@ impl RustyleafMap {
             return Ok(());
         };
 
+        // Advance a running fly_to/ease_to before anything else moves the view
+        if self.camera_animation.is_some() {
+            self.step_camera_animation(js_sys::Date::now());
+        }
+
         // Apply momentum if active
         if self.has_momentum {
             self.apply_momentum();
@@ -893,57 +2265,108 @@ impl RustyleafMap {
                 self.has_momentum = false;
                 self.drag_velocity = (0.0, 0.0);
             }
+        } else if self.max_bounds.is_some() && !self.mouse_state.is_dragging && self.camera_animation.is_none() {
+            self.relax_into_max_bounds();
+        }
+
+        // Clear the canvas
+        context.clear_color(0.9, 0.9, 0.9, 1.0); // Light gray background
+        context.clear(WebGl2RenderingContext::COLOR_BUFFER_BIT);
+
+        // Set viewport to the full drawing buffer
+        let (buffer_width, buffer_height) = self.drawing_buffer_size();
+        context.viewport(0, 0, buffer_width as i32, buffer_height as i32);
+
+        // Update spatial index for hit-testing
+        self.update_spatial_index();
+
+        // Render tiles, then points, lines, polygons, and GeoJSON once per visible world copy
+        self.render_tiles(&context)?;
+        for offset in self.world_copy_offsets() {
+            self.world_copy_offset = offset;
+            let result = self.render_points(&context)
+                .and_then(|_| self.render_lines(&context))
+                .and_then(|_| self.render_polygons(&context))
+                .and_then(|_| self.render_geojson(&context));
+            self.world_copy_offset = 0.0;
+            result?;
+        }
+
+        Ok(())
+    }
+
+    fn render_tiles(&mut self, context: &WebGl2RenderingContext) -> Result<(), JsValue> {
+        if (self.tile_layers.is_empty() && self.image_overlays.is_empty()) || self.gl_state.is_none() {
+            return Ok(());
+        }
+
+        // Enable blending for transparent tiles
+        context.enable(WebGl2RenderingContext::BLEND);
+        context.blend_func(WebGl2RenderingContext::SRC_ALPHA, WebGl2RenderingContext::ONE_MINUS_SRC_ALPHA);
+
+        let mut tiles_to_load = Vec::new();
+        // Visible tiles and everything drawn in their place stay cached
+        let mut pinned = HashSet::new();
+        // Layers are drawn bottom to top, each from within its own zoom
+        // range; nothing is drawn below a layer's minimum zoom
+        for stacked in self.stacked_layers() {
+            let layer = match stacked {
+                StackedLayer::Tile(layer) => layer,
+                StackedLayer::Overlay(overlay) => {
+                    if overlay.visible && overlay.opacity > 0.0 {
+                        self.render_image_overlay(context, &overlay)?;
+                    }
+                    continue;
+                }
+            };
+            let layer = &layer;
+            if !layer.visible || layer.opacity <= 0.0 || self.layer_tile_zoom_and_scale(layer).is_none() {
+                continue;
+            }
+            let visible_tiles = self.layer_covering_tiles(layer);
+            if layer.is_vector() {
+                self.render_vector_tile_layer(context, layer, &visible_tiles, &mut pinned, &mut tiles_to_load)?;
+            } else {
+                self.render_raster_tile_layer(context, layer, &visible_tiles, &mut pinned, &mut tiles_to_load);
+            }
         }
+        TILE_TEXTURES.with(|store| store.borrow_mut().set_pinned(pinned.clone()));
+        VECTOR_TILES.with(|store| store.borrow_mut().set_pinned(pinned));
 
-        // Clear the canvas
-        context.clear_color(0.9, 0.9, 0.9, 1.0); // Light gray background
-        context.clear(WebGl2RenderingContext::COLOR_BUFFER_BIT);
-
-        // Set viewport
-        context.viewport(0, 0, self.width as i32, self.height as i32);
+        // Load tiles that were missing
+        self.request_tiles(tiles_to_load);
 
-        // Update spatial index for hit-testing
-        self.update_spatial_index();
+        // Bring the cache back within budget
+        self.cleanup_old_tiles();
 
-        // Render tiles, points, lines, polygons, and GeoJSON
-        self.render_tiles(&context)?;
-        self.render_points(&context)?;
-        self.render_lines(&context)?;
-        self.render_polygons(&context)?;
-        self.render_geojson(&context)?;
+        // Disable blending when done
+        context.disable(WebGl2RenderingContext::BLEND);
 
         Ok(())
     }
 
-    fn render_tiles(&mut self, context: &WebGl2RenderingContext) -> Result<(), JsValue> {
-        if self.tile_layer.is_none() {
-            return Ok(());
-        }
-
+    // Draws a raster layer's visible tiles, standing in cached neighbours
+    // from other levels for missing ones, which are queued for loading
+    fn render_raster_tile_layer(
+        &self,
+        context: &WebGl2RenderingContext,
+        layer: &TileLayer,
+        visible_tiles: &[(TileCoord, i32)],
+        pinned: &mut HashSet<String>,
+        tiles_to_load: &mut Vec<(String, (u32, TileCoord), f64)>,
+    ) {
         if let Some(ref gl_state) = self.gl_state {
-            let tile_zoom = self.zoom.round() as u32;
-            
-            let center_pixel = self.lat_lng_to_pixel(self.center_lat, self.center_lng, tile_zoom);
-            
-            let start_x = center_pixel.0 - (self.width as f64 / 2.0);
-            let start_y = center_pixel.1 - (self.height as f64 / 2.0);
+            let tile_size = self.tile_size as f64;
 
-            let start_tile_x = (start_x / self.tile_size as f64).floor() as i32;
-            let start_tile_y = (start_y / self.tile_size as f64).floor() as i32;
-            let tiles_x = (self.width as f64 / self.tile_size as f64).ceil() as i32 + 2;
-            let tiles_y = (self.height as f64 / self.tile_size as f64).ceil() as i32 + 2;
+            let center_pixel = self.lat_lng_to_pixel(self.center_lat, self.center_lng, self.zoom);
 
             // Use tile shader program
             context.use_program(Some(&gl_state.programs.tile_program));
             context.bind_vertex_array(Some(&gl_state.tile_vao));
 
-            // Set up projection matrix - orthographic projection for 2D tiles
-            let projection_matrix = [
-                2.0 / self.width as f32, 0.0, 0.0, 0.0,
-                0.0, -2.0 / self.height as f32, 0.0, 0.0,
-                0.0, 0.0, -1.0, 0.0,
-                -1.0, 1.0, 0.0, 1.0,
-            ];
+            // Tiles are positioned relative to the map center; the camera
+            // matrix applies bearing and pitch and maps the viewport to clip space
+            let projection_matrix = self.camera_matrix();
             let u_matrix: Option<WebGlUniformLocation> = context.get_uniform_location(&gl_state.programs.tile_program, "u_matrix");
             if let Some(loc) = u_matrix.as_ref() {
                 context.uniform_matrix4fv_with_f32_array(Some(loc), false, &projection_matrix);
@@ -954,102 +2377,345 @@ impl RustyleafMap {
             if let Some(loc) = u_texture.as_ref() {
                 context.uniform1i(Some(loc), 0); // Use texture unit 0
             }
+            Self::set_raster_uniforms(context, &gl_state.programs.tile_program, layer.opacity, &layer.adjustments);
 
-            // Enable blending for transparent tiles
-            context.enable(WebGl2RenderingContext::BLEND);
-            context.blend_func(WebGl2RenderingContext::SRC_ALPHA, WebGl2RenderingContext::ONE_MINUS_SRC_ALPHA);
-
-            let mut tiles_rendered = 0;
-            let mut tiles_found = 0;
-            let mut tiles_to_load = Vec::new();
             TILE_TEXTURES.with(|store| {
-                for i in 0..tiles_x {
-                    for j in 0..tiles_y {
-                        let tile_x = start_tile_x + i;
-                        let tile_y = start_tile_y + j;
-
-                        if tile_x >= 0 && tile_y >= 0 && tile_x < (1 << tile_zoom) && tile_y < (1 << tile_zoom) {
-                            let key = format!("{}/{}/{}", tile_zoom, tile_x, tile_y);
-                            let pixel_x = (tile_x * self.tile_size as i32) as f64 - start_x;
-                            let pixel_y = (tile_y * self.tile_size as i32) as f64 - start_y;
-
-                            if let Some(texture) = store.borrow().get(&key) {
-                                tiles_found += 1;
-                                
-                                // Bind texture
-                                context.active_texture(WebGl2RenderingContext::TEXTURE0);
-                                context.bind_texture(WebGl2RenderingContext::TEXTURE_2D, Some(texture));
-
-                                // Create quad vertices for this tile in pixel coordinates
-                                let vertices = Float32Array::new_with_length(16);
-
-                                // Pixel coordinates (0,0 at top-left)
-                                let x0 = pixel_x as f32;
-                                let y0 = pixel_y as f32;
-                                let x1 = (pixel_x + self.tile_size as f64) as f32;
-                                let y1 = (pixel_y + self.tile_size as f64) as f32;
-
-                                // Top-left
-                                vertices.set_index(0, x0);
-                                vertices.set_index(1, y0);
-                                vertices.set_index(2, 0.0);
-                                vertices.set_index(3, 0.0);
-
-                                // Bottom-left
-                                vertices.set_index(4, x0);
-                                vertices.set_index(5, y1);
-                                vertices.set_index(6, 0.0);
-                                vertices.set_index(7, 1.0);
-
-                                // Top-right
-                                vertices.set_index(8, x1);
-                                vertices.set_index(9, y0);
-                                vertices.set_index(10, 1.0);
-                                vertices.set_index(11, 0.0);
-
-                                // Bottom-right
-                                vertices.set_index(12, x1);
-                                vertices.set_index(13, y1);
-                                vertices.set_index(14, 1.0);
-                                vertices.set_index(15, 1.0);
-
-                                context.bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, Some(&gl_state.tile_buffer));
-                                context.buffer_data_with_array_buffer_view(
-                                    WebGl2RenderingContext::ARRAY_BUFFER,
-                                    &vertices,
-                                    WebGl2RenderingContext::DYNAMIC_DRAW,
-                                );
-
-                                // Draw the tile
-                                context.draw_arrays(WebGl2RenderingContext::TRIANGLE_STRIP, 0, 4);
-                                tiles_rendered += 1;
-                            } else {
-                                // Request tile if not yet in cache - force immediate load
-                                let tile_coord = TileCoord { x: tile_x, y: tile_y, z: tile_zoom };
-                                let should_load = !self.requested.contains(&key);
-                                if should_load {
-                                    tiles_to_load.push((key.clone(), tile_coord));
-                                }
-                            }
-                        }
+                let mut textures = store.borrow_mut();
+                for &(ref tile, wrapped_x) in visible_tiles {
+                    // Copies of the world reuse the canonical tile's texture
+                    let tile_coord = TileCoord { x: wrapped_x, y: tile.y, z: tile.z };
+                    let key = tile_coord.key(layer.id);
+                    let cached = textures.get(&key).is_some();
+
+                    // Until a tile arrives, cached ancestors and children stand in for it
+                    let draws = Self::tile_fallbacks(layer, tile, wrapped_x, |key| textures.contains(key));
+                    for draw in draws {
+                        let texture = match textures.peek(&draw.key) {
+                            Some(texture) => texture,
+                            None => continue,
+                        };
+
+                        // Bind texture
+                        context.active_texture(WebGl2RenderingContext::TEXTURE0);
+                        context.bind_texture(WebGl2RenderingContext::TEXTURE_2D, Some(texture));
+
+                        // Tiles toward the horizon and fallbacks from other
+                        // levels are scaled from their own zoom
+                        let region = &draw.region;
+                        let scale = 2f64.powf(self.zoom - region.z as f64);
+                        let pixel_x = region.x as f64 * tile_size * scale - center_pixel.0;
+                        let pixel_y = region.y as f64 * tile_size * scale - center_pixel.1;
+
+                        // Create quad vertices for this tile in pixel coordinates
+                        let vertices = Float32Array::new_with_length(16);
+
+                        // Pixel offsets from the map center
+                        let x0 = pixel_x as f32;
+                        let y0 = pixel_y as f32;
+                        let x1 = (pixel_x + tile_size * scale) as f32;
+                        let y1 = (pixel_y + tile_size * scale) as f32;
+                        let [u0, v0, u1, v1] = draw.uv;
+
+                        // Top-left
+                        vertices.set_index(0, x0);
+                        vertices.set_index(1, y0);
+                        vertices.set_index(2, u0);
+                        vertices.set_index(3, v0);
+
+                        // Bottom-left
+                        vertices.set_index(4, x0);
+                        vertices.set_index(5, y1);
+                        vertices.set_index(6, u0);
+                        vertices.set_index(7, v1);
+
+                        // Top-right
+                        vertices.set_index(8, x1);
+                        vertices.set_index(9, y0);
+                        vertices.set_index(10, u1);
+                        vertices.set_index(11, v0);
+
+                        // Bottom-right
+                        vertices.set_index(12, x1);
+                        vertices.set_index(13, y1);
+                        vertices.set_index(14, u1);
+                        vertices.set_index(15, v1);
+
+                        context.bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, Some(&gl_state.tile_buffer));
+                        context.buffer_data_with_array_buffer_view(
+                            WebGl2RenderingContext::ARRAY_BUFFER,
+                            &vertices,
+                            WebGl2RenderingContext::DYNAMIC_DRAW,
+                        );
+
+                        // Draw the tile
+                        context.draw_arrays(WebGl2RenderingContext::TRIANGLE_STRIP, 0, 4);
+                        pinned.insert(draw.key);
+                    }
+
+                    // Request tile if not yet in cache, nearest the center first
+                    if !cached && !tiles_to_load.iter().any(|(queued, _, _)| queued == &key) {
+                        tiles_to_load.push((key.clone(), (layer.id, tile_coord), self.tile_priority(tile)));
                     }
+                    pinned.insert(key);
                 }
             });
-            
-            if tiles_rendered == 0 {
-                // No tiles rendered - could indicate various issues
+        }
+    }
+
+    // Sets the tile program's opacity and color adjustments
+    fn set_raster_uniforms(context: &WebGl2RenderingContext, program: &WebGlProgram, opacity: f32, adjustments: &RasterAdjustments) {
+        let uniform = |name: &str| context.get_uniform_location(program, name);
+        context.uniform1f(uniform("u_opacity").as_ref(), opacity);
+        context.uniform1f(uniform("u_invert").as_ref(), if adjustments.invert { 1.0 } else { 0.0 });
+        context.uniform_matrix3fv_with_f32_array(uniform("u_hue_matrix").as_ref(), false, &adjustments.hue_matrix());
+        context.uniform1f(uniform("u_saturation_factor").as_ref(), adjustments.saturation_factor());
+        context.uniform1f(uniform("u_contrast_factor").as_ref(), adjustments.contrast_factor());
+        context.uniform2f(uniform("u_brightness").as_ref(), adjustments.brightness_min, adjustments.brightness_max);
+    }
+
+    // Draws an image overlay through the tile program, once per world copy.
+    // Its texture is loaded the first time it is drawn.
+    fn render_image_overlay(&self, context: &WebGl2RenderingContext, overlay: &ImageOverlay) -> Result<(), JsValue> {
+        let Some(ref gl_state) = self.gl_state else {
+            return Ok(());
+        };
+        let texture = match OVERLAY_TEXTURES.with(|textures| {
+            textures.borrow().get(&overlay.id).map(|texture| match texture {
+                OverlayTexture::Loaded(texture) => Some(texture.clone()),
+                OverlayTexture::Loading { previous, .. } => previous.clone(),
+                OverlayTexture::Failed => None,
+            })
+        }) {
+            Some(Some(texture)) => texture,
+            Some(None) => return Ok(()),
+            None => return Self::load_overlay_texture(context, overlay, None),
+        };
+
+        let program = &gl_state.programs.tile_program;
+        context.use_program(Some(program));
+        context.bind_vertex_array(Some(&gl_state.overlay_vao));
+        context.uniform_matrix4fv_with_f32_array(context.get_uniform_location(program, "u_matrix").as_ref(), false, &self.camera_matrix());
+        context.uniform1i(context.get_uniform_location(program, "u_texture").as_ref(), 0);
+        Self::set_raster_uniforms(context, program, overlay.opacity, &RasterAdjustments::default());
+        context.active_texture(WebGl2RenderingContext::TEXTURE0);
+        context.bind_texture(WebGl2RenderingContext::TEXTURE_2D, Some(&texture));
+
+        // Corners in pixels from the map center
+        let center_pixel = self.lat_lng_to_pixel(self.center_lat, self.center_lng, self.zoom);
+        let quad = overlay.corners.map(|(lat, lng)| {
+            let (x, y) = self.lat_lng_to_pixel(lat, lng, self.zoom);
+            (x - center_pixel.0, y - center_pixel.1)
+        });
+        let weights = ImageOverlay::texture_weights(quad);
+        let uv = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)];
+
+        for offset in self.world_copy_offsets() {
+            // Top-left, bottom-left, top-right, bottom-right, as for tiles
+            let vertices = Float32Array::new_with_length(24);
+            for (slot, corner) in [0, 3, 1, 2].into_iter().enumerate() {
+                let (x, y) = quad[corner];
+                let (u, v) = uv[corner];
+                let q = weights[corner];
+                for (i, value) in [x + offset, y, u * q, v * q, 0.0, q].into_iter().enumerate() {
+                    vertices.set_index((slot * 6 + i) as u32, value as f32);
+                }
             }
-            
-            // Load tiles that were missing
-            for (key, tile_coord) in tiles_to_load {
-                self.requested.insert(key);
-                self.load_tile(tile_coord);
+            context.bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, Some(&gl_state.overlay_buffer));
+            context.buffer_data_with_array_buffer_view(WebGl2RenderingContext::ARRAY_BUFFER, &vertices, WebGl2RenderingContext::DYNAMIC_DRAW);
+            context.draw_arrays(WebGl2RenderingContext::TRIANGLE_STRIP, 0, 4);
+        }
+        Ok(())
+    }
+
+    // Creates an image overlay's texture, at once from image data or once an
+    // image URL has loaded, and then deletes the `previous` one it replaces
+    fn load_overlay_texture(context: &WebGl2RenderingContext, overlay: &ImageOverlay, previous: Option<WebGlTexture>) -> Result<(), JsValue> {
+        let id = overlay.id;
+        let create_texture = |context: &WebGl2RenderingContext| {
+            let texture = context.create_texture().ok_or_else(|| JsValue::from_str("Failed to create overlay texture"))?;
+            context.bind_texture(WebGl2RenderingContext::TEXTURE_2D, Some(&texture));
+            for (parameter, value) in [
+                (WebGl2RenderingContext::TEXTURE_WRAP_S, WebGl2RenderingContext::CLAMP_TO_EDGE),
+                (WebGl2RenderingContext::TEXTURE_WRAP_T, WebGl2RenderingContext::CLAMP_TO_EDGE),
+                (WebGl2RenderingContext::TEXTURE_MIN_FILTER, WebGl2RenderingContext::LINEAR),
+                (WebGl2RenderingContext::TEXTURE_MAG_FILTER, WebGl2RenderingContext::LINEAR),
+            ] {
+                context.tex_parameteri(WebGl2RenderingContext::TEXTURE_2D, parameter, value as i32);
             }
-            
-            // Disable blending when done
-            context.disable(WebGl2RenderingContext::BLEND);
+            Ok::<WebGlTexture, JsValue>(texture)
+        };
+
+        match &overlay.image {
+            OverlayImage::Data(data) => {
+                let texture = create_texture(context)?;
+                context.tex_image_2d_with_u32_and_u32_and_image_data(
+                    WebGl2RenderingContext::TEXTURE_2D,
+                    0,
+                    WebGl2RenderingContext::RGBA as i32,
+                    WebGl2RenderingContext::RGBA,
+                    WebGl2RenderingContext::UNSIGNED_BYTE,
+                    data,
+                )?;
+                OVERLAY_TEXTURES.with(|textures| textures.borrow_mut().insert(id, OverlayTexture::Loaded(texture)));
+                if let Some(previous) = previous {
+                    context.delete_texture(Some(&previous));
+                }
+            }
+            OverlayImage::Url(url) => {
+                let image = HtmlImageElement::new()?;
+                image.set_cross_origin(Some("anonymous"));
+                let cancelled = Rc::new(Cell::new(false));
+                OVERLAY_TEXTURES.with(|textures| {
+                    textures.borrow_mut().insert(id, OverlayTexture::Loading { cancelled: cancelled.clone(), previous })
+                });
+                // Swaps the new texture, or the failure, in for the one drawn
+                // while loading
+                let settle = move |context: &WebGl2RenderingContext, state: OverlayTexture| {
+                    let replaced = OVERLAY_TEXTURES.with(|textures| textures.borrow_mut().insert(id, state));
+                    if let Some(OverlayTexture::Loading { previous: Some(previous), .. }) = replaced {
+                        context.delete_texture(Some(&previous));
+                    }
+                };
+
+                let loaded_image = image.clone();
+                let context = context.clone();
+                let failed_context = context.clone();
+                let load_cancelled = cancelled.clone();
+                let onload_closure = Closure::wrap(Box::new(move || {
+                    if load_cancelled.get() {
+                        return;
+                    }
+                    let uploaded = create_texture(&context).and_then(|texture| {
+                        context.tex_image_2d_with_u32_and_u32_and_html_image_element(
+                            WebGl2RenderingContext::TEXTURE_2D,
+                            0,
+                            WebGl2RenderingContext::RGBA as i32,
+                            WebGl2RenderingContext::RGBA,
+                            WebGl2RenderingContext::UNSIGNED_BYTE,
+                            &loaded_image,
+                        )?;
+                        Ok(texture)
+                    });
+                    let state = match uploaded {
+                        Ok(texture) => OverlayTexture::Loaded(texture),
+                        Err(_) => OverlayTexture::Failed,
+                    };
+                    settle(&context, state);
+                }) as Box<dyn FnMut()>);
+                image.set_onload(Some(onload_closure.as_ref().unchecked_ref()));
+                onload_closure.forget();
+
+                let failed_url = url.clone();
+                let onerror_closure = Closure::wrap(Box::new(move || {
+                    if cancelled.get() {
+                        return;
+                    }
+                    settle(&failed_context, OverlayTexture::Failed);
+                    web_sys::console::warn_1(&JsValue::from_str(&format!("⚠️ Rustyleaf: Failed to load overlay image {}", failed_url)));
+                }) as Box<dyn FnMut()>);
+                image.set_onerror(Some(onerror_closure.as_ref().unchecked_ref()));
+                onerror_closure.forget();
+
+                image.set_src(url);
+            }
+        }
+        Ok(())
+    }
+
+    // Frees an image overlay's texture, or stops it loading
+    fn release_overlay_texture(&self, id: u32) {
+        match OVERLAY_TEXTURES.with(|textures| textures.borrow_mut().remove(&id)) {
+            Some(OverlayTexture::Loaded(texture)) => {
+                if let Some(ref gl_state) = self.gl_state {
+                    gl_state.context.delete_texture(Some(&texture));
+                }
+            }
+            Some(OverlayTexture::Loading { cancelled, previous }) => {
+                cancelled.set(true);
+                if let (Some(previous), Some(gl_state)) = (previous, &self.gl_state) {
+                    gl_state.context.delete_texture(Some(&previous));
+                }
+            }
+            Some(OverlayTexture::Failed) | None => {}
+        }
+    }
+
+    // Draws a vector tile layer's visible tiles, standing in cached tiles
+    // from other levels for missing ones, which are queued for loading
+    fn render_vector_tile_layer(
+        &mut self,
+        context: &WebGl2RenderingContext,
+        layer: &TileLayer,
+        visible_tiles: &[(TileCoord, i32)],
+        pinned: &mut HashSet<String>,
+        tiles_to_load: &mut Vec<(String, (u32, TileCoord), f64)>,
+    ) -> Result<(), JsValue> {
+        for (tile, wrapped_x) in visible_tiles {
+            let tile_coord = TileCoord { x: *wrapped_x, y: tile.y, z: tile.z };
+            let key = tile_coord.key(layer.id);
+            let cached = VECTOR_TILES.with(|store| store.borrow_mut().get(&key).is_some());
+            if !cached && !tiles_to_load.iter().any(|(queued, _, _)| queued == &key) {
+                tiles_to_load.push((key.clone(), (layer.id, tile_coord), self.tile_priority(tile)));
+            }
+            pinned.insert(key);
+        }
+
+        for (key, copy_offset) in self.vector_tile_draws(layer, visible_tiles) {
+            let cached = VECTOR_TILE_GEOMETRY.with(|geometry| geometry.borrow().get(&key).cloned());
+            let geometry = match cached {
+                Some(geometry) => geometry,
+                None => {
+                    let features = match VECTOR_TILES.with(|store| store.borrow().peek(&key).cloned()) {
+                        Some(features) => features,
+                        None => continue,
+                    };
+                    let geometry = Rc::new(vector_tile_geometry(layer, &features));
+                    VECTOR_TILE_GEOMETRY.with(|cache| cache.borrow_mut().insert(key.clone(), geometry.clone()));
+                    geometry
+                }
+            };
+            self.world_copy_offset = copy_offset;
+            let result = self.render_vector_tile(context, &geometry);
+            self.world_copy_offset = 0.0;
+            result?;
+            pinned.insert(key);
         }
+        Ok(())
+    }
+
+    /// Cached vector tiles to draw for the visible tiles of a layer, each
+    /// with the world-pixel shift of the world copy it lands on. Unlike
+    /// textures, a tile is drawn whole, so one standing in for several
+    /// missing tiles is listed once.
+    fn vector_tile_draws(&self, layer: &TileLayer, visible_tiles: &[(TileCoord, i32)]) -> Vec<(String, f64)> {
+        let world = self.world_size(self.zoom);
+        let mut draws: Vec<(String, f64)> = Vec::new();
+        VECTOR_TILES.with(|store| {
+            let store = store.borrow();
+            for (tile, wrapped_x) in visible_tiles {
+                let copy_offset = (tile.x - wrapped_x) as f64 / (1u64 << tile.z) as f64 * world;
+                for draw in Self::tile_fallbacks(layer, tile, *wrapped_x, |key| store.contains(key)) {
+                    if !draws.iter().any(|(key, offset)| *key == draw.key && *offset == copy_offset) {
+                        draws.push((draw.key, copy_offset));
+                    }
+                }
+            }
+        });
+        draws
+    }
 
+    // Draws one vector tile's styled geometry: fills, then lines and
+    // outlines, then points, like GeoJSON
+    fn render_vector_tile(&self, context: &WebGl2RenderingContext, geometry: &VectorTileGeometry) -> Result<(), JsValue> {
+        for (color, triangles) in &geometry.fills {
+            self.render_geojson_polygon_triangles(context, triangles, *color)?;
+        }
+        if !geometry.lines.is_empty() {
+            self.render_geojson_lines(context, &geometry.lines)?;
+        }
+        if !geometry.points.is_empty() {
+            self.render_geojson_points(context, &geometry.points)?;
+        }
         Ok(())
     }
 
@@ -1061,6 +2727,10 @@ impl RustyleafMap {
         if let Some(ref gl_state) = self.gl_state {
             context.use_program(Some(&gl_state.programs.point_program));
             context.bind_vertex_array(Some(&gl_state.point_vao));
+            let u_pixel_ratio = context.get_uniform_location(&gl_state.programs.point_program, "u_pixel_ratio");
+            if let Some(loc) = u_pixel_ratio.as_ref() {
+                context.uniform1f(Some(loc), self.pixel_ratio as f32);
+            }
 
             for layer in &self.point_layers {
                 if !layer.visible {
@@ -1126,15 +2796,7 @@ impl RustyleafMap {
                         
                         let start_screen = self.lat_lng_to_screen(start[0], start[1]);
                         let end_screen = self.lat_lng_to_screen(end[0], end[1]);
-                        
-                        // Create line segment with points and attributes
-                        // Each segment gets both endpoints with the same color
-                        vertex_data.extend_from_slice(&[
-                            start_screen.0 as f32, start_screen.1 as f32,
-                            line.color[0], line.color[1], line.color[2], line.color[3],
-                            end_screen.0 as f32, end_screen.1 as f32,
-                            line.color[0], line.color[1], line.color[2], line.color[3],
-                        ]);
+                        push_line_segment(&mut vertex_data, start_screen, end_screen, line.width, line.color);
                     }
                 }
 
@@ -1162,143 +2824,783 @@ impl RustyleafMap {
                     context.enable_vertex_attrib_array(1);
                     context.vertex_attrib_pointer_with_i32(1, 4, WebGl2RenderingContext::FLOAT, false, stride, 2 * 4);
 
-                    // Draw lines as line segments
+                    // Draw each segment as a quad of two triangles
+                    let total_vertices = vertex_data.len() / 6; // 6 floats per vertex
+                    context.draw_arrays(WebGl2RenderingContext::TRIANGLES, 0, total_vertices as i32);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn render_polygons(&mut self, context: &WebGl2RenderingContext) -> Result<(), JsValue> {
+        if self.polygon_layers.is_empty() {
+            return Ok(());
+        }
+
+        if let Some(ref gl_state) = self.gl_state {
+            context.use_program(Some(&gl_state.programs.polygon_program));
+            context.bind_vertex_array(Some(&gl_state.polygon_vao));
+
+            for layer in &self.polygon_layers {
+                if !layer.visible {
+                    continue;
+                }
+
+                // Collect all polygon triangle data
+                let mut vertex_data = Vec::new();
+
+                for polygon in layer.polygons.iter() {
+                    if polygon.rings.is_empty() {
+                        continue;
+                    }
+
+                    // Process outer ring and holes
+                    for ring in &polygon.rings {
+                        if ring.len() < 3 {
+                            continue;
+                        }
+
+                        // Triangulate the ring
+                        let triangles = self.triangulate_polygon(ring);
+                        
+                        // Convert triangles to screen coordinates
+                        for triangle in triangles.chunks(3) {
+                            if triangle.len() == 3 {
+                                for &[lat, lng] in triangle {
+                                    let screen_pos = self.lat_lng_to_screen(lat, lng);
+                                    vertex_data.extend_from_slice(&[
+                                        screen_pos.0 as f32, screen_pos.1 as f32,
+                                        polygon.color[0], polygon.color[1], polygon.color[2], polygon.color[3],
+                                    ]);
+                                }
+                            }
+                        }
+                    }
+                }
+
+                if !vertex_data.is_empty() {
+                    let vertices = Float32Array::new_with_length(vertex_data.len() as u32);
+                    for (i, &val) in vertex_data.iter().enumerate() {
+                        vertices.set_index(i as u32, val);
+                    }
+
+                    context.bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, Some(&gl_state.polygon_buffer));
+                    context.buffer_data_with_array_buffer_view(
+                        WebGl2RenderingContext::ARRAY_BUFFER,
+                        &vertices,
+                        WebGl2RenderingContext::STATIC_DRAW,
+                    );
+
+                    // Set up attributes for polygon rendering
+                    let stride = 6 * 4; // 6 floats per vertex * 4 bytes
+                    
+                    // Position attribute (vec2)
+                    context.enable_vertex_attrib_array(0);
+                    context.vertex_attrib_pointer_with_i32(0, 2, WebGl2RenderingContext::FLOAT, false, stride, 0);
+                    
+                    // Color attribute (vec4)
+                    context.enable_vertex_attrib_array(1);
+                    context.vertex_attrib_pointer_with_i32(1, 4, WebGl2RenderingContext::FLOAT, false, stride, 2 * 4);
+
+                    // Draw polygons as triangles
                     let total_vertices = vertex_data.len() / 6; // 6 floats per vertex
-                    context.draw_arrays(WebGl2RenderingContext::LINES, 0, total_vertices as i32);
+                    context.draw_arrays(WebGl2RenderingContext::TRIANGLES, 0, total_vertices as i32);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn create_projection_matrix(&self) -> [f32; 16] {
+        // Orthographic projection that converts pixel coordinates into NDC
+        // Matches the tile program's matrix so all layers share the same space
+        let w = self.width as f32;
+        let h = self.height as f32;
+        [
+            2.0 / w, 0.0,      0.0, 0.0,
+            0.0,     -2.0 / h, 0.0, 0.0,
+            0.0,      0.0,     -1.0, 0.0,
+            -1.0,     1.0,      0.0, 1.0,
+        ]
+    }
+
+    fn lat_lng_to_screen(&self, lat: f64, lng: f64) -> (f64, f64) {
+        let center_pixel = self.lat_lng_to_pixel(self.center_lat, self.center_lng, self.zoom);
+        let pixel = self.lat_lng_to_pixel(lat, lng, self.zoom);
+        let (offset_x, offset_y) = self.world_to_screen_offset(
+            pixel.0 - center_pixel.0 + self.world_copy_offset,
+            pixel.1 - center_pixel.1,
+        );
+        (self.width as f64 / 2.0 + offset_x, self.height as f64 / 2.0 + offset_y)
+    }
+
+    /// Projects a north-up world-pixel offset from the center into a screen
+    /// offset from the middle of the viewport, applying bearing and pitch
+    fn world_to_screen_offset(&self, dx: f64, dy: f64) -> (f64, f64) {
+        let (sin, cos) = self.bearing.to_radians().sin_cos();
+        let (u, v) = (cos * dx + sin * dy, cos * dy - sin * dx);
+        let cos_pitch = self.pitch.to_radians().cos();
+        let depth = self.perspective_depth(dx, dy);
+        (u / depth, v * cos_pitch / depth)
+    }
+
+    /// Inverse of `world_to_screen_offset`: casts the ray through a screen
+    /// offset onto the ground plane
+    fn screen_to_world_offset(&self, dx: f64, dy: f64) -> (f64, f64) {
+        let (sin_pitch, cos_pitch) = self.pitch.to_radians().sin_cos();
+        let distance = self.camera_distance();
+        let denominator = (distance * cos_pitch + dy * sin_pitch).max(distance * 1e-6);
+        let v = dy * distance / denominator;
+        let u = dx * (distance - v * sin_pitch) / distance;
+        let (sin, cos) = self.bearing.to_radians().sin_cos();
+        (cos * u - sin * v, sin * u + cos * v)
+    }
+
+    // Distance from the camera to the map center, in screen pixels
+    fn camera_distance(&self) -> f64 {
+        1.5 * self.height as f64
+    }
+
+    // Clip-space w of a ground offset from the center: 1.0 at the center,
+    // growing toward the horizon. Screen scale at that point is 1 / depth.
+    fn perspective_depth(&self, dx: f64, dy: f64) -> f64 {
+        let (sin, cos) = self.bearing.to_radians().sin_cos();
+        let v = cos * dy - sin * dx;
+        1.0 - v * self.pitch.to_radians().sin() / self.camera_distance()
+    }
+
+    /// Ground under the viewport's top-left, top-right, bottom-right and
+    /// bottom-left corners, as world-pixel offsets from the center. A
+    /// trapezoid when the map is pitched.
+    fn ground_footprint(&self) -> [(f64, f64); 4] {
+        let (half_width, half_height) = (self.width as f64 / 2.0, self.height as f64 / 2.0);
+        [
+            self.screen_to_world_offset(-half_width, -half_height),
+            self.screen_to_world_offset(half_width, -half_height),
+            self.screen_to_world_offset(half_width, half_height),
+            self.screen_to_world_offset(-half_width, half_height),
+        ]
+    }
+
+    /// Width and height of the north-aligned world-pixel box, centered on the
+    /// map center, that holds the visible ground
+    fn viewport_extent(&self) -> (f64, f64) {
+        let footprint = self.ground_footprint();
+        let half_width = footprint.iter().map(|c| c.0.abs()).fold(0.0, f64::max);
+        let half_height = footprint.iter().map(|c| c.1.abs()).fold(0.0, f64::max);
+        (2.0 * half_width, 2.0 * half_height)
+    }
+
+    /// Column-major transform from world pixels relative to the map center
+    /// into clip space. Vertices are sent center-relative so they stay small
+    /// enough for f32 at high zoom. Pitch goes into w, so the GPU's perspective
+    /// divide foreshortens the ground.
+    fn camera_matrix(&self) -> [f32; 16] {
+        let (sin, cos) = self.bearing.to_radians().sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.to_radians().sin_cos();
+        let sx = 2.0 / self.width as f64;
+        let sy = 2.0 * cos_pitch / self.height as f64;
+        let sw = sin_pitch / self.camera_distance();
+        [
+            (sx * cos) as f32, (sy * sin) as f32, 0.0, (sw * sin) as f32,
+            (sx * sin) as f32, (-sy * cos) as f32, 0.0, (-sw * cos) as f32,
+            0.0, 0.0, -1.0, 0.0,
+            0.0, 0.0, 0.0, 1.0,
+        ]
+    }
+
+    /// Textures to draw for `tile` of `layer` (whose canonical column is
+    /// `wrapped_x`). That is the tile itself once cached; until then its
+    /// cached children on top of the nearest cached ancestor, cropped to the
+    /// tile's area.
+    fn tile_fallbacks(layer: &TileLayer, tile: &TileCoord, wrapped_x: i32, is_cached: impl Fn(&str) -> bool) -> Vec<TileDraw> {
+        let key = TileCoord { x: wrapped_x, y: tile.y, z: tile.z }.key(layer.id);
+        if is_cached(&key) {
+            return vec![TileDraw { key, region: tile.clone(), uv: [0.0, 0.0, 1.0, 1.0] }];
+        }
+
+        // Children are sharper, typically left over from zooming out
+        let (min_level, max_level) = layer.tile_zoom_range();
+        let mut children = Vec::new();
+        if tile.z < max_level {
+            for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                let key = TileCoord { x: wrapped_x * 2 + dx, y: tile.y * 2 + dy, z: tile.z + 1 }.key(layer.id);
+                if is_cached(&key) {
+                    let region = TileCoord { x: tile.x * 2 + dx, y: tile.y * 2 + dy, z: tile.z + 1 };
+                    children.push(TileDraw { key, region, uv: [0.0, 0.0, 1.0, 1.0] });
+                }
+            }
+        }
+        if children.len() == 4 {
+            return children;
+        }
+
+        // The nearest cached ancestor fills whatever the children leave uncovered
+        let mut draws = Vec::new();
+        for levels_up in 1..=tile.z.saturating_sub(min_level) {
+            let ancestor = TileCoord { x: wrapped_x >> levels_up, y: tile.y >> levels_up, z: tile.z - levels_up };
+            let key = ancestor.key(layer.id);
+            if is_cached(&key) {
+                let span = 1 << levels_up;
+                let size = 1.0 / span as f32;
+                let u0 = (wrapped_x & (span - 1)) as f32 * size;
+                let v0 = (tile.y & (span - 1)) as f32 * size;
+                draws.push(TileDraw { key, region: tile.clone(), uv: [u0, v0, u0 + size, v0 + size] });
+                break;
+            }
+        }
+        draws.extend(children);
+        draws
+    }
+
+    /// Tiles covering the visible ground, between levels `min_zoom` and
+    /// `max_zoom`. Each tile's level follows its on-screen scale, so a pitched
+    /// view uses coarser tiles toward the horizon, and tiles larger than the
+    /// map's 256px come from a coarser level. `x` is the unwrapped column;
+    /// tiles off the single world are left out unless world copies are on.
+    fn covering_tiles(&self, tile_size: u32, min_zoom: u32, max_zoom: u32) -> Vec<TileCoord> {
+        let level_shift = (tile_size as f64 / self.tile_size as f64).log2();
+        let footprint = self.ground_footprint();
+        let center_pixel = self.lat_lng_to_pixel(self.center_lat, self.center_lng, self.zoom);
+        let world = self.world_size(self.zoom);
+
+        // Nothing visible is closer to the camera than the bottom of the screen
+        let min_depth = footprint.iter().map(|&(x, y)| self.perspective_depth(x, y)).fold(f64::INFINITY, f64::min);
+
+        let west = footprint.iter().map(|c| c.0).fold(f64::INFINITY, f64::min);
+        let east = footprint.iter().map(|c| c.0).fold(f64::NEG_INFINITY, f64::max);
+        let first_copy = ((center_pixel.0 + west) / world).floor() as i32;
+        let last_copy = ((center_pixel.0 + east) / world).floor() as i32;
+        let mut pending: Vec<TileCoord> = (first_copy..=last_copy)
+            .filter(|&column| self.wrap_tile_x(column, 0).is_some())
+            .map(|x| TileCoord { x, y: 0, z: 0 })
+            .collect();
+
+        let mut tiles = Vec::new();
+        while let Some(tile) = pending.pop() {
+            let size = world / (1u64 << tile.z) as f64;
+            let x0 = tile.x as f64 * size - center_pixel.0;
+            let y0 = tile.y as f64 * size - center_pixel.1;
+            let corners = [(x0, y0), (x0 + size, y0), (x0 + size, y0 + size), (x0, y0 + size)];
+            if !Self::convex_quads_intersect(&corners, &footprint) {
+                continue;
+            }
+
+            let depth = corners
+                .iter()
+                .map(|&(x, y)| self.perspective_depth(x, y))
+                .fold(f64::INFINITY, f64::min)
+                .max(min_depth);
+            let desired = (self.zoom - depth.log2() - level_shift).round().max(0.0) as u32;
+            if tile.z >= desired.clamp(min_zoom, max_zoom) {
+                tiles.push(tile);
+                continue;
+            }
+            for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                pending.push(TileCoord { x: tile.x * 2 + dx, y: tile.y * 2 + dy, z: tile.z + 1 });
+            }
+        }
+        tiles
+    }
+
+    /// A layer's tiles covering the view, each with its canonical column.
+    /// Tiles outside the layer's bounds are left out.
+    fn layer_covering_tiles(&self, layer: &TileLayer) -> Vec<(TileCoord, i32)> {
+        let (min_level, max_level) = layer.tile_zoom_range();
+        self.covering_tiles(layer.tile_size, min_level, max_level)
+            .into_iter()
+            .filter_map(|tile| {
+                let wrapped_x = self.wrap_tile_x(tile.x, tile.z)?;
+                let within_bounds = layer.coverage_bounds().is_none_or(|bounds| {
+                    self.tile_intersects_bounds(&TileCoord { x: wrapped_x, y: tile.y, z: tile.z }, bounds)
+                });
+                // WMS servers have nothing to draw beyond the projection's world
+                let within_world = layer.wms.is_none() || self.tile_intersects_world(&tile);
+                (within_bounds && within_world).then_some((tile, wrapped_x))
+            })
+            .collect()
+    }
+
+    // Whether any of a tile's rows lie within the projection's world
+    fn tile_intersects_world(&self, tile: &TileCoord) -> bool {
+        let (north, south) = self.projection.world_y_range();
+        let tiles_across = (1u64 << tile.z) as f64;
+        (tile.y as f64) / tiles_across < south && (tile.y + 1) as f64 / tiles_across > north
+    }
+
+    // Whether a tile overlaps a lat/lng box [sw_lat, sw_lng, ne_lat, ne_lng]
+    fn tile_intersects_bounds(&self, tile: &TileCoord, bounds: [f64; 4]) -> bool {
+        // At the tile's own zoom every tile is one map tile across
+        let [min_x, min_y, max_x, max_y] = self.projected_bounds(bounds, tile.z as f64);
+        let size = self.tile_size as f64;
+        let (x0, y0) = (tile.x as f64 * size, tile.y as f64 * size);
+        x0 < max_x && x0 + size > min_x && y0 < max_y && y0 + size > min_y
+    }
+
+    /// Tile levels with the ranges of columns and rows, inclusive, of a
+    /// layer's tiles covering a lat/lng box at map zooms `min_zoom` to
+    /// `max_zoom`, within the layer's zoom range and bounds
+    fn region_tile_ranges(&self, layer: &TileLayer, bounds: [f64; 4], min_zoom: u32, max_zoom: u32) -> Vec<(u32, [i32; 4])> {
+        let bounds = match layer.coverage_bounds() {
+            Some(coverage) => [bounds[0].max(coverage[0]), bounds[1].max(coverage[1]), bounds[2].min(coverage[2]), bounds[3].min(coverage[3])],
+            None => bounds,
+        };
+        if bounds[2] <= bounds[0] || bounds[3] <= bounds[1] {
+            return Vec::new();
+        }
+
+        let (layer_min_zoom, layer_max_zoom) = layer.zoom_range();
+        let level = |zoom: u32| (zoom as i32 - layer.zoom_shift()).max(0) as u32;
+        let mut levels: Vec<u32> = (min_zoom.max(layer_min_zoom)..=max_zoom.min(layer_max_zoom)).map(level).collect();
+        levels.dedup();
+
+        levels
+            .into_iter()
+            .map(|z| {
+                // At the tile's own zoom every tile is one map tile across
+                let [min_x, min_y, max_x, max_y] = self.projected_bounds(bounds, z as f64);
+                let size = self.tile_size as f64;
+                let last = (1i64 << z) - 1;
+                let index = |pixel: f64| ((pixel / size).floor() as i64).clamp(0, last) as i32;
+                (z, [index(min_x), index(min_y), index(max_x), index(max_y)])
+            })
+            .collect()
+    }
+
+    fn region_tile_count(ranges: &[(u32, [i32; 4])]) -> usize {
+        ranges
+            .iter()
+            .map(|(_, [min_x, min_y, max_x, max_y])| (max_x - min_x + 1) as usize * (max_y - min_y + 1) as usize)
+            .sum()
+    }
+
+    // The layer's tiles covering a region with their URLs, which key them in
+    // the tile store
+    fn region_tiles(&self, layer: &TileLayer, ranges: &[(u32, [i32; 4])]) -> Vec<(String, TileCoord)> {
+        let mut tiles = Vec::new();
+        for &(z, [min_x, min_y, max_x, max_y]) in ranges {
+            for y in min_y..=max_y {
+                for x in min_x..=max_x {
+                    let coord = TileCoord { x, y, z };
+                    tiles.push((self.tile_url(layer, &coord), coord));
+                }
+            }
+        }
+        tiles
+    }
+
+    // Separating axis test between two convex quads
+    fn convex_quads_intersect(a: &[(f64, f64); 4], b: &[(f64, f64); 4]) -> bool {
+        let project = |quad: &[(f64, f64); 4], axis: (f64, f64)| {
+            quad.iter().map(|p| p.0 * axis.0 + p.1 * axis.1).fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), d| (lo.min(d), hi.max(d)))
+        };
+        for quad in [a, b] {
+            for i in 0..4 {
+                let (p, q) = (quad[i], quad[(i + 1) % 4]);
+                let axis = (q.1 - p.1, p.0 - q.0);
+                let (a_min, a_max) = project(a, axis);
+                let (b_min, b_max) = project(b, axis);
+                if a_max <= b_min || b_max <= a_min {
+                    return false;
                 }
             }
         }
+        true
+    }
+
+    /// World-pixel x shifts of every copy of the world overlapping the viewport.
+    /// Only the canonical copy (shift 0) is returned when world copies are off.
+    fn world_copy_offsets(&self) -> Vec<f64> {
+        if !self.render_world_copies || !self.projection.wraps() {
+            return vec![0.0];
+        }
+
+        let world = self.world_size(self.zoom);
+        let center_pixel = self.lat_lng_to_pixel(self.center_lat, self.center_lng, self.zoom);
+        // Horizontal world-pixel span of the viewport
+        let (extent_width, _) = self.viewport_extent();
+        let view_west = center_pixel.0 - extent_width / 2.0;
+        let view_east = center_pixel.0 + extent_width / 2.0;
+
+        let first = (view_west / world - 1.0).floor() as i32 + 1;
+        let last = (view_east / world).ceil() as i32 - 1;
+        (first..=last).map(|copy| copy as f64 * world).collect()
+    }
+
+    /// Tile column to request for `tile_x`, wrapping around the world when
+    /// world copies are on. `None` if the column lies off the single world.
+    fn wrap_tile_x(&self, tile_x: i32, tile_zoom: u32) -> Option<i32> {
+        let tiles_across = 1 << tile_zoom;
+        if self.render_world_copies && self.projection.wraps() {
+            Some(tile_x.rem_euclid(tiles_across))
+        } else if (0..tiles_across).contains(&tile_x) {
+            Some(tile_x)
+        } else {
+            None
+        }
+    }
+
+    fn screen_to_lat_lng(&self, screen_x: f64, screen_y: f64) -> (f64, f64) {
+        let center_pixel = self.lat_lng_to_pixel(self.center_lat, self.center_lng, self.zoom);
+        let (offset_x, offset_y) = self.screen_to_world_offset(
+            screen_x - self.width as f64 / 2.0,
+            screen_y - self.height as f64 / 2.0,
+        );
+        self.pixel_to_lat_lng(center_pixel.0 + offset_x, center_pixel.1 + offset_y, self.zoom)
+    }
+
+    /// Integer zoom level whose tiles are drawn for the current fractional zoom,
+    /// and the factor by which those tiles are scaled to fit the view.
+    fn tile_zoom_and_scale(&self) -> (u32, f64) {
+        let tile_zoom = self.zoom.round().max(0.0) as u32;
+        (tile_zoom, 2f64.powf(self.zoom - tile_zoom as f64))
+    }
+
+    /// Same as `tile_zoom_and_scale`, restricted to a tile layer's zoom range.
+    /// Returns `None` below the layer's minimum zoom; above its maximum zoom the
+    /// last available level is overzoomed.
+    fn layer_tile_zoom_and_scale(&self, layer: &TileLayer) -> Option<(u32, f64)> {
+        let (tile_zoom, _) = self.tile_zoom_and_scale();
+        let (min_zoom, max_zoom) = layer.zoom_range();
+        if tile_zoom < min_zoom {
+            return None;
+        }
+        let tile_zoom = tile_zoom.min(max_zoom);
+        Some((tile_zoom, 2f64.powf(self.zoom - tile_zoom as f64)))
+    }
+
+    fn clamp_zoom(&self, zoom: f64) -> f64 {
+        let min_zoom = self.effective_min_zoom();
+        zoom.clamp(min_zoom, self.max_zoom.max(min_zoom))
+    }
+
+    // Minimum zoom, raised so that max bounds always fill the viewport
+    fn effective_min_zoom(&self) -> f64 {
+        match self.max_bounds {
+            Some(bounds) => {
+                let [min_x, min_y, max_x, max_y] = self.projected_bounds(bounds, 0.0);
+                let (extent_width, extent_height) = self.viewport_extent();
+                let fill_zoom = (extent_width / (max_x - min_x))
+                    .max(extent_height / (max_y - min_y))
+                    .log2();
+                self.min_zoom.max(fill_zoom)
+            }
+            None => self.min_zoom,
+        }
+    }
+
+    /// World-pixel box `[min_x, min_y, max_x, max_y]` at `zoom` enclosing the
+    /// lat/lng box `[sw_lat, sw_lng, ne_lat, ne_lng]`. The edges are sampled
+    /// since in non-cylindrical projections they are curves, not straight lines.
+    fn projected_bounds(&self, bounds: [f64; 4], zoom: f64) -> [f64; 4] {
+        let [sw_lat, sw_lng, ne_lat, ne_lng] = bounds;
+        let samples = if self.projection.wraps() { 1 } else { 16 };
+
+        let mut envelope = [f64::INFINITY, f64::INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY];
+        for i in 0..=samples {
+            let t = i as f64 / samples as f64;
+            let lat = sw_lat + (ne_lat - sw_lat) * t;
+            let lng = sw_lng + (ne_lng - sw_lng) * t;
+            for (lat, lng) in [(lat, sw_lng), (lat, ne_lng), (sw_lat, lng), (ne_lat, lng)] {
+                let (x, y) = self.lat_lng_to_pixel(lat, lng, zoom);
+                envelope = [envelope[0].min(x), envelope[1].min(y), envelope[2].max(x), envelope[3].max(y)];
+            }
+        }
+        envelope
+    }
 
-        Ok(())
+    /// Clamps a center given in world pixels at `zoom` so the viewport stays
+    /// inside max bounds. Returns `None` when no max bounds are set.
+    fn constrain_to_max_bounds(&self, center: (f64, f64), zoom: f64) -> Option<(f64, f64)> {
+        let [min_x, min_y, max_x, max_y] = self.projected_bounds(self.max_bounds?, zoom);
+        let (extent_width, extent_height) = self.viewport_extent();
+        let half_width = extent_width / 2.0;
+        let half_height = extent_height / 2.0;
+
+        // Center on the bounds along any axis where they are smaller than the viewport
+        let x = if max_x - min_x <= 2.0 * half_width {
+            (min_x + max_x) / 2.0
+        } else {
+            center.0.clamp(min_x + half_width, max_x - half_width)
+        };
+        let y = if max_y - min_y <= 2.0 * half_height {
+            (min_y + max_y) / 2.0
+        } else {
+            center.1.clamp(min_y + half_height, max_y - half_height)
+        };
+        Some((x, y))
     }
 
-    fn render_polygons(&mut self, context: &WebGl2RenderingContext) -> Result<(), JsValue> {
-        if self.polygon_layers.is_empty() {
-            return Ok(());
+    // Moves the current center inside max bounds without firing events
+    fn constrain_view(&mut self) {
+        let center = self.lat_lng_to_pixel(self.center_lat, self.center_lng, self.zoom);
+        if let Some((x, y)) = self.constrain_to_max_bounds(center, self.zoom) {
+            let (lat, lng) = self.pixel_to_lat_lng(x, y, self.zoom);
+            self.center_lat = lat;
+            self.center_lng = lng;
         }
+    }
 
-        if let Some(ref gl_state) = self.gl_state {
-            context.use_program(Some(&gl_state.programs.polygon_program));
-            context.bind_vertex_array(Some(&gl_state.polygon_vao));
+    /// Resolves a pan from `current` to `target` (world pixels) against max
+    /// bounds. When `elastic`, movement past the bounds is slowed by the
+    /// viscosity instead of being stopped outright.
+    fn constrain_pan(&self, current: (f64, f64), target: (f64, f64), zoom: f64, elastic: bool) -> (f64, f64) {
+        let constrained = match self.constrain_to_max_bounds(target, zoom) {
+            Some(constrained) => constrained,
+            None => return target,
+        };
+        if !elastic || self.max_bounds_viscosity >= 1.0 {
+            return constrained;
+        }
 
-            for layer in &self.polygon_layers {
-                if !layer.visible {
-                    continue;
-                }
+        let current_constrained = self.constrain_to_max_bounds(current, zoom).unwrap_or(current);
+        let viscosity = self.max_bounds_viscosity;
+        (
+            Self::resist_overshoot(target.0, constrained.0, current.0 - current_constrained.0, viscosity),
+            Self::resist_overshoot(target.1, constrained.1, current.1 - current_constrained.1, viscosity),
+        )
+    }
 
-                // Collect all polygon triangle data
-                let mut vertex_data = Vec::new();
+    fn resist_overshoot(target: f64, constrained: f64, previous_excess: f64, viscosity: f64) -> f64 {
+        let excess = target - constrained;
+        // Only movement that pushes further outside the bounds meets resistance
+        if excess * previous_excess >= 0.0 && excess.abs() > previous_excess.abs() {
+            constrained + previous_excess + (excess - previous_excess) * (1.0 - viscosity)
+        } else {
+            target
+        }
+    }
 
-                for polygon in layer.polygons.iter() {
-                    if polygon.rings.is_empty() {
-                        continue;
-                    }
+    // Eases the view back inside max bounds after an elastic drag or fling
+    fn relax_into_max_bounds(&mut self) {
+        let center = self.lat_lng_to_pixel(self.center_lat, self.center_lng, self.zoom);
+        let target = match self.constrain_to_max_bounds(center, self.zoom) {
+            Some(target) => target,
+            None => return,
+        };
 
-                    // Process outer ring and holes
-                    for ring in &polygon.rings {
-                        if ring.len() < 3 {
-                            continue;
-                        }
+        let delta_x = target.0 - center.0;
+        let delta_y = target.1 - center.1;
+        if delta_x == 0.0 && delta_y == 0.0 {
+            return;
+        }
 
-                        // Triangulate the ring
-                        let triangles = self.triangulate_polygon(ring);
-                        
-                        // Convert triangles to screen coordinates
-                        for triangle in triangles.chunks(3) {
-                            if triangle.len() == 3 {
-                                for &[lat, lng] in triangle {
-                                    let screen_pos = self.lat_lng_to_screen(lat, lng);
-                                    vertex_data.extend_from_slice(&[
-                                        screen_pos.0 as f32, screen_pos.1 as f32,
-                                        polygon.color[0], polygon.color[1], polygon.color[2], polygon.color[3],
-                                    ]);
-                                }
-                            }
-                        }
-                    }
-                }
+        // Close a fixed fraction of the gap per frame, snapping once sub-pixel
+        let (step_x, step_y) = if delta_x.abs() < 0.5 && delta_y.abs() < 0.5 {
+            (delta_x, delta_y)
+        } else {
+            (delta_x * 0.2, delta_y * 0.2)
+        };
+        let (lat, lng) = self.pixel_to_lat_lng(center.0 + step_x, center.1 + step_y, self.zoom);
+        self.center_lat = lat;
+        self.center_lng = lng;
+        self.load_visible_tiles();
+        self.trigger_move_event();
+    }
 
-                if !vertex_data.is_empty() {
-                    let vertices = Float32Array::new_with_length(vertex_data.len() as u32);
-                    for (i, &val) in vertex_data.iter().enumerate() {
-                        vertices.set_index(i as u32, val);
-                    }
+    #[wasm_bindgen]
+    pub fn resize(&mut self, width: u32, height: u32) -> Result<(), JsValue> {
+        self.width = width;
+        self.height = height;
+        self.apply_drawing_buffer_size();
+        // The zoom needed for max bounds to fill the viewport depends on its size
+        if self.max_bounds.is_some() {
+            self.set_view(self.center_lat, self.center_lng, self.zoom);
+        }
+        Ok(())
+    }
 
-                    context.bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, Some(&gl_state.polygon_buffer));
-                    context.buffer_data_with_array_buffer_view(
-                        WebGl2RenderingContext::ARRAY_BUFFER,
-                        &vertices,
-                        WebGl2RenderingContext::STATIC_DRAW,
-                    );
+    /// Sets the number of device pixels per CSS pixel, normally
+    /// `window.devicePixelRatio`. The drawing buffer is sized `width * ratio`
+    /// so the map stays sharp on high-DPI screens; positions, point sizes and
+    /// hit-testing stay in CSS pixels. Tile templates with `{r}` switch to
+    /// `@2x` tiles above a ratio of 1, and WMS layers request double-size images.
+    #[wasm_bindgen]
+    pub fn set_pixel_ratio(&mut self, ratio: f64) -> Result<(), JsValue> {
+        if !ratio.is_finite() || ratio <= 0.0 {
+            return Err(JsValue::from_str("Pixel ratio must be a positive number"));
+        }
+        if ratio == self.pixel_ratio {
+            return Ok(());
+        }
 
-                    // Set up attributes for polygon rendering
-                    let stride = 6 * 4; // 6 floats per vertex * 4 bytes
-                    
-                    // Position attribute (vec2)
-                    context.enable_vertex_attrib_array(0);
-                    context.vertex_attrib_pointer_with_i32(0, 2, WebGl2RenderingContext::FLOAT, false, stride, 0);
-                    
-                    // Color attribute (vec4)
-                    context.enable_vertex_attrib_array(1);
-                    context.vertex_attrib_pointer_with_i32(1, 4, WebGl2RenderingContext::FLOAT, false, stride, 2 * 4);
+        let retina_changed = (ratio > 1.0) != (self.pixel_ratio > 1.0);
+        self.pixel_ratio = ratio;
+        self.apply_drawing_buffer_size();
 
-                    // Draw polygons as triangles
-                    let total_vertices = vertex_data.len() / 6; // 6 floats per vertex
-                    context.draw_arrays(WebGl2RenderingContext::TRIANGLES, 0, total_vertices as i32);
+        // Tiles already loaded are at the wrong resolution for the new ratio
+        let uses_retina_tiles = self.tile_layers.iter().any(TileLayer::uses_retina_tiles);
+        if retina_changed && uses_retina_tiles {
+            for (_, texture) in TILE_TEXTURES.with(|store| store.borrow_mut().clear()) {
+                if let Some(ref gl_state) = self.gl_state {
+                    gl_state.context.delete_texture(Some(&texture));
                 }
             }
+            ELEVATION_TILES.with(|tiles| tiles.borrow_mut().clear());
+            for (_, handle) in TILE_REQUESTS.with(|requests| requests.borrow_mut().reset()) {
+                Self::abort_tile_request(&handle);
+            }
+            self.load_visible_tiles();
         }
+        self.schedule_render();
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    pub fn get_pixel_ratio(&self) -> f64 {
+        self.pixel_ratio
+    }
 
+    /// Caps the tile texture cache at `max_tiles` tiles and `max_bytes` of
+    /// estimated GPU memory, and the vector tile cache likewise. Least
+    /// recently used tiles are freed first; tiles on screen are never evicted.
+    #[wasm_bindgen]
+    pub fn set_tile_cache_limits(&mut self, max_tiles: u32, max_bytes: f64) -> Result<(), JsValue> {
+        if !max_bytes.is_finite() || max_bytes < 0.0 {
+            return Err(JsValue::from_str("Tile cache byte limit must be a non-negative number"));
+        }
+        TILE_TEXTURES.with(|store| store.borrow_mut().set_limits(max_tiles as usize, max_bytes as usize));
+        VECTOR_TILES.with(|store| store.borrow_mut().set_limits(max_tiles as usize, max_bytes as usize));
+        self.cleanup_old_tiles();
         Ok(())
     }
 
-    fn create_projection_matrix(&self) -> [f32; 16] {
-        // Orthographic projection that converts pixel coordinates into NDC
-        // Matches the tile program's matrix so all layers share the same space
-        let w = self.width as f32;
-        let h = self.height as f32;
-        [
-            2.0 / w, 0.0,      0.0, 0.0,
-            0.0,     -2.0 / h, 0.0, 0.0,
-            0.0,      0.0,     -1.0, 0.0,
-            -1.0,     1.0,      0.0, 1.0,
-        ]
+    /// Number of tile downloads allowed in flight at once
+    #[wasm_bindgen]
+    pub fn set_max_tile_requests(&mut self, max_requests: u32) {
+        TILE_REQUESTS.with(|requests| requests.borrow_mut().set_max_concurrent(max_requests.max(1) as usize));
+        self.load_visible_tiles();
     }
 
-    fn lat_lng_to_screen(&self, lat: f64, lng: f64) -> (f64, f64) {
-        let zoom = self.zoom.round() as u32;
-        let center_pixel = self.lat_lng_to_pixel(self.center_lat, self.center_lng, zoom);
-        let start_x = center_pixel.0 - (self.width as f64 / 2.0);
-        let start_y = center_pixel.1 - (self.height as f64 / 2.0);
-        let pixel = self.lat_lng_to_pixel(lat, lng, zoom);
-        let screen_x = pixel.0 - start_x;
-        let screen_y = pixel.1 - start_y;
-        (screen_x, screen_y)
+    /// Load state of a tile layer's tile at `z/x/y`: "loaded", "loading",
+    /// "retrying" (failed, waiting to try again), "failed" (given up on) or "idle"
+    #[wasm_bindgen]
+    pub fn get_tile_status(&self, layer_id: u32, z: u32, x: i32, y: i32) -> String {
+        let key = TileCoord { x, y, z }.key(layer_id);
+        if TILE_TEXTURES.with(|store| store.borrow().contains(&key)) || VECTOR_TILES.with(|store| store.borrow().contains(&key)) {
+            return "loaded".to_string();
+        }
+        match TILE_REQUESTS.with(|requests| requests.borrow().state(&key)) {
+            TileRequestState::Idle => "idle",
+            TileRequestState::Loading => "loading",
+            TileRequestState::Retrying { .. } => "retrying",
+            TileRequestState::Failed { .. } => "failed",
+        }
+        .to_string()
     }
 
+    /// Tile cache counters: `{ hits, misses, evictions, tiles, bytes }`
     #[wasm_bindgen]
-    pub fn resize(&mut self, width: u32, height: u32) -> Result<(), JsValue> {
-        self.width = width;
-        self.height = height;
+    pub fn get_tile_cache_stats(&self) -> Result<JsValue, JsValue> {
+        let (stats, tiles, bytes) = TILE_TEXTURES.with(|store| {
+            let cache = store.borrow();
+            (cache.stats(), cache.len(), cache.bytes())
+        });
+        let obj = js_sys::Object::new();
+        for (name, value) in [
+            ("hits", stats.hits as f64),
+            ("misses", stats.misses as f64),
+            ("evictions", stats.evictions as f64),
+            ("tiles", tiles as f64),
+            ("bytes", bytes as f64),
+        ] {
+            js_sys::Reflect::set(&obj, &JsValue::from_str(name), &JsValue::from_f64(value))
+                .map_err(|e| JsValue::from_str(&format!("Failed to set {}: {:?}", name, e)))?;
+        }
+        Ok(obj.into())
+    }
+
+    /// Persistent tile store tile layers read from before downloading, and
+    /// that `seed_tiles` fills: an object with `get(key)`, resolving to the
+    /// stored Uint8Array or undefined, and `put(key, bytes)`, or "memory" to
+    /// keep tiles for the session only. Tiles are keyed by URL, which for
+    /// `{r}` templates and WMS layers depends on the pixel ratio. Null removes
+    /// the store.
+    #[wasm_bindgen]
+    pub fn set_tile_store(&mut self, store: &JsValue) -> Result<(), JsValue> {
+        self.tile_store = parse_tile_store(store)?;
+        Ok(())
+    }
+
+    /// Number of a tile layer's tiles covering `bounds` ([sw_lat, sw_lng,
+    /// ne_lat, ne_lng]) from `min_zoom` to `max_zoom`, and a rough estimate of
+    /// their size: `{ tiles, bytes }`
+    #[wasm_bindgen]
+    pub fn estimate_seed(&self, layer_id: u32, bounds: &JsValue, min_zoom: u32, max_zoom: u32) -> Result<JsValue, JsValue> {
+        let layer = &self.tile_layers[self.tile_layer_index(layer_id)?];
+        let bounds = parse_bounds(bounds, "Seed bounds")?.ok_or_else(|| JsValue::from_str("Seed bounds are required"))?;
+        let tiles = Self::region_tile_count(&self.region_tile_ranges(layer, bounds, min_zoom, max_zoom));
+
+        let obj = js_sys::Object::new();
+        for (name, value) in [("tiles", tiles as f64), ("bytes", (tiles * ESTIMATED_TILE_BYTES) as f64)] {
+            js_sys::Reflect::set(&obj, &JsValue::from_str(name), &JsValue::from_f64(value))
+                .map_err(|e| JsValue::from_str(&format!("Failed to set {}: {:?}", name, e)))?;
+        }
+        Ok(obj.into())
+    }
+
+    /// Downloads a tile layer's tiles covering `bounds` from `min_zoom` to
+    /// `max_zoom` into the tile store, skipping those already stored, so the
+    /// region can be viewed offline. `on_progress` is called with `{ total,
+    /// stored, skipped, failed, bytes, finished, cancelled }` as tiles
+    /// complete. Layers reading from a tile source (PMTiles, MBTiles) can't
+    /// be seeded. Tiles are requested at the current pixel ratio, and a layer
+    /// whose URLs depend on it only finds them when viewed at that ratio.
+    #[wasm_bindgen]
+    pub fn seed_tiles(&self, layer_id: u32, bounds: &JsValue, min_zoom: u32, max_zoom: u32, on_progress: &JsValue) -> Result<TileSeedJob, JsValue> {
+        let layer = &self.tile_layers[self.tile_layer_index(layer_id)?];
+        let store = self.tile_store.clone().ok_or_else(|| JsValue::from_str("No tile store is set"))?;
+        if layer.source.is_some() {
+            return Err(JsValue::from_str(&format!("Tile layer {} reads from a tile source and can't be seeded", layer_id)));
+        }
+        let bounds = parse_bounds(bounds, "Seed bounds")?.ok_or_else(|| JsValue::from_str("Seed bounds are required"))?;
+        let on_progress = on_progress.clone().dyn_into::<js_sys::Function>().ok();
+
+        let ranges = self.region_tile_ranges(layer, bounds, min_zoom, max_zoom);
+        let count = Self::region_tile_count(&ranges);
+        if count > MAX_SEED_TILES {
+            return Err(JsValue::from_str(&format!("Region has {} tiles, more than the {} allowed in one seed", count, MAX_SEED_TILES)));
+        }
+
+        let job = SeedJob::start(
+            self.region_tiles(layer, &ranges),
+            store,
+            Box::new(|url, _, done| fetch_tile_bytes(url, done)),
+            Rc::new(move |progress: &SeedProgress| {
+                if let Some(callback) = &on_progress {
+                    let _ = callback.call1(&JsValue::NULL, &seed_progress_to_js(progress));
+                }
+            }),
+            MAX_SEED_REQUESTS,
+        );
+        Ok(TileSeedJob { job })
+    }
+
+    // Drawing buffer size in device pixels
+    fn drawing_buffer_size(&self) -> (u32, u32) {
+        (
+            (self.width as f64 * self.pixel_ratio).round().max(1.0) as u32,
+            (self.height as f64 * self.pixel_ratio).round().max(1.0) as u32,
+        )
+    }
+
+    fn apply_drawing_buffer_size(&self) {
+        let (buffer_width, buffer_height) = self.drawing_buffer_size();
         if let Some(ref canvas) = self.canvas {
-            canvas.set_width(width);
-            canvas.set_height(height);
+            canvas.set_width(buffer_width);
+            canvas.set_height(buffer_height);
         }
         if let Some(ref gl_state) = self.gl_state {
-            gl_state.context.viewport(0, 0, width as i32, height as i32);
+            gl_state.context.viewport(0, 0, buffer_width as i32, buffer_height as i32);
         }
-        Ok(())
     }
 
     #[wasm_bindgen]
     pub fn screen_xy(&self, lat: f64, lng: f64) -> Array {
-        let zoom = self.zoom.round() as u32;
-        let center_pixel = self.lat_lng_to_pixel(self.center_lat, self.center_lng, zoom);
-        let start_x = center_pixel.0 - (self.width as f64 / 2.0);
-        let start_y = center_pixel.1 - (self.height as f64 / 2.0);
-        let (px, py) = self.lat_lng_to_pixel(lat, lng, zoom);
-        let screen_x = px - start_x;
-        let screen_y = py - start_y;
+        let (screen_x, screen_y) = self.lat_lng_to_screen(lat, lng);
         let arr = Array::new();
         arr.push(&JsValue::from_f64(screen_x));
         arr.push(&JsValue::from_f64(screen_y));
@@ -1308,180 +3610,474 @@ impl RustyleafMap {
     // removed stale canvas 2D debug renderer
 
     fn load_visible_tiles(&mut self) {
-        let zoom = self.zoom.round() as u32;
-        let center_pixel = self.lat_lng_to_pixel(self.center_lat, self.center_lng, zoom);
-
-        let start_x = center_pixel.0 - (self.width as f64 / 2.0);
-        let start_y = center_pixel.1 - (self.height as f64 / 2.0);
-
-        let start_tile_x = (start_x / self.tile_size as f64).floor() as i32;
-        let start_tile_y = (start_y / self.tile_size as f64).floor() as i32;
-
-        let tiles_x = (self.width as f64 / self.tile_size as f64).ceil() as i32 + 1;
-        let tiles_y = (self.height as f64 / self.tile_size as f64).ceil() as i32 + 1;
-
-        // Limit the number of tiles we try to load at once
-        let mut load_count = 0;
-        let max_load_per_frame = 3; // Ultra conservative loading
-
-        for x in (start_tile_x)..(start_tile_x + tiles_x) {
-            for y in (start_tile_y)..(start_tile_y + tiles_y) {
-                if x >= 0 && y >= 0 && x < (1 << zoom) && y < (1 << zoom) {
-                    let tile_coord = TileCoord { x, y, z: zoom };
-                    let tile_key = format!("{}/{}/{}", zoom, x, y);
-                    let already_requested = self.requested.contains(&tile_key);
-                    let already_cached = TILE_TEXTURES.with(|store| store.borrow().contains_key(&tile_key));
-
-                    if !already_requested && !already_cached && load_count < max_load_per_frame {
-                        let tile = Tile {
-                            coord: tile_coord.clone(),
-                            texture: None,
-                            loading: false,
-                        };
-                        self.tiles.insert(tile_key.clone(), tile);
-                        self.requested.insert(tile_key.clone());
-                        self.load_tile(tile_coord);
-                        load_count += 1;
-                    }
+        let mut wanted = Vec::new();
+        for layer in self.tile_layers_in_draw_order() {
+            // Hidden layers and those below their minimum zoom request nothing
+            if !layer.visible || layer.opacity <= 0.0 || self.layer_tile_zoom_and_scale(layer).is_none() {
+                continue;
+            }
+            for (tile, x) in self.layer_covering_tiles(layer) {
+                let tile_coord = TileCoord { x, y: tile.y, z: tile.z };
+                let tile_key = tile_coord.key(layer.id);
+                let already_cached = if layer.is_vector() {
+                    VECTOR_TILES.with(|store| store.borrow().contains(&tile_key))
+                } else {
+                    TILE_TEXTURES.with(|store| store.borrow().contains(&tile_key))
+                };
+                if !already_cached && !wanted.iter().any(|(queued, _, _)| queued == &tile_key) {
+                    wanted.push((tile_key, (layer.id, tile_coord), self.tile_priority(&tile)));
                 }
             }
         }
+        self.request_tiles(wanted);
     }
 
-    fn load_tile(&mut self, coord: TileCoord) {
-        let tile_key = format!("{}/{}/{}", coord.z, coord.x, coord.y);
-        let url = if let Some(layer) = &self.tile_layer {
-            // Support subdomains like {s}
-            let subdomain = layer.subdomains.get(((coord.x + coord.y) as usize) % layer.subdomains.len()).cloned().unwrap_or_else(|| "a".to_string());
-            layer.url_template
-                .replace("{s}", &subdomain)
-                .replace("{z}", &coord.z.to_string())
-                .replace("{x}", &coord.x.to_string())
-                .replace("{y}", &coord.y.to_string())
-        } else {
-            format!("https://tile.openstreetmap.org/{}/{}/{}.png", coord.z, coord.x, coord.y)
-        };
+    /// Hands the missing tiles the view needs, across all layers, to the
+    /// scheduler, which starts the most central ones within the concurrency
+    /// cap and aborts requests for tiles that are no longer wanted
+    fn request_tiles(&mut self, wanted: Vec<(String, (u32, TileCoord), f64)>) {
+        // Nothing can be uploaded before the canvas is initialised
+        if self.gl_state.is_none() {
+            return;
+        }
+        let plan = TILE_REQUESTS.with(|requests| requests.borrow_mut().plan(wanted, js_sys::Date::now()));
+        for (_, handle) in plan.cancel {
+            Self::abort_tile_request(&handle);
+        }
+        for (_, (layer_id, coord)) in plan.start {
+            self.load_tile(layer_id, coord);
+        }
+    }
 
-        if let Some(tile) = self.tiles.get_mut(&tile_key) {
-            if tile.loading || tile.texture.is_some() {
-                return;
+    // Detach the handlers first so the abort isn't reported as a failure
+    fn abort_tile_request(handle: &TileRequestHandle) {
+        match handle {
+            TileRequestHandle::Image(image) => {
+                image.set_onload(None);
+                image.set_onerror(None);
+                // Archive tiles' object URLs are otherwise released on load
+                let src = image.src();
+                if src.starts_with("blob:") {
+                    let _ = web_sys::Url::revoke_object_url(&src);
+                }
+                image.set_src(EMPTY_IMAGE_URL);
             }
-            tile.loading = true;
+            TileRequestHandle::Xhr(xhr) => {
+                xhr.set_onload(None);
+                xhr.set_onerror(None);
+                let _ = xhr.abort();
+            }
+            // Range requests already made run on, but their tile is dropped
+            TileRequestHandle::Source(cancelled) => cancelled.set(true),
         }
+    }
 
-    
-        if let Some(ref gl_state) = self.gl_state {
-            let context = &gl_state.context;
+    // Screen distance from the viewport center to the middle of a tile
+    fn tile_priority(&self, tile: &TileCoord) -> f64 {
+        let center_pixel = self.lat_lng_to_pixel(self.center_lat, self.center_lng, self.zoom);
+        let size = self.tile_size as f64 * 2f64.powf(self.zoom - tile.z as f64);
+        let (dx, dy) = self.world_to_screen_offset(
+            (tile.x as f64 + 0.5) * size - center_pixel.0,
+            (tile.y as f64 + 0.5) * size - center_pixel.1,
+        );
+        dx.hypot(dy)
+    }
 
-            // Create image element for tile
-            let image = HtmlImageElement::new().unwrap();
-            // Ensure CORS so textures can be used by WebGL
-            image.set_cross_origin(Some("anonymous"));
-
-            // Clone the tile key and context for the closure
-            let tile_key_clone = tile_key.clone();
-            let tile_key_clone2 = tile_key.clone(); // For error handler
-            let url_clone = url.clone();
-            let img_clone = image.clone();
-            let context_clone = context.clone();
-
-            // Set up onload handler
-            let onload_closure = Closure::wrap(Box::new(move || {
-                // Create WebGL texture from the loaded image
-                let texture = match context_clone.create_texture() {
-                    Some(tex) => tex,
-                    None => {
-                        web_sys::console::error_1(&JsValue::from_str(&format!(
-                            "Failed to create texture for tile: {}", tile_key_clone
-                        )));
-                        return;
-                    }
-                };
-                context_clone.bind_texture(WebGl2RenderingContext::TEXTURE_2D, Some(&texture));
+    // Tile URL from the layer's template, or its WMTS or WMS request
+    fn tile_url(&self, layer: &TileLayer, coord: &TileCoord) -> String {
+        if let Some(wmts) = &layer.wmts {
+            return wmts.tile_url(coord);
+        }
+        if let Some(wms) = &layer.wms {
+            let size = if self.pixel_ratio > 1.0 { layer.tile_size * 2 } else { layer.tile_size };
+            return wms.get_map_url(&layer.url_template, self.projection.code(), self.tile_crs_bbox(coord), size, size);
+        }
+        self.url_template_source(layer).tile_url(coord)
+    }
+
+    // The layer's URL template as a tile source
+    fn url_template_source(&self, layer: &TileLayer) -> UrlTemplateSource {
+        UrlTemplateSource {
+            template: layer.url_template.clone(),
+            subdomains: layer.subdomains.clone(),
+            tms: layer.tms,
+            // Large tiles are numbered by the zoom they are meant for
+            zoom_offset: layer.zoom_shift() + layer.zoom_offset,
+            // {r} requests double-resolution tiles on high-DPI screens
+            retina: self.pixel_ratio > 1.0,
+            format: if layer.vector.is_some() { TileFormat::Mvt } else { TileFormat::Image },
+        }
+    }
 
-                // Set texture parameters for proper tile rendering
-                context_clone.tex_parameteri(WebGl2RenderingContext::TEXTURE_2D, WebGl2RenderingContext::TEXTURE_WRAP_S, WebGl2RenderingContext::CLAMP_TO_EDGE as i32);
-                context_clone.tex_parameteri(WebGl2RenderingContext::TEXTURE_2D, WebGl2RenderingContext::TEXTURE_WRAP_T, WebGl2RenderingContext::CLAMP_TO_EDGE as i32);
-                context_clone.tex_parameteri(WebGl2RenderingContext::TEXTURE_2D, WebGl2RenderingContext::TEXTURE_MIN_FILTER, WebGl2RenderingContext::LINEAR as i32);
-                context_clone.tex_parameteri(WebGl2RenderingContext::TEXTURE_2D, WebGl2RenderingContext::TEXTURE_MAG_FILTER, WebGl2RenderingContext::LINEAR as i32);
+    /// Tile's extent `[min_x, min_y, max_x, max_y]` in the projection's CRS units
+    fn tile_crs_bbox(&self, coord: &TileCoord) -> [f64; 4] {
+        let tiles_across = (1u64 << coord.z) as f64;
+        let (min_x, max_y) = self.projection.to_crs(coord.x as f64 / tiles_across, coord.y as f64 / tiles_across);
+        let (max_x, min_y) = self.projection.to_crs((coord.x + 1) as f64 / tiles_across, (coord.y + 1) as f64 / tiles_across);
+        [min_x, min_y, max_x, max_y]
+    }
 
-                // Upload image to texture
-                let result = context_clone.tex_image_2d_with_u32_and_u32_and_html_image_element(
-                    WebGl2RenderingContext::TEXTURE_2D,
-                    0,
-                    WebGl2RenderingContext::RGBA as i32,
-                    WebGl2RenderingContext::RGBA,
-                    WebGl2RenderingContext::UNSIGNED_BYTE,
-                    &img_clone,
-                );
+    // Starts loading a tile, recording its request with the scheduler.
+    // Template layers go through their URL template source; WMTS and WMS
+    // layers build their own requests.
+    fn load_tile(&self, layer_id: u32, coord: TileCoord) {
+        let Some(layer) = self.tile_layers.iter().find(|layer| layer.id == layer_id) else {
+            return;
+        };
+        let source: Rc<dyn TileSource> = match &layer.source {
+            Some(source) => source.clone(),
+            None if layer.wmts.is_some() || layer.wms.is_some() => {
+                if let Some(download) = self.tile_download(layer, coord.clone()) {
+                    download.start(self.tile_url(layer, &coord));
+                }
+                return;
+            }
+            None => Rc::new(self.url_template_source(layer)),
+        };
+        self.load_source_tile(layer, source, coord);
+    }
+
+    // How a layer's tile is fetched once its URL is known
+    fn tile_download(&self, layer: &TileLayer, coord: TileCoord) -> Option<TileDownload> {
+        Some(TileDownload {
+            context: self.gl_state.as_ref()?.context.clone(),
+            tile_key: coord.key(layer.id),
+            coord,
+            projection_code: self.projection.code(),
+            vector: layer.is_vector(),
+            terrain: layer.terrain.clone(),
+            error_tile_url: layer.error_tile_url.clone(),
+            store: self.tile_store.clone(),
+        })
+    }
 
-                if let Ok(_) = result {
-                    // Store the texture
-                    TILE_TEXTURES.with(|store| {
-                        store.borrow_mut().insert(tile_key_clone.clone(), texture);
-                    });
+    // Loads a raster tile's image and uploads it into the texture cache
+    // Loads a tile image into a texture; terrain tiles are decoded into
+    // heights and their hillshading uploaded instead
+    fn load_tile_image(
+        context: &WebGl2RenderingContext,
+        tile_key: String,
+        coord: TileCoord,
+        url: &str,
+        error_tile_url: Option<String>,
+        terrain: Option<TerrainStyle>,
+    ) -> HtmlImageElement {
+        // Create image element for tile
+        let image = HtmlImageElement::new().unwrap();
+        // Ensure CORS so textures can be used by WebGL
+        image.set_cross_origin(Some("anonymous"));
+
+        // Clone the tile key and context for the closure
+        let tile_key_clone = tile_key.clone();
+        let tile_key_clone2 = tile_key.clone(); // For error handler
+        let url_clone = url.to_string();
+        let img_clone = image.clone();
+        let context_clone = context.clone();
+        let error_context = context.clone();
+        // Object URLs of archive tiles are released once the image is done with them
+        let object_url = url.starts_with("blob:").then(|| url.to_string());
+        let object_url_clone = object_url.clone();
+        let loaded_coord = coord.clone();
+
+        // Set up onload handler
+        let onload_closure = Closure::wrap(Box::new(move || {
+            if let Some(object_url) = &object_url {
+                let _ = web_sys::Url::revoke_object_url(object_url);
+            }
+            // Create WebGL texture from the loaded image
+            let texture = match context_clone.create_texture() {
+                Some(tex) => tex,
+                None => {
+                    web_sys::console::error_1(&JsValue::from_str(&format!(
+                        "Failed to create texture for tile: {}", tile_key_clone
+                    )));
+                    TILE_REQUESTS.with(|requests| requests.borrow_mut().failed(&tile_key_clone, js_sys::Date::now()));
+                    return;
                 }
-            }) as Box<dyn FnMut()>);
+            };
+            context_clone.bind_texture(WebGl2RenderingContext::TEXTURE_2D, Some(&texture));
+
+            // Set texture parameters for proper tile rendering
+            context_clone.tex_parameteri(WebGl2RenderingContext::TEXTURE_2D, WebGl2RenderingContext::TEXTURE_WRAP_S, WebGl2RenderingContext::CLAMP_TO_EDGE as i32);
+            context_clone.tex_parameteri(WebGl2RenderingContext::TEXTURE_2D, WebGl2RenderingContext::TEXTURE_WRAP_T, WebGl2RenderingContext::CLAMP_TO_EDGE as i32);
+            context_clone.tex_parameteri(WebGl2RenderingContext::TEXTURE_2D, WebGl2RenderingContext::TEXTURE_MIN_FILTER, WebGl2RenderingContext::LINEAR as i32);
+            context_clone.tex_parameteri(WebGl2RenderingContext::TEXTURE_2D, WebGl2RenderingContext::TEXTURE_MAG_FILTER, WebGl2RenderingContext::LINEAR as i32);
+
+            // Upload image to texture, sized as uploaded (RGBA, no mipmaps)
+            let result = match &terrain {
+                Some(terrain) => Self::upload_terrain_tile(&context_clone, &img_clone, &tile_key_clone, &loaded_coord, terrain),
+                None => context_clone
+                    .tex_image_2d_with_u32_and_u32_and_html_image_element(
+                        WebGl2RenderingContext::TEXTURE_2D,
+                        0,
+                        WebGl2RenderingContext::RGBA as i32,
+                        WebGl2RenderingContext::RGBA,
+                        WebGl2RenderingContext::UNSIGNED_BYTE,
+                        &img_clone,
+                    )
+                    .map(|_| img_clone.natural_width() as usize * img_clone.natural_height() as usize * 4),
+            };
 
-            image.set_onload(Some(onload_closure.as_ref().unchecked_ref()));
-            onload_closure.forget();
+            if let Ok(bytes) = result {
+                // Store the texture
+                let replaced = TILE_TEXTURES.with(|store| {
+                    store.borrow_mut().insert(tile_key_clone.clone(), texture, bytes)
+                });
+                if let Some(replaced) = replaced {
+                    context_clone.delete_texture(Some(&replaced));
+                }
+                TILE_REQUESTS.with(|requests| requests.borrow_mut().succeeded(&tile_key_clone));
+            } else {
+                context_clone.delete_texture(Some(&texture));
+                TILE_REQUESTS.with(|requests| requests.borrow_mut().failed(&tile_key_clone, js_sys::Date::now()));
+            }
+        }) as Box<dyn FnMut()>);
 
-            // Set up onerror handler
-            let onerror_closure = Closure::wrap(Box::new(move || {
-                web_sys::console::warn_1(&JsValue::from_str(&format!(
-                    "⚠️ Rustyleaf: Failed to load tile at zoom {}, x {}, y {}. URL: {}\n\
-                    This could be due to:\n\
-                    • Network connectivity issues\n\
-                    • Invalid tile service URL template\n\
-                    • Tile service rate limiting or unavailability\n\
-                    • CORS restrictions on the tile service\n\
-                    Consider checking your network connection and tile service URL.",
-                    tile_key_clone2.split('/').next().unwrap_or("unknown"),
-                    tile_key_clone2.split('/').nth(1).unwrap_or("unknown"),
-                    tile_key_clone2.split('/').nth(2).unwrap_or("unknown"),
-                    url_clone
-                )));
-            }) as Box<dyn FnMut()>);
+        image.set_onload(Some(onload_closure.as_ref().unchecked_ref()));
+        onload_closure.forget();
+
+        // Set up onerror handler
+        let onerror_closure = Closure::wrap(Box::new(move || {
+            if let Some(object_url) = &object_url_clone {
+                let _ = web_sys::Url::revoke_object_url(object_url);
+            }
+            // Retried later with backoff, until it has failed too often
+            let gave_up = TILE_REQUESTS.with(|requests| {
+                let mut requests = requests.borrow_mut();
+                requests.failed(&tile_key_clone2, js_sys::Date::now());
+                matches!(requests.state(&tile_key_clone2), TileRequestState::Failed { .. })
+            });
+            // Once given up on, the error tile is loaded and cached in its
+            // place, as a plain image rather than heights to shade
+            if let (true, Some(error_url)) = (gave_up, error_tile_url.as_ref()) {
+                ELEVATION_TILES.with(|tiles| tiles.borrow_mut().remove(&tile_key_clone2));
+                Self::load_tile_image(&error_context, tile_key_clone2.clone(), coord.clone(), error_url, None, None);
+            }
+            web_sys::console::warn_1(&JsValue::from_str(&format!(
+                "⚠️ Rustyleaf: Failed to load tile at zoom {}, x {}, y {}. URL: {}\n\
+                This could be due to:\n\
+                • Network connectivity issues\n\
+                • Invalid tile service URL template\n\
+                • Tile service rate limiting or unavailability\n\
+                • CORS restrictions on the tile service\n\
+                Consider checking your network connection and tile service URL.",
+                coord.z,
+                coord.x,
+                coord.y,
+                url_clone
+            )));
+        }) as Box<dyn FnMut()>);
 
-            image.set_onerror(Some(onerror_closure.as_ref().unchecked_ref()));
-            onerror_closure.forget();
+        image.set_onerror(Some(onerror_closure.as_ref().unchecked_ref()));
+        onerror_closure.forget();
 
-            // Start loading the image
-            image.set_src(&url);
-        }
+        // Start loading the image
+        image.set_src(url);
+        image
     }
 
-    fn lat_lng_to_pixel(&self, lat: f64, lng: f64, zoom: u32) -> (f64, f64) {
-        // Clamp latitude to Web Mercator bounds
-        let clamped_lat = lat.max(-85.05112878).min(85.05112878);
-
-        let n = (1u32 << zoom) as f64;
+    // Decodes a loaded terrain tile into heights, kept for elevation queries,
+    // and uploads its hillshading into the bound texture. Returns the memory
+    // held by both.
+    fn upload_terrain_tile(context: &WebGl2RenderingContext, image: &HtmlImageElement, tile_key: &str, coord: &TileCoord, terrain: &TerrainStyle) -> Result<usize, JsValue> {
+        let (width, height) = (image.natural_width(), image.natural_height());
+        let canvas_context = TERRAIN_CANVAS.with(|canvas_context| -> Result<CanvasRenderingContext2d, JsValue> {
+            let mut canvas_context = canvas_context.borrow_mut();
+            if let Some(canvas_context) = canvas_context.as_ref() {
+                return Ok(canvas_context.clone());
+            }
+            let document = window().and_then(|window| window.document()).ok_or_else(|| JsValue::from_str("Document not available"))?;
+            let canvas: HtmlCanvasElement = document.create_element("canvas")?.dyn_into()?;
+            let created: CanvasRenderingContext2d = canvas
+                .get_context("2d")?
+                .ok_or_else(|| JsValue::from_str("2D canvas not available"))?
+                .dyn_into()?;
+            *canvas_context = Some(created.clone());
+            Ok(created)
+        })?;
+        let canvas = canvas_context.canvas().ok_or_else(|| JsValue::from_str("2D canvas not available"))?;
+        // Sizing the canvas also clears what the last tile left on it
+        canvas.set_width(width);
+        canvas.set_height(height);
+        canvas_context.draw_image_with_html_image_element(image, 0.0, 0.0)?;
+        let rgba = canvas_context.get_image_data(0.0, 0.0, width as f64, height as f64)?.data();
+
+        let elevation = ElevationTile::decode(&rgba, width as usize, height as usize, coord.z, coord.y, terrain.encoding)
+            .map_err(|error| JsValue::from_str(&error))?;
+        let pixels = terrain.hillshade.shade(&elevation);
+        Self::upload_tile_pixels(context, &elevation, &pixels)?;
+        let bytes = pixels.len() + elevation.bytes();
+        ELEVATION_TILES.with(|tiles| tiles.borrow_mut().insert(tile_key.to_string(), elevation));
+        Ok(bytes)
+    }
+
+    // Uploads RGBA pixels the size of an elevation tile into the bound texture
+    fn upload_tile_pixels(context: &WebGl2RenderingContext, elevation: &ElevationTile, pixels: &[u8]) -> Result<(), JsValue> {
+        context.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+            WebGl2RenderingContext::TEXTURE_2D,
+            0,
+            WebGl2RenderingContext::RGBA as i32,
+            elevation.width as i32,
+            elevation.height as i32,
+            0,
+            WebGl2RenderingContext::RGBA,
+            WebGl2RenderingContext::UNSIGNED_BYTE,
+            Some(pixels),
+        )
+    }
+
+    // Requests a vector tile's protobuf; it is decoded into the vector tile
+    // cache once it arrives
+    fn fetch_vector_tile(tile_key: String, coord: TileCoord, projection_code: &'static str, url: String) -> Option<XmlHttpRequest> {
+        let xhr = XmlHttpRequest::new().ok()?;
+        xhr.open("GET", &url).ok()?;
+        xhr.set_response_type(web_sys::XmlHttpRequestResponseType::Arraybuffer);
 
-        // X: linear with longitude
-        let x_tile = (lng + 180.0) / 360.0 * n;
+        let xhr_clone = xhr.clone();
+        let tile_key_clone = tile_key.clone();
+        let onload_closure = Closure::wrap(Box::new(move || {
+            let status = xhr_clone.status().unwrap_or(0);
+            // Servers answer 204 No Content for tiles without features
+            let data = if (200..300).contains(&status) {
+                Ok(xhr_clone.response().map(|response| js_sys::Uint8Array::new(&response).to_vec()).unwrap_or_default())
+            } else {
+                Err(format!("HTTP {}", status))
+            };
+            Self::store_vector_tile(&tile_key_clone, &coord, projection_code, data);
+        }) as Box<dyn FnMut()>);
+        xhr.set_onload(Some(onload_closure.as_ref().unchecked_ref()));
+        onload_closure.forget();
+
+        let onerror_closure = Closure::wrap(Box::new(move || {
+            // Retried later with backoff, until it has failed too often
+            TILE_REQUESTS.with(|requests| requests.borrow_mut().failed(&tile_key, js_sys::Date::now()));
+            web_sys::console::warn_1(&JsValue::from_str(&format!("⚠️ Rustyleaf: Failed to load vector tile. URL: {}", url)));
+        }) as Box<dyn FnMut()>);
+        xhr.set_onerror(Some(onerror_closure.as_ref().unchecked_ref()));
+        onerror_closure.forget();
+
+        xhr.send().ok()?;
+        Some(xhr)
+    }
+
+    // Decodes a vector tile's protobuf into the vector tile cache, or records
+    // the request as failed
+    fn store_vector_tile(tile_key: &str, coord: &TileCoord, projection_code: &'static str, data: Result<Vec<u8>, String>) {
+        let decoded = data.and_then(|data| mvt::decode(&data, 1.0)).and_then(|layers| {
+            let projection = projection_from_code(projection_code)
+                .ok_or_else(|| format!("Unsupported projection: {}", projection_code))?;
+            Ok(vector_tile_features(layers, coord, projection.as_ref()))
+        });
+        match decoded {
+            Ok(features) => {
+                let bytes = features.iter().map(|feature| feature.geometry.vertex_count() * 16 + 64).sum();
+                VECTOR_TILES.with(|store| store.borrow_mut().insert(tile_key.to_string(), Rc::new(features), bytes));
+                VECTOR_TILE_GEOMETRY.with(|geometry| geometry.borrow_mut().remove(tile_key));
+                TILE_REQUESTS.with(|requests| requests.borrow_mut().succeeded(tile_key));
+            }
+            Err(error) => {
+                TILE_REQUESTS.with(|requests| requests.borrow_mut().failed(tile_key, js_sys::Date::now()));
+                web_sys::console::warn_1(&JsValue::from_str(&format!(
+                    "⚠️ Rustyleaf: Failed to load vector tile at zoom {}, x {}, y {}: {}",
+                    coord.z, coord.x, coord.y, error
+                )));
+            }
+        }
+    }
 
-        // Y: Web Mercator projection (matches inverse atan(sinh(...)))
-        let lat_rad = clamped_lat.to_radians();
-        let y_tile = (1.0 - ((std::f64::consts::FRAC_PI_4 + lat_rad / 2.0).tan().ln() / std::f64::consts::PI)) / 2.0 * n;
+    // Asks the layer's tile source for a tile. URLs are downloaded, or taken
+    // from the tile store if seeded; vector tiles held in memory are decoded
+    // like fetched ones and images go through the texture path from an object
+    // URL; tiles the source lacks are left blank. Sources holding tiles in
+    // memory answer at once, so the request is recorded as started here,
+    // before asking.
+    fn load_source_tile(&self, layer: &TileLayer, source: Rc<dyn TileSource>, coord: TileCoord) {
+        let Some(mut download) = self.tile_download(layer, coord.clone()) else {
+            return;
+        };
+        let cancelled = Rc::new(Cell::new(false));
+        TILE_REQUESTS.with(|requests| requests.borrow_mut().started(download.tile_key.clone(), TileRequestHandle::Source(cancelled.clone())));
+        let format_source = source.clone();
+        source.load(&coord, Box::new(move |tile| {
+            if cancelled.get() {
+                return;
+            }
+            download.vector = format_source.format() == Some(TileFormat::Mvt);
+            match tile {
+                Ok(Some(TileData::Url(url))) => download.start(url),
+                Ok(Some(TileData::Bytes(data))) => {
+                    let TileDownload { context, tile_key, coord, projection_code, vector, terrain, .. } = download;
+                    Self::load_tile_bytes(&context, tile_key, coord, projection_code, vector, terrain, data);
+                }
+                Ok(None) if download.vector => {
+                    Self::store_vector_tile(&download.tile_key, &download.coord, download.projection_code, Ok(Vec::new()));
+                }
+                Ok(None) => {
+                    Self::load_tile_image(&download.context, download.tile_key, download.coord, EMPTY_IMAGE_URL, None, None);
+                }
+                Err(error) => {
+                    let coord = &download.coord;
+                    TILE_REQUESTS.with(|requests| requests.borrow_mut().failed(&download.tile_key, js_sys::Date::now()));
+                    web_sys::console::warn_1(&JsValue::from_str(&format!(
+                        "⚠️ Rustyleaf: Failed to load tile at zoom {}, x {}, y {}: {}",
+                        coord.z, coord.x, coord.y, error
+                    )));
+                }
+            }
+        }));
+    }
+
+    // Loads a tile held in memory: vector tiles are decoded into the vector
+    // tile cache, images go through the texture path from an object URL
+    fn load_tile_bytes(
+        context: &WebGl2RenderingContext,
+        tile_key: String,
+        coord: TileCoord,
+        projection_code: &'static str,
+        vector: bool,
+        terrain: Option<TerrainStyle>,
+        data: Vec<u8>,
+    ) {
+        if vector {
+            return Self::store_vector_tile(&tile_key, &coord, projection_code, Ok(data));
+        }
+        match Self::object_url(&data) {
+            Ok(url) => {
+                // Recorded in place of the request that answered, so
+                // cancelling the tile aborts the image too
+                let image = Self::load_tile_image(context, tile_key.clone(), coord, &url, None, terrain);
+                TILE_REQUESTS.with(|requests| requests.borrow_mut().started(tile_key, TileRequestHandle::Image(image)));
+            }
+            Err(_) => {
+                TILE_REQUESTS.with(|requests| requests.borrow_mut().failed(&tile_key, js_sys::Date::now()));
+            }
+        }
+    }
 
-        // Convert to pixel coordinates
-        (x_tile * self.tile_size as f64, y_tile * self.tile_size as f64)
+    // Object URL for an image held in memory; browsers tell the image format
+    // from its bytes
+    fn object_url(data: &[u8]) -> Result<String, JsValue> {
+        let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(data));
+        let blob = web_sys::Blob::new_with_u8_array_sequence(&parts)?;
+        web_sys::Url::create_object_url_with_blob(&blob)
     }
 
-    fn pixel_to_lat_lng(&self, x: f64, y: f64, zoom: u32) -> (f64, f64) {
-        let n = (1u32 << zoom) as f64;
-        let tile_x = x / self.tile_size as f64;
-        let tile_y = y / self.tile_size as f64;
+    // Size of the whole world in pixels at a (possibly fractional) zoom level
+    fn world_size(&self, zoom: f64) -> f64 {
+        self.tile_size as f64 * 2f64.powf(zoom)
+    }
 
-        // Inverse for longitude
-        let lng = tile_x / n * 360.0 - 180.0;
+    fn lat_lng_to_pixel(&self, lat: f64, lng: f64, zoom: f64) -> (f64, f64) {
+        let world_size = self.world_size(zoom);
+        let (x_world, y_world) = self.projection.project(lat, lng);
 
-        // Inverse Web Mercator for latitude
-        let a = std::f64::consts::PI * (1.0 - 2.0 * (tile_y / n));
-        let lat_rad = a.sinh().atan();
-        let lat = lat_rad.to_degrees();
+        // Convert to pixel coordinates
+        (x_world * world_size, y_world * world_size)
+    }
 
-        (lat, lng)
+    fn pixel_to_lat_lng(&self, x: f64, y: f64, zoom: f64) -> (f64, f64) {
+        let world_size = self.world_size(zoom);
+        self.projection.unproject(x / world_size, y / world_size)
     }
 
     // Event handling methods (simplified)
@@ -1494,105 +4090,115 @@ impl RustyleafMap {
     // Public event registration methods
     #[wasm_bindgen]
     pub fn on_move(&mut self, callback: &js_sys::Function) {
-        self.move_callbacks.push(Box::new(callback.clone()));
+        self.move_callbacks.push(callback.clone());
     }
 
     #[wasm_bindgen]
     pub fn on_zoom(&mut self, callback: &js_sys::Function) {
-        self.zoom_callbacks.push(Box::new(callback.clone()));
+        self.zoom_callbacks.push(callback.clone());
     }
 
     #[wasm_bindgen]
     pub fn on_click(&mut self, callback: &js_sys::Function) {
-        self.click_callbacks.push(Box::new(callback.clone()));
+        self.click_callbacks.push(callback.clone());
     }
 
     #[wasm_bindgen]
     pub fn on_hover(&mut self, callback: &js_sys::Function) {
-        self.hover_callbacks.push(Box::new(callback.clone()));
+        self.hover_callbacks.push(callback.clone());
     }
 
     // Event removal methods - remove all matching callbacks
     #[wasm_bindgen]
     pub fn off_move(&mut self, callback: &js_sys::Function) {
-        self.move_callbacks.retain(|cb| cb.as_ref() != callback);
+        self.move_callbacks.retain(|cb| cb != callback);
     }
 
     #[wasm_bindgen]
     pub fn off_zoom(&mut self, callback: &js_sys::Function) {
-        self.zoom_callbacks.retain(|cb| cb.as_ref() != callback);
+        self.zoom_callbacks.retain(|cb| cb != callback);
     }
 
     #[wasm_bindgen]
     pub fn off_click(&mut self, callback: &js_sys::Function) {
-        self.click_callbacks.retain(|cb| cb.as_ref() != callback);
+        self.click_callbacks.retain(|cb| cb != callback);
     }
 
     #[wasm_bindgen]
     pub fn off_hover(&mut self, callback: &js_sys::Function) {
-        self.hover_callbacks.retain(|cb| cb.as_ref() != callback);
+        self.hover_callbacks.retain(|cb| cb != callback);
     }
 
     // Additional event registration methods
     #[wasm_bindgen]
     pub fn on_mouse_down(&mut self, callback: &js_sys::Function) {
-        self.mousedown_callbacks.push(Box::new(callback.clone()));
+        self.mousedown_callbacks.push(callback.clone());
     }
 
     #[wasm_bindgen]
     pub fn on_mouse_up(&mut self, callback: &js_sys::Function) {
-        self.mouseup_callbacks.push(Box::new(callback.clone()));
+        self.mouseup_callbacks.push(callback.clone());
     }
 
     #[wasm_bindgen]
     pub fn on_contextmenu(&mut self, callback: &js_sys::Function) {
-        self.contextmenu_callbacks.push(Box::new(callback.clone()));
+        self.contextmenu_callbacks.push(callback.clone());
     }
 
     #[wasm_bindgen]
     pub fn on_key_down(&mut self, callback: &js_sys::Function) {
-        self.keydown_callbacks.push(Box::new(callback.clone()));
+        self.keydown_callbacks.push(callback.clone());
     }
 
     #[wasm_bindgen]
     pub fn on_key_up(&mut self, callback: &js_sys::Function) {
-        self.keyup_callbacks.push(Box::new(callback.clone()));
+        self.keyup_callbacks.push(callback.clone());
     }
 
     // Additional event removal methods
     #[wasm_bindgen]
     pub fn off_mouse_down(&mut self, callback: &js_sys::Function) {
-        self.mousedown_callbacks.retain(|cb| cb.as_ref() != callback);
+        self.mousedown_callbacks.retain(|cb| cb != callback);
     }
 
     #[wasm_bindgen]
     pub fn off_mouse_up(&mut self, callback: &js_sys::Function) {
-        self.mouseup_callbacks.retain(|cb| cb.as_ref() != callback);
+        self.mouseup_callbacks.retain(|cb| cb != callback);
     }
 
     #[wasm_bindgen]
     pub fn off_contextmenu(&mut self, callback: &js_sys::Function) {
-        self.contextmenu_callbacks.retain(|cb| cb.as_ref() != callback);
+        self.contextmenu_callbacks.retain(|cb| cb != callback);
     }
 
     #[wasm_bindgen]
     pub fn off_key_down(&mut self, callback: &js_sys::Function) {
-        self.keydown_callbacks.retain(|cb| cb.as_ref() != callback);
+        self.keydown_callbacks.retain(|cb| cb != callback);
     }
 
     #[wasm_bindgen]
     pub fn off_key_up(&mut self, callback: &js_sys::Function) {
-        self.keyup_callbacks.retain(|cb| cb.as_ref() != callback);
+        self.keyup_callbacks.retain(|cb| cb != callback);
     }
 
   #[wasm_bindgen]
     pub fn on_dragend(&mut self, callback: &js_sys::Function) {
-        self.dragend_callbacks.push(Box::new(callback.clone()));
+        self.dragend_callbacks.push(callback.clone());
     }
 
   #[wasm_bindgen]
     pub fn off_dragend(&mut self, callback: &js_sys::Function) {
-        self.dragend_callbacks.retain(|cb| cb.as_ref() != callback);
+        self.dragend_callbacks.retain(|cb| cb != callback);
+    }
+
+    #[wasm_bindgen]
+    pub fn on_moveend(&mut self, callback: &js_sys::Function) {
+        self.moveend_callbacks.push(callback.clone());
+    }
+
+    #[wasm_bindgen]
+    pub fn off_moveend(&mut self, callback: &js_sys::Function) {
+        self.moveend_callbacks.retain(|cb| cb != callback);
     }
 
     // Event trigger methods
@@ -1660,6 +4266,14 @@ impl RustyleafMap {
         }
     }
 
+    fn trigger_moveend_event(&self) {
+        if let Ok(event_obj) = self.create_map_event("moveend") {
+            for callback in &self.moveend_callbacks {
+                let _ = callback.call1(&JsValue::null(), &event_obj);
+            }
+        }
+    }
+
     // Event object creation methods
     fn create_map_event(&self, event_type: &str) -> Result<JsValue, JsValue> {
         let obj = js_sys::Object::new();
@@ -1732,21 +4346,38 @@ impl RustyleafMap {
     // Public methods for JavaScript
     #[wasm_bindgen]
     pub fn pan(&mut self, delta_x: f64, delta_y: f64) {
-        let zoom = self.zoom.round() as u32;
+        self.stop_camera_animation();
+        self.pan_by_pixels(delta_x, delta_y);
+    }
+
+    // Pans the view by a screen offset and returns the screen offset actually
+    // applied once max bounds have been taken into account
+    fn pan_by_pixels(&mut self, delta_x: f64, delta_y: f64) -> (f64, f64) {
+        let zoom = self.zoom;
         let pixel_center = self.lat_lng_to_pixel(self.center_lat, self.center_lng, zoom);
 
         // Note: delta_x and delta_y are in screen pixels (standard web coordinates)
         // Positive delta_x means mouse moved right, so we want to show area to the left (west)
         // Positive delta_y means mouse moved down, so we want to show area above (north)
         // This is the standard behavior: dragging down shows what's above the current view
-        let new_pixel_x = pixel_center.0 - delta_x;
-        let new_pixel_y = pixel_center.1 - delta_y;
+        let (world_dx, world_dy) = self.screen_to_world_offset(delta_x, delta_y);
+        let target = (pixel_center.0 - world_dx, pixel_center.1 - world_dy);
+
+        // Max bounds are elastic only while the user drags or flings the map
+        let elastic = self.mouse_state.is_dragging || self.has_momentum;
+        let (mut new_pixel_x, mut new_pixel_y) = self.constrain_pan(pixel_center, target, zoom, elastic);
+
+        // Keep the center on the projected world. For Web Mercator this is the
+        // usual +/-85.05 degree latitude limit; only wrapping projections may
+        // leave it horizontally.
+        let world_size = self.world_size(zoom);
+        new_pixel_y = new_pixel_y.clamp(0.0, world_size);
+        if !self.projection.wraps() {
+            new_pixel_x = new_pixel_x.clamp(0.0, world_size);
+        }
 
-        let (new_lat, new_lng) = self.pixel_to_lat_lng(new_pixel_x, new_pixel_y, zoom);
+        let (clamped_lat, new_lng) = self.pixel_to_lat_lng(new_pixel_x, new_pixel_y, zoom);
 
-        // Clamp coordinates to valid Web Mercator ranges
-        // Latitude: -85.05112878 to 85.05112878 degrees (to avoid singularity at poles)
-        let clamped_lat = new_lat.max(-85.05112878).min(85.05112878);
         // Longitude: -180 to 180 degrees (wrap around)
         let mut clamped_lng = new_lng;
         while clamped_lng > 180.0 {
@@ -1762,13 +4393,136 @@ impl RustyleafMap {
             self.center_lng = clamped_lng;
             self.load_visible_tiles();
             self.trigger_move_event();
+            return self.world_to_screen_offset(pixel_center.0 - new_pixel_x, pixel_center.1 - new_pixel_y);
+        }
+        (0.0, 0.0)
+    }
+
+    /// Animates to the given view along a zoom-out-then-in arc. `options` may
+    /// set `duration` (ms) and `easing` (a name or a function of t in [0, 1]).
+    #[wasm_bindgen]
+    pub fn fly_to(&mut self, lat: f64, lng: f64, zoom: f64, options: &JsValue) -> Result<(), JsValue> {
+        let (duration, easing) = Self::parse_animation_options(options)?;
+        let (to, to_zoom) = self.camera_target(lat, lng, zoom)?;
+        let from = self.lat_lng_to_pixel(self.center_lat, self.center_lng, 0.0);
+        let viewport = self.width.max(self.height) as f64;
+        let animation = CameraAnimation::fly(from, self.zoom, to, to_zoom, viewport, js_sys::Date::now(), duration, easing);
+        self.start_camera_animation(animation);
+        Ok(())
+    }
+
+    /// Animates center and zoom linearly to the given view. Takes the same
+    /// options as `fly_to`.
+    #[wasm_bindgen]
+    pub fn ease_to(&mut self, lat: f64, lng: f64, zoom: f64, options: &JsValue) -> Result<(), JsValue> {
+        let (duration, easing) = Self::parse_animation_options(options)?;
+        let (to, to_zoom) = self.camera_target(lat, lng, zoom)?;
+        let from = self.lat_lng_to_pixel(self.center_lat, self.center_lng, 0.0);
+        let animation = CameraAnimation::ease(from, self.zoom, to, to_zoom, js_sys::Date::now(), duration, easing);
+        self.start_camera_animation(animation);
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    pub fn is_animating(&self) -> bool {
+        self.camera_animation.is_some()
+    }
+
+    fn parse_animation_options(options: &JsValue) -> Result<(Option<f64>, Easing), JsValue> {
+        if options.is_undefined() || options.is_null() {
+            return Ok((None, Easing::EaseInOut));
+        }
+
+        let duration = js_sys::Reflect::get(options, &JsValue::from_str("duration"))?;
+        let duration = if duration.is_undefined() || duration.is_null() {
+            None
+        } else {
+            match duration.as_f64() {
+                Some(ms) if ms.is_finite() && ms >= 0.0 => Some(ms),
+                _ => return Err(JsValue::from_str("Animation duration must be a non-negative number of milliseconds")),
+            }
+        };
+
+        let easing = Easing::from_js(&js_sys::Reflect::get(options, &JsValue::from_str("easing"))?)?;
+        Ok((duration, easing))
+    }
+
+    // Target center in world pixels at zoom 0, plus the clamped target zoom
+    fn camera_target(&self, lat: f64, lng: f64, zoom: f64) -> Result<((f64, f64), f64), JsValue> {
+        if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lng) {
+            return Err(JsValue::from_str("Invalid coordinates: lat must be in [-90, 90] and lng in [-180, 180]"));
+        }
+        if !zoom.is_finite() {
+            return Err(JsValue::from_str("Zoom must be a finite number"));
+        }
+
+        let from = self.lat_lng_to_pixel(self.center_lat, self.center_lng, 0.0);
+        let mut to = self.lat_lng_to_pixel(lat, lng, 0.0);
+
+        // Travel the short way round across the antimeridian
+        let world = self.world_size(0.0);
+        let wraps = self.projection.wraps();
+        if wraps && to.0 - from.0 > world / 2.0 {
+            to.0 -= world;
+        } else if wraps && from.0 - to.0 > world / 2.0 {
+            to.0 += world;
+        }
+        Ok((to, self.clamp_zoom(zoom)))
+    }
+
+    fn start_camera_animation(&mut self, animation: CameraAnimation) {
+        self.stop_camera_animation();
+        self.has_momentum = false;
+        self.drag_velocity = (0.0, 0.0);
+        self.camera_animation = Some(animation);
+        self.schedule_render();
+    }
+
+    // Cancels a running animation, leaving the view where it currently is
+    fn stop_camera_animation(&mut self) {
+        if self.camera_animation.take().is_some() {
+            self.trigger_moveend_event();
+        }
+    }
+
+    fn step_camera_animation(&mut self, now: f64) {
+        let ((x, y), zoom, done) = match self.camera_animation {
+            Some(ref animation) => animation.frame(now),
+            None => return,
+        };
+
+        let (lat, mut lng) = self.pixel_to_lat_lng(x, y, 0.0);
+        while lng > 180.0 {
+            lng -= 360.0;
+        }
+        while lng < -180.0 {
+            lng += 360.0;
+        }
+
+        let previous_zoom = self.zoom;
+        self.center_lat = lat;
+        self.center_lng = lng;
+        self.zoom = self.clamp_zoom(zoom);
+        self.constrain_view();
+        self.load_visible_tiles();
+        self.trigger_move_event();
+        if self.zoom != previous_zoom {
+            self.trigger_zoom_event();
+        }
+
+        if done {
+            self.camera_animation = None;
+            self.trigger_moveend_event();
         }
     }
 
     #[wasm_bindgen]
     pub fn zoom_in(&mut self) {
-        if self.zoom < 18.0 {
-            self.zoom += 1.0;
+        self.stop_camera_animation();
+        let new_zoom = self.clamp_zoom(self.zoom + 1.0);
+        if new_zoom != self.zoom {
+            self.zoom = new_zoom;
+            self.constrain_view();
             self.load_visible_tiles();
             self.trigger_zoom_event();
         }
@@ -1776,8 +4530,11 @@ impl RustyleafMap {
 
     #[wasm_bindgen]
     pub fn zoom_out(&mut self) {
-        if self.zoom > 1.0 {
-            self.zoom -= 1.0;
+        self.stop_camera_animation();
+        let new_zoom = self.clamp_zoom(self.zoom - 1.0);
+        if new_zoom != self.zoom {
+            self.zoom = new_zoom;
+            self.constrain_view();
             self.load_visible_tiles();
             self.trigger_zoom_event();
         }
@@ -1798,28 +4555,93 @@ impl RustyleafMap {
     }
 
     #[wasm_bindgen]
-    pub fn set_min_zoom(&mut self, _min_zoom: f64) {
-        // Store min zoom constraint (implementation would need zoom limit checking)
+    pub fn set_min_zoom(&mut self, min_zoom: f64) {
+        if !min_zoom.is_finite() {
+            return;
+        }
+        self.min_zoom = min_zoom.max(0.0);
+        if self.max_zoom < self.min_zoom {
+            self.max_zoom = self.min_zoom;
+        }
+        self.apply_zoom_constraints();
+    }
+
+    #[wasm_bindgen]
+    pub fn set_max_zoom(&mut self, max_zoom: f64) {
+        if !max_zoom.is_finite() {
+            return;
+        }
+        self.max_zoom = max_zoom.max(0.0);
+        if self.min_zoom > self.max_zoom {
+            self.min_zoom = self.max_zoom;
+        }
+        self.apply_zoom_constraints();
+    }
+
+    #[wasm_bindgen]
+    pub fn get_min_zoom(&self) -> f64 {
+        self.min_zoom
+    }
+
+    #[wasm_bindgen]
+    pub fn get_max_zoom(&self) -> f64 {
+        self.max_zoom
+    }
+
+    /// Restricts the viewport to `[sw_lat, sw_lng, ne_lat, ne_lng]`, or lifts
+    /// the restriction when passed `null`/`undefined`.
+    #[wasm_bindgen]
+    pub fn set_max_bounds(&mut self, bounds_data: &JsValue) -> Result<(), JsValue> {
+        self.max_bounds = parse_bounds(bounds_data, "Max bounds")?;
+        if self.max_bounds.is_none() {
+            return Ok(());
+        }
+
+        // Pull the current view inside the new bounds
+        self.set_view(self.center_lat, self.center_lng, self.zoom);
+        Ok(())
+    }
+
+    /// How strongly max bounds resist dragging: 0.0 lets the map be dragged
+    /// past them and spring back on release, 1.0 (the default) stops it dead.
+    #[wasm_bindgen]
+    pub fn set_max_bounds_viscosity(&mut self, viscosity: f64) {
+        if viscosity.is_finite() {
+            self.max_bounds_viscosity = viscosity.clamp(0.0, 1.0);
+        }
+    }
+
+    /// Draws tiles and vector layers on every copy of the world visible east
+    /// or west of the canonical one, so panning across the antimeridian is seamless.
+    #[wasm_bindgen]
+    pub fn set_render_world_copies(&mut self, enabled: bool) {
+        self.render_world_copies = enabled;
+        self.load_visible_tiles();
+        self.schedule_render();
     }
 
     #[wasm_bindgen]
-    pub fn set_max_zoom(&mut self, _max_zoom: f64) {
-        // Store max zoom constraint (implementation would need zoom limit checking)
+    pub fn get_render_world_copies(&self) -> bool {
+        self.render_world_copies
+    }
+
+    // Re-zoom into the allowed range after the constraints changed
+    fn apply_zoom_constraints(&mut self) {
+        let constrained = self.clamp_zoom(self.zoom);
+        if constrained != self.zoom {
+            self.set_view(self.center_lat, self.center_lng, constrained);
+            self.trigger_zoom_event();
+        }
     }
 
     #[wasm_bindgen]
     pub fn get_bounds(&self) -> Array {
-        // Calculate current visible bounds based on center, zoom, and viewport dimensions
-        let zoom = self.zoom.round() as u32;
-        let center_pixel = self.lat_lng_to_pixel(self.center_lat, self.center_lng, zoom);
-        
-        let start_x = center_pixel.0 - (self.width as f64 / 2.0);
-        let start_y = center_pixel.1 - (self.height as f64 / 2.0);
-        let end_x = center_pixel.0 + (self.width as f64 / 2.0);
-        let end_y = center_pixel.1 + (self.height as f64 / 2.0);
-        
-        let (sw_lat, sw_lng) = self.pixel_to_lat_lng(start_x, end_y, zoom);
-        let (ne_lat, ne_lng) = self.pixel_to_lat_lng(end_x, start_y, zoom);
+        // Envelope of the visible quad; equal to its corners when north is up
+        let corners = self.viewport_corners();
+        let sw_lat = corners.iter().map(|c| c.0).fold(f64::INFINITY, f64::min);
+        let sw_lng = corners.iter().map(|c| c.1).fold(f64::INFINITY, f64::min);
+        let ne_lat = corners.iter().map(|c| c.0).fold(f64::NEG_INFINITY, f64::max);
+        let ne_lng = corners.iter().map(|c| c.1).fold(f64::NEG_INFINITY, f64::max);
         
         let arr = Array::new();
         arr.push(&JsValue::from_f64(sw_lat));
@@ -1829,8 +4651,84 @@ impl RustyleafMap {
         arr
     }
 
+    /// Lat/lng of the viewport's top-left, top-right, bottom-right and
+    /// bottom-left corners. Unlike `get_bounds` this follows the bearing.
+    #[wasm_bindgen]
+    pub fn get_bounds_quad(&self) -> Array {
+        let quad = Array::new();
+        for (lat, lng) in self.viewport_corners() {
+            let corner = Array::new();
+            corner.push(&JsValue::from_f64(lat));
+            corner.push(&JsValue::from_f64(lng));
+            quad.push(&corner);
+        }
+        quad
+    }
+
+    fn viewport_corners(&self) -> [(f64, f64); 4] {
+        let (width, height) = (self.width as f64, self.height as f64);
+        [
+            self.screen_to_lat_lng(0.0, 0.0),
+            self.screen_to_lat_lng(width, 0.0),
+            self.screen_to_lat_lng(width, height),
+            self.screen_to_lat_lng(0.0, height),
+        ]
+    }
+
+    /// Rotates the map so the given compass direction (degrees clockwise
+    /// from north) points up.
     #[wasm_bindgen]
-    pub fn fit_bounds(&mut self, bounds_data: &JsValue) -> Result<(), JsValue> {
+    pub fn set_bearing(&mut self, bearing: f64) {
+        if !bearing.is_finite() {
+            return;
+        }
+        // Normalize into (-180, 180]
+        let normalized = 180.0 - (180.0 - bearing).rem_euclid(360.0);
+        if normalized == self.bearing {
+            return;
+        }
+        self.bearing = normalized;
+        self.constrain_view();
+        self.load_visible_tiles();
+        self.schedule_render();
+        self.trigger_move_event();
+    }
+
+    #[wasm_bindgen]
+    pub fn get_bearing(&self) -> f64 {
+        self.bearing
+    }
+
+    /// Tilts the camera away from looking straight down, in degrees from 0
+    /// up to 60. The ground is drawn in perspective with lower-detail tiles
+    /// toward the horizon.
+    #[wasm_bindgen]
+    pub fn set_pitch(&mut self, pitch: f64) {
+        if !pitch.is_finite() {
+            return;
+        }
+        let pitch = pitch.clamp(0.0, MAX_PITCH);
+        if pitch == self.pitch {
+            return;
+        }
+        self.pitch = pitch;
+        self.constrain_view();
+        self.load_visible_tiles();
+        self.schedule_render();
+        self.trigger_move_event();
+    }
+
+    #[wasm_bindgen]
+    pub fn get_pitch(&self) -> f64 {
+        self.pitch
+    }
+
+    /// Fits `[sw_lat, sw_lng, ne_lat, ne_lng]` in the viewport. Bounds whose
+    /// `ne_lng` is less than `sw_lng` cross the antimeridian. `options` may set
+    /// `padding` (a number, `[top, right, bottom, left]` or an object with those
+    /// keys, in pixels) and `maxZoom`.
+    #[wasm_bindgen]
+    pub fn fit_bounds(&mut self, bounds_data: &JsValue, options: &JsValue) -> Result<(), JsValue> {
         // Validate input is an array
         let bounds_array = js_sys::Array::from(bounds_data);
         
@@ -1845,137 +4743,480 @@ impl RustyleafMap {
         let ne_lng = bounds_array.get(3).as_f64().ok_or_else(|| JsValue::from_str("ne_lng must be a number"))?;
         
         // Validate coordinate ranges
-        if !(sw_lat >= -90.0 && sw_lat <= 90.0) {
+        if !(-90.0..=90.0).contains(&sw_lat) {
             return Err(JsValue::from_str("sw_lat must be between -90 and 90"));
         }
-        if !(sw_lng >= -180.0 && sw_lng <= 180.0) {
+        if !(-180.0..=180.0).contains(&sw_lng) {
             return Err(JsValue::from_str("sw_lng must be between -180 and 180"));
         }
-        if !(ne_lat >= -90.0 && ne_lat <= 90.0) {
+        if !(-90.0..=90.0).contains(&ne_lat) {
             return Err(JsValue::from_str("ne_lat must be between -90 and 90"));
         }
-        if !(ne_lng >= -180.0 && ne_lng <= 180.0) {
+        if !(-180.0..=180.0).contains(&ne_lng) {
             return Err(JsValue::from_str("ne_lng must be between -180 and 180"));
         }
         
-        // Validate that bounds are valid (ne > sw)
+        // Validate that bounds are valid (ne north of sw). Longitudes may wrap.
         if ne_lat <= sw_lat {
             return Err(JsValue::from_str("ne_lat must be greater than sw_lat"));
         }
-        if ne_lng <= sw_lng {
-            return Err(JsValue::from_str("ne_lng must be greater than sw_lng"));
+
+        let (padding, max_zoom) = Self::parse_fit_options(options)?;
+        let (center_lat, center_lng, zoom) = self.fit_bounds_view([sw_lat, sw_lng, ne_lat, ne_lng], padding, max_zoom);
+        
+        // Apply the new view
+        self.set_view(center_lat, center_lng, zoom);
+        
+        Ok(())
+    }
+
+    // Padding as [top, right, bottom, left] and the optional zoom cap
+    fn parse_fit_options(options: &JsValue) -> Result<([f64; 4], Option<f64>), JsValue> {
+        if options.is_undefined() || options.is_null() {
+            return Ok(([0.0; 4], None));
+        }
+
+        let padding_value = js_sys::Reflect::get(options, &JsValue::from_str("padding"))?;
+        let padding = if padding_value.is_undefined() || padding_value.is_null() {
+            [0.0; 4]
+        } else if let Some(all) = padding_value.as_f64() {
+            [all; 4]
+        } else if Array::is_array(&padding_value) {
+            let sides = Array::from(&padding_value);
+            if sides.length() != 4 {
+                return Err(JsValue::from_str("Padding array must be [top, right, bottom, left]"));
+            }
+            let mut padding = [0.0; 4];
+            for (i, side) in padding.iter_mut().enumerate() {
+                *side = sides.get(i as u32).as_f64().ok_or_else(|| JsValue::from_str("Padding values must be numbers"))?;
+            }
+            padding
+        } else {
+            let mut padding = [0.0; 4];
+            for (side, key) in padding.iter_mut().zip(["top", "right", "bottom", "left"]) {
+                *side = js_sys::Reflect::get(&padding_value, &JsValue::from_str(key))?.as_f64().unwrap_or(0.0);
+            }
+            padding
+        };
+        if padding.iter().any(|side| !side.is_finite() || *side < 0.0) {
+            return Err(JsValue::from_str("Padding values must be non-negative numbers"));
+        }
+
+        let max_zoom = js_sys::Reflect::get(options, &JsValue::from_str("maxZoom"))?;
+        let max_zoom = if max_zoom.is_undefined() || max_zoom.is_null() {
+            None
+        } else {
+            Some(max_zoom.as_f64().filter(|zoom| zoom.is_finite()).ok_or_else(|| JsValue::from_str("maxZoom must be a number"))?)
+        };
+
+        Ok((padding, max_zoom))
+    }
+
+    /// Center and fractional zoom that fit `[sw_lat, sw_lng, ne_lat, ne_lng]`
+    /// inside the viewport minus `padding` ([top, right, bottom, left]).
+    fn fit_bounds_view(&self, bounds: [f64; 4], padding: [f64; 4], max_zoom: Option<f64>) -> (f64, f64, f64) {
+        let [sw_lat, sw_lng, ne_lat, mut ne_lng] = bounds;
+        // Bounds crossing the antimeridian continue east past 180
+        if ne_lng < sw_lng {
+            ne_lng += 360.0;
+        }
+
+        // Work in world pixels at zoom 0
+        let [min_x, min_y, max_x, max_y] = self.projected_bounds([sw_lat, sw_lng, ne_lat, ne_lng], 0.0);
+        let [top, right, bottom, left] = padding;
+        let available_width = (self.width as f64 - left - right).max(1.0);
+        let available_height = (self.height as f64 - top - bottom).max(1.0);
+
+        // Under a bearing the bounds box turns, so fit its rotated envelope
+        let (sin, cos) = self.bearing.to_radians().sin_cos();
+        let (sin, cos) = (sin.abs(), cos.abs());
+        let (bounds_width, bounds_height) = (max_x - min_x, max_y - min_y);
+        let rotated_width = bounds_width * cos + bounds_height * sin;
+        let rotated_height = bounds_width * sin + bounds_height * cos;
+
+        // Zero-size bounds give an infinite zoom, which the caps below bring back
+        let fit_zoom = (available_width / rotated_width)
+            .min(available_height / rotated_height)
+            .log2();
+        let zoom = self.clamp_zoom(fit_zoom.min(max_zoom.unwrap_or(f64::INFINITY)));
+
+        // Shift the center so the bounds sit in the middle of the padded area
+        let scale = 2f64.powf(zoom);
+        let (offset_x, offset_y) = self.screen_to_world_offset((left - right) / 2.0, (top - bottom) / 2.0);
+        let center_x = (min_x + max_x) / 2.0 - offset_x / scale;
+        let center_y = (min_y + max_y) / 2.0 - offset_y / scale;
+        let (lat, mut lng) = self.pixel_to_lat_lng(center_x, center_y, 0.0);
+        if lng > 180.0 {
+            lng -= 360.0;
+        } else if lng < -180.0 {
+            lng += 360.0;
+        }
+        (lat, lng, zoom)
+    }
+
+    #[wasm_bindgen]
+    pub fn project(&self, latlng_data: &JsValue) -> Array {
+        let latlng_array = js_sys::Array::from(latlng_data);
+        
+        if latlng_array.length() < 2 {
+            return Array::new();
+        }
+        
+        let lat = latlng_array.get(0).as_f64().unwrap_or(0.0);
+        let lng = latlng_array.get(1).as_f64().unwrap_or(0.0);
+        
+        let (screen_x, screen_y) = self.lat_lng_to_screen(lat, lng);
+        
+        let arr = Array::new();
+        arr.push(&JsValue::from_f64(screen_x));
+        arr.push(&JsValue::from_f64(screen_y));
+        arr
+    }
+
+    #[wasm_bindgen]
+    pub fn unproject(&self, point_data: &JsValue) -> Array {
+        let point_array = js_sys::Array::from(point_data);
+        
+        if point_array.length() < 2 {
+            return Array::new();
+        }
+        
+        let screen_x = point_array.get(0).as_f64().unwrap_or(0.0);
+        let screen_y = point_array.get(1).as_f64().unwrap_or(0.0);
+        
+        let (lat, lng) = self.screen_to_lat_lng(screen_x, screen_y);
+        
+        let arr = Array::new();
+        arr.push(&JsValue::from_f64(lat));
+        arr.push(&JsValue::from_f64(lng));
+        arr
+    }
+
+    // API methods for adding layers
+
+    /// Adds a raster tile layer on top of the existing ones and returns its id
+    #[wasm_bindgen]
+    pub fn add_tile_layer(&mut self, url_template: &str) -> Result<u32, JsValue> {
+        Ok(self.insert_tile_layer(TileLayer::new(url_template)))
+    }
+
+    fn insert_tile_layer(&mut self, mut tile_layer: TileLayer) -> u32 {
+        tile_layer.id = self.next_tile_layer_id;
+        self.next_tile_layer_id += 1;
+        self.tile_layers.push(tile_layer);
+        self.load_visible_tiles();
+        self.next_tile_layer_id - 1
+    }
+
+    /// Adds a WMS layer served from `base_url` on top of the existing layers
+    /// and returns its id. See `TileLayerApi::set_wms` for the options.
+    #[wasm_bindgen]
+    pub fn add_wms_layer(&mut self, base_url: &str, options: &JsValue) -> Result<u32, JsValue> {
+        let mut tile_layer = TileLayer::new(base_url);
+        tile_layer.wms = Some(parse_wms_options(options)?);
+        Ok(self.insert_tile_layer(tile_layer))
+    }
+
+    /// Adds a WMTS layer described by a GetCapabilities document on top of
+    /// the existing layers and returns its id. See `TileLayerApi::set_wmts`
+    /// for the options.
+    #[wasm_bindgen]
+    pub fn add_wmts_layer(&mut self, capabilities: &str, options: &JsValue) -> Result<u32, JsValue> {
+        let mut tile_layer = TileLayer::new("");
+        tile_layer.set_wmts(parse_wmts(capabilities, options)?);
+        Ok(self.insert_tile_layer(tile_layer))
+    }
+
+    /// Adds a vector tile layer fetching Mapbox Vector Tiles from
+    /// `url_template` on top of the existing layers and returns its id. See
+    /// `TileLayerApi::set_vector_style` for the style.
+    #[wasm_bindgen]
+    pub fn add_vector_tile_layer(&mut self, url_template: &str, style: &JsValue) -> Result<u32, JsValue> {
+        let mut tile_layer = TileLayer::new(url_template);
+        tile_layer.vector = Some(parse_vector_style(style)?);
+        Ok(self.insert_tile_layer(tile_layer))
+    }
+
+    /// Adds a layer reading raster or vector tiles from the PMTiles archive at
+    /// `url` with HTTP range requests, on top of the existing layers, and
+    /// returns its id. Vector tiles are drawn with `style`; see
+    /// `TileLayerApi::set_vector_style`.
+    #[wasm_bindgen]
+    pub fn add_pmtiles_layer(&mut self, url: &str, style: &JsValue) -> Result<u32, JsValue> {
+        let mut tile_layer = TileLayer::new(url);
+        tile_layer.vector = Some(parse_vector_style(style)?);
+        tile_layer.set_source(Rc::new(open_pmtiles(url, &JsValue::NULL)?));
+        Ok(self.insert_tile_layer(tile_layer))
+    }
+
+    /// Adds a layer drawing the tiles of an MBTiles tileset handed over as
+    /// rows of its `tiles` table and its `metadata`, on top of the existing
+    /// layers, and returns its id. Vector tiles are drawn with `style`. See
+    /// `TileLayerApi::set_mbtiles`.
+    #[wasm_bindgen]
+    pub fn add_mbtiles_layer(&mut self, rows: &JsValue, metadata: &JsValue, style: &JsValue) -> Result<u32, JsValue> {
+        let mut tile_layer = TileLayer::new("");
+        tile_layer.vector = Some(parse_vector_style(style)?);
+        tile_layer.set_source(Rc::new(parse_mbtiles(rows, metadata)?));
+        Ok(self.insert_tile_layer(tile_layer))
+    }
+
+    /// Restyles a vector tile layer; its tiles are kept
+    #[wasm_bindgen]
+    pub fn set_tile_layer_vector_style(&mut self, layer_id: u32, style: &JsValue) -> Result<(), JsValue> {
+        let index = self.tile_layer_index(layer_id)?;
+        if self.tile_layers[index].vector.is_none() {
+            return Err(JsValue::from_str(&format!("Tile layer {} is not a vector tile layer", layer_id)));
+        }
+        self.tile_layers[index].vector = Some(parse_vector_style(style)?);
+        let prefix = format!("{}/", layer_id);
+        VECTOR_TILE_GEOMETRY.with(|geometry| geometry.borrow_mut().retain(|key, _| !key.starts_with(&prefix)));
+        Ok(())
+    }
+
+    /// Removes a tile layer, freeing its cached tiles
+    #[wasm_bindgen]
+    pub fn remove_tile_layer(&mut self, layer_id: u32) -> Result<(), JsValue> {
+        let index = self.tile_layer_index(layer_id)?;
+        self.tile_layers.remove(index);
+
+        let prefix = format!("{}/", layer_id);
+        for (_, texture) in TILE_TEXTURES.with(|store| store.borrow_mut().remove_prefixed(&prefix)) {
+            if let Some(ref gl_state) = self.gl_state {
+                gl_state.context.delete_texture(Some(&texture));
+            }
+        }
+        VECTOR_TILES.with(|store| store.borrow_mut().remove_prefixed(&prefix));
+        VECTOR_TILE_GEOMETRY.with(|geometry| geometry.borrow_mut().retain(|key, _| !key.starts_with(&prefix)));
+        ELEVATION_TILES.with(|tiles| tiles.borrow_mut().retain(|key, _| !key.starts_with(&prefix)));
+        // Aborts the layer's requests in flight
+        self.load_visible_tiles();
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    pub fn set_tile_layer_opacity(&mut self, layer_id: u32, opacity: f32) -> Result<(), JsValue> {
+        if !(0.0..=1.0).contains(&opacity) {
+            return Err(JsValue::from_str("Opacity must be between 0 and 1"));
+        }
+        let index = self.tile_layer_index(layer_id)?;
+        self.tile_layers[index].opacity = opacity;
+        // Vector tiles' colors carry the opacity
+        let prefix = format!("{}/", layer_id);
+        VECTOR_TILE_GEOMETRY.with(|geometry| geometry.borrow_mut().retain(|key, _| !key.starts_with(&prefix)));
+        self.load_visible_tiles();
+        Ok(())
+    }
+
+    /// Restyles a terrain layer's hillshading; see `TileLayerApi::set_terrain`.
+    /// Cached tiles are shaded again from the heights already decoded rather
+    /// than reloaded, so an `encoding` in the options is ignored.
+    #[wasm_bindgen]
+    pub fn set_tile_layer_hillshade(&mut self, layer_id: u32, options: &JsValue) -> Result<(), JsValue> {
+        let hillshade = parse_hillshade_style(options)?;
+        let index = self.tile_layer_index(layer_id)?;
+        let Some(terrain) = self.tile_layers[index].terrain.as_mut() else {
+            return Err(JsValue::from_str(&format!("Tile layer {} is not a terrain layer", layer_id)));
+        };
+        terrain.hillshade = hillshade;
+
+        if let Some(ref gl_state) = self.gl_state {
+            let context = &gl_state.context;
+            let prefix = format!("{}/", layer_id);
+            ELEVATION_TILES.with(|tiles| {
+                for (key, elevation) in tiles.borrow().iter().filter(|(key, _)| key.starts_with(&prefix)) {
+                    let Some(texture) = TILE_TEXTURES.with(|store| store.borrow().peek(key).cloned()) else {
+                        continue;
+                    };
+                    context.bind_texture(WebGl2RenderingContext::TEXTURE_2D, Some(&texture));
+                    Self::upload_tile_pixels(context, elevation, &terrain.hillshade.shade(elevation))?;
+                }
+                Ok::<(), JsValue>(())
+            })?;
+        }
+        self.load_visible_tiles();
+        Ok(())
+    }
+
+    /// Height in meters at a point, read from the finest cached tile of the
+    /// topmost visible terrain layer that has one; undefined where no terrain
+    /// tile covering it is loaded
+    #[wasm_bindgen]
+    pub fn elevation_at(&self, lat: f64, lng: f64) -> Option<f64> {
+        let size = self.tile_size as f64;
+        let layers = self.tile_layers_in_draw_order();
+        ELEVATION_TILES.with(|tiles| {
+            let tiles = tiles.borrow();
+            layers.iter().rev().filter(|layer| layer.visible && layer.terrain.is_some()).find_map(|layer| {
+                let (min_level, max_level) = layer.tile_zoom_range();
+                (min_level..=max_level).rev().find_map(|z| {
+                    // At the tile's own zoom every tile is one map tile across
+                    let (x, y) = self.lat_lng_to_pixel(lat, lng, z as f64);
+                    let (tile_x, tile_y) = ((x / size).floor(), (y / size).floor());
+                    let wrapped_x = self.wrap_tile_x(tile_x as i32, z)?;
+                    let elevation = tiles.get(&TileCoord { x: wrapped_x, y: tile_y as i32, z }.key(layer.id))?;
+                    Some(elevation.sample(x / size - tile_x, y / size - tile_y) as f64)
+                })
+            })
+        })
+    }
+
+    /// Replaces a raster layer's color adjustments; see
+    /// `TileLayerApi::set_adjustments`. Tiles are kept.
+    #[wasm_bindgen]
+    pub fn set_tile_layer_adjustments(&mut self, layer_id: u32, options: &JsValue) -> Result<(), JsValue> {
+        let adjustments = parse_raster_adjustments(options)?;
+        let index = self.tile_layer_index(layer_id)?;
+        self.tile_layers[index].adjustments = adjustments;
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    pub fn set_tile_layer_visible(&mut self, layer_id: u32, visible: bool) -> Result<(), JsValue> {
+        let index = self.tile_layer_index(layer_id)?;
+        self.tile_layers[index].visible = visible;
+        self.load_visible_tiles();
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    pub fn set_tile_layer_z_index(&mut self, layer_id: u32, z_index: i32) -> Result<(), JsValue> {
+        let index = self.tile_layer_index(layer_id)?;
+        self.tile_layers[index].z_index = z_index;
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    pub fn set_tile_layer_zoom_range(&mut self, layer_id: u32, min_zoom: u32, max_zoom: u32) -> Result<(), JsValue> {
+        if min_zoom > max_zoom {
+            return Err(JsValue::from_str("min_zoom must not be greater than max_zoom"));
         }
-        
-        // Calculate center of bounds
-        let center_lat = (sw_lat + ne_lat) / 2.0;
-        let center_lng = (sw_lng + ne_lng) / 2.0;
-        
-        // Calculate appropriate zoom level to fit bounds in viewport
-        let zoom = self.calculate_fit_zoom(sw_lat, sw_lng, ne_lat, ne_lng);
-        
-        // Apply the new view
-        self.set_view(center_lat, center_lng, zoom);
-        
+        let index = self.tile_layer_index(layer_id)?;
+        self.tile_layers[index].min_zoom = min_zoom;
+        self.tile_layers[index].max_zoom = max_zoom;
+        self.load_visible_tiles();
         Ok(())
     }
 
-    fn calculate_fit_zoom(&self, sw_lat: f64, sw_lng: f64, ne_lat: f64, ne_lng: f64) -> f64 {
-        let mut best_zoom = 1.0;
-        
-        // Calculate center of bounds
-        let center_lat = (sw_lat + ne_lat) / 2.0;
-        let center_lng = (sw_lng + ne_lng) / 2.0;
-        
-        // Binary search for the best zoom level
-        for zoom in (1..=18).rev() {
-            let bounds = self.get_view_bounds_at_zoom(center_lat, center_lng, zoom);
-            
-            if bounds[0] <= sw_lat && bounds[1] <= sw_lng && 
-               bounds[2] >= ne_lat && bounds[3] >= ne_lng {
-                best_zoom = zoom as f64;
-                break;
+    fn tile_layer_index(&self, layer_id: u32) -> Result<usize, JsValue> {
+        self.tile_layers
+            .iter()
+            .position(|layer| layer.id == layer_id)
+            .ok_or_else(|| JsValue::from_str(&format!("Tile layer {} not found", layer_id)))
+    }
+
+    // Bottom to top: ascending z-index, then insertion order
+    fn tile_layers_in_draw_order(&self) -> Vec<&TileLayer> {
+        let mut layers: Vec<&TileLayer> = self.tile_layers.iter().collect();
+        layers.sort_by_key(|layer| layer.z_index);
+        layers
+    }
+
+    // Tile layers and image overlays bottom to top: ascending z-index, then
+    // insertion order, which their shared ids follow
+    fn stacked_layers(&self) -> Vec<StackedLayer> {
+        let mut layers: Vec<(i32, u32, StackedLayer)> = self
+            .tile_layers
+            .iter()
+            .map(|layer| (layer.z_index, layer.id, StackedLayer::Tile(Box::new(layer.clone()))))
+            .chain(self.image_overlays.iter().map(|overlay| (overlay.z_index, overlay.id, StackedLayer::Overlay(overlay.clone()))))
+            .collect();
+        layers.sort_by_key(|&(z_index, id, _)| (z_index, id));
+        layers.into_iter().map(|(_, _, layer)| layer).collect()
+    }
+
+    /// Places an image on the map and returns its id. `image` is a URL or an
+    /// ImageData; `corners` is either a lat/lng box [sw_lat, sw_lng, ne_lat,
+    /// ne_lng] or the image's top-left, top-right, bottom-right and
+    /// bottom-left corners as [lat, lng] pairs, for images not aligned with
+    /// the map. Overlays stack with the tile layers, vector tile layers
+    /// included, by z-index, and always draw below point, line, polygon and
+    /// GeoJSON layers, whatever their z-index.
+    #[wasm_bindgen]
+    pub fn add_image_overlay(&mut self, image: &JsValue, corners: &JsValue) -> Result<u32, JsValue> {
+        let overlay = ImageOverlay {
+            id: self.next_tile_layer_id,
+            image: parse_overlay_image(image)?,
+            corners: parse_overlay_corners(corners)?,
+            opacity: 1.0,
+            visible: true,
+            z_index: 0,
+        };
+        self.next_tile_layer_id += 1;
+        self.image_overlays.push(overlay);
+        Ok(self.next_tile_layer_id - 1)
+    }
+
+    /// Replaces an overlay's image, e.g. with the next radar frame
+    #[wasm_bindgen]
+    pub fn set_image_overlay_image(&mut self, overlay_id: u32, image: &JsValue) -> Result<(), JsValue> {
+        let image = parse_overlay_image(image)?;
+        let index = self.image_overlay_index(overlay_id)?;
+        self.image_overlays[index].image = image;
+        // The current image stays up until the new one has loaded
+        let previous = match OVERLAY_TEXTURES.with(|textures| textures.borrow_mut().remove(&overlay_id)) {
+            Some(OverlayTexture::Loaded(texture)) => Some(texture),
+            Some(OverlayTexture::Loading { cancelled, previous }) => {
+                cancelled.set(true);
+                previous
             }
+            Some(OverlayTexture::Failed) | None => None,
+        };
+        match (previous, &self.gl_state) {
+            (Some(previous), Some(gl_state)) => Self::load_overlay_texture(&gl_state.context, &self.image_overlays[index], Some(previous)),
+            _ => Ok(()),
         }
-        
-        best_zoom
     }
 
-    fn get_view_bounds_at_zoom(&self, center_lat: f64, center_lng: f64, zoom: u32) -> [f64; 4] {
-        let center_pixel = self.lat_lng_to_pixel(center_lat, center_lng, zoom);
-        
-        let start_x = center_pixel.0 - (self.width as f64 / 2.0);
-        let start_y = center_pixel.1 - (self.height as f64 / 2.0);
-        let end_x = center_pixel.0 + (self.width as f64 / 2.0);
-        let end_y = center_pixel.1 + (self.height as f64 / 2.0);
-        
-        let (sw_lat, sw_lng) = self.pixel_to_lat_lng(start_x, end_y, zoom);
-        let (ne_lat, ne_lng) = self.pixel_to_lat_lng(end_x, start_y, zoom);
-        
-        [sw_lat, sw_lng, ne_lat, ne_lng]
+    /// Moves an overlay; `corners` as for `add_image_overlay`
+    #[wasm_bindgen]
+    pub fn set_image_overlay_corners(&mut self, overlay_id: u32, corners: &JsValue) -> Result<(), JsValue> {
+        let corners = parse_overlay_corners(corners)?;
+        let index = self.image_overlay_index(overlay_id)?;
+        self.image_overlays[index].corners = corners;
+        Ok(())
     }
 
     #[wasm_bindgen]
-    pub fn project(&self, latlng_data: &JsValue) -> Array {
-        let latlng_array = js_sys::Array::from(latlng_data);
-        
-        if latlng_array.length() < 2 {
-            return Array::new();
+    pub fn set_image_overlay_opacity(&mut self, overlay_id: u32, opacity: f32) -> Result<(), JsValue> {
+        if !(0.0..=1.0).contains(&opacity) {
+            return Err(JsValue::from_str("Opacity must be between 0 and 1"));
         }
-        
-        let lat = latlng_array.get(0).as_f64().unwrap_or(0.0);
-        let lng = latlng_array.get(1).as_f64().unwrap_or(0.0);
-        
-        let zoom = self.zoom.round() as u32;
-        let center_pixel = self.lat_lng_to_pixel(self.center_lat, self.center_lng, zoom);
-        let point_pixel = self.lat_lng_to_pixel(lat, lng, zoom);
-        
-        let screen_x = point_pixel.0 - center_pixel.0 + (self.width as f64 / 2.0);
-        let screen_y = point_pixel.1 - center_pixel.1 + (self.height as f64 / 2.0);
-        
-        let arr = Array::new();
-        arr.push(&JsValue::from_f64(screen_x));
-        arr.push(&JsValue::from_f64(screen_y));
-        arr
+        let index = self.image_overlay_index(overlay_id)?;
+        self.image_overlays[index].opacity = opacity;
+        Ok(())
     }
 
     #[wasm_bindgen]
-    pub fn unproject(&self, point_data: &JsValue) -> Array {
-        let point_array = js_sys::Array::from(point_data);
-        
-        if point_array.length() < 2 {
-            return Array::new();
-        }
-        
-        let screen_x = point_array.get(0).as_f64().unwrap_or(0.0);
-        let screen_y = point_array.get(1).as_f64().unwrap_or(0.0);
-        
-        let zoom = self.zoom.round() as u32;
-        let center_pixel = self.lat_lng_to_pixel(self.center_lat, self.center_lng, zoom);
-        
-        let point_x = screen_x - (self.width as f64 / 2.0) + center_pixel.0;
-        let point_y = screen_y - (self.height as f64 / 2.0) + center_pixel.1;
-        
-        let (lat, lng) = self.pixel_to_lat_lng(point_x, point_y, zoom);
-        
-        let arr = Array::new();
-        arr.push(&JsValue::from_f64(lat));
-        arr.push(&JsValue::from_f64(lng));
-        arr
+    pub fn set_image_overlay_visible(&mut self, overlay_id: u32, visible: bool) -> Result<(), JsValue> {
+        let index = self.image_overlay_index(overlay_id)?;
+        self.image_overlays[index].visible = visible;
+        Ok(())
     }
 
-    // API methods for adding layers
     #[wasm_bindgen]
-    pub fn add_tile_layer(&mut self, url_template: &str) -> Result<(), JsValue> {
-        let tile_layer = TileLayer {
-            url_template: url_template.to_string(),
-            subdomains: vec!["a".to_string(), "b".to_string(), "c".to_string()],
-            max_zoom: 18,
-            min_zoom: 0,
-        };
-        self.tile_layer = Some(tile_layer);
+    pub fn set_image_overlay_z_index(&mut self, overlay_id: u32, z_index: i32) -> Result<(), JsValue> {
+        let index = self.image_overlay_index(overlay_id)?;
+        self.image_overlays[index].z_index = z_index;
+        Ok(())
+    }
+
+    /// Removes an image overlay, freeing its texture
+    #[wasm_bindgen]
+    pub fn remove_image_overlay(&mut self, overlay_id: u32) -> Result<(), JsValue> {
+        let index = self.image_overlay_index(overlay_id)?;
+        self.image_overlays.remove(index);
+        self.release_overlay_texture(overlay_id);
         Ok(())
     }
 
+    fn image_overlay_index(&self, overlay_id: u32) -> Result<usize, JsValue> {
+        self.image_overlays
+            .iter()
+            .position(|overlay| overlay.id == overlay_id)
+            .ok_or_else(|| JsValue::from_str(&format!("Image overlay {} not found", overlay_id)))
+    }
+
     #[wasm_bindgen]
     pub fn add_point_layer(&mut self) {
         let point_layer = PointLayer {
@@ -2314,6 +5555,7 @@ impl RustyleafMap {
         self.drag_accumulated_x = 0.0;
         self.drag_accumulated_y = 0.0;
         self.has_momentum = false; // Stop any ongoing momentum
+        self.stop_camera_animation();
         self.last_drag_time = js_sys::Date::now();
 
         // Convert canvas coordinates to lat/lng and trigger mousedown event
@@ -2430,14 +5672,83 @@ impl RustyleafMap {
         }
     }
 
+    /// Starts a rotate and tilt drag, typically from the right button or a
+    /// modifier-held left button.
     #[wasm_bindgen]
-    pub fn on_wheel(&mut self, delta_y: f64, _canvas_x: f64, _canvas_y: f64) {
-        // Zoom based on wheel direction
-        if delta_y > 0.0 {
-            self.zoom_out();
-        } else {
-            self.zoom_in();
+    pub fn handle_rotate_start(&mut self, canvas_x: f64, canvas_y: f64) {
+        self.stop_camera_animation();
+        self.has_momentum = false;
+        self.drag_velocity = (0.0, 0.0);
+        self.mouse_state.is_rotating = true;
+        self.mouse_state.last_x = canvas_x;
+        self.mouse_state.last_y = canvas_y;
+    }
+
+    #[wasm_bindgen]
+    pub fn on_rotate_move(&mut self, canvas_x: f64, canvas_y: f64) {
+        if !self.mouse_state.is_rotating {
+            return;
+        }
+
+        // Horizontal movement turns the map; dragging right turns it clockwise.
+        // Vertical movement tilts it; dragging up leans toward the horizon.
+        let degrees_per_pixel = 0.5;
+        let delta_x = canvas_x - self.mouse_state.last_x;
+        let delta_y = canvas_y - self.mouse_state.last_y;
+        if delta_x != 0.0 {
+            self.set_bearing(self.bearing - delta_x * degrees_per_pixel);
+        }
+        if delta_y != 0.0 {
+            self.set_pitch(self.pitch - delta_y * degrees_per_pixel);
+        }
+
+        self.mouse_state.last_x = canvas_x;
+        self.mouse_state.last_y = canvas_y;
+    }
+
+    #[wasm_bindgen]
+    pub fn handle_rotate_end(&mut self) {
+        self.mouse_state.is_rotating = false;
+    }
+
+    #[wasm_bindgen]
+    pub fn on_wheel(&mut self, delta_y: f64, canvas_x: f64, canvas_y: f64) {
+        if delta_y == 0.0 || !delta_y.is_finite() {
+            return;
+        }
+        self.stop_camera_animation();
+
+        // Scale the step by the wheel delta so trackpads zoom smoothly while a
+        // mouse wheel notch still moves by at most one full level
+        let wheel_pixels_per_zoom_level = 60.0;
+        let step = (-delta_y / wheel_pixels_per_zoom_level).clamp(-1.0, 1.0);
+        let new_zoom = self.clamp_zoom(self.zoom + step);
+        if new_zoom == self.zoom {
+            return;
         }
+
+        self.zoom_around(new_zoom, canvas_x, canvas_y);
+    }
+
+    // Change zoom while keeping the lat/lng under the given canvas point fixed
+    fn zoom_around(&mut self, zoom: f64, anchor_x: f64, anchor_y: f64) {
+        let (lat, lng) = self.center_for_zoom_around(zoom, anchor_x, anchor_y);
+        self.set_view(lat, lng, zoom);
+        self.trigger_zoom_event();
+    }
+
+    fn center_for_zoom_around(&self, zoom: f64, anchor_x: f64, anchor_y: f64) -> (f64, f64) {
+        let (anchor_lat, anchor_lng) = self.screen_to_lat_lng(anchor_x, anchor_y);
+        let anchor_pixel = self.lat_lng_to_pixel(anchor_lat, anchor_lng, zoom);
+
+        // Place the center so the anchor lands back under the same canvas point
+        let (offset_x, offset_y) = self.screen_to_world_offset(
+            anchor_x - self.width as f64 / 2.0,
+            anchor_y - self.height as f64 / 2.0,
+        );
+        let center_x = anchor_pixel.0 - offset_x;
+        let center_y = anchor_pixel.1 - offset_y;
+        self.pixel_to_lat_lng(center_x, center_y, zoom)
     }
 
     // GeoJSON rendering and conversion methods
@@ -2639,6 +5950,10 @@ impl RustyleafMap {
                 if let Some(loc) = u_matrix_loc.as_ref() {
                     context.uniform_matrix4fv_with_f32_array(Some(loc), false, &projection_matrix);
                 }
+                let u_pixel_ratio = context.get_uniform_location(&gl_state.programs.point_program, "u_pixel_ratio");
+                if let Some(loc) = u_pixel_ratio.as_ref() {
+                    context.uniform1f(Some(loc), self.pixel_ratio as f32);
+                }
 
                 context.draw_arrays(WebGl2RenderingContext::POINTS, 0, points.len() as i32);
             }
@@ -2663,13 +5978,7 @@ impl RustyleafMap {
                     
                     let start_screen = self.lat_lng_to_screen(start[0], start[1]);
                     let end_screen = self.lat_lng_to_screen(end[0], end[1]);
-                    
-                    vertex_data.extend_from_slice(&[
-                        start_screen.0 as f32, start_screen.1 as f32,
-                        line.color[0], line.color[1], line.color[2], line.color[3],
-                        end_screen.0 as f32, end_screen.1 as f32,
-                        line.color[0], line.color[1], line.color[2], line.color[3],
-                    ]);
+                    push_line_segment(&mut vertex_data, start_screen, end_screen, line.width, line.color);
                 }
             }
 
@@ -2703,7 +6012,7 @@ impl RustyleafMap {
                 }
 
                 let total_vertices = vertex_data.len() / 6;
-                context.draw_arrays(WebGl2RenderingContext::LINES, 0, total_vertices as i32);
+                context.draw_arrays(WebGl2RenderingContext::TRIANGLES, 0, total_vertices as i32);
             }
         }
 
@@ -3298,72 +6607,151 @@ impl RustyleafMap {
         let mut new_index = RTree::new();
         let mut feature_id = 0;
 
-        // Index point features
-        for (layer_idx, layer) in self.point_layers.iter().enumerate() {
-            for (point_idx, point) in layer.points.iter().enumerate() {
-                let screen_pos = self.lat_lng_to_screen(point.lat, point.lng);
-                let tolerance = 3.0; // 3px tolerance as specified
-                
-                let bounds = AABB::from_corners(
-                    [screen_pos.0 - tolerance, screen_pos.1 - tolerance],
-                    [screen_pos.0 + tolerance, screen_pos.1 + tolerance]
-                );
-
-                let mut meta = serde_json::json!({});
-                meta["layer_type"] = "point".into();
-                meta["layer_index"] = layer_idx.into();
-                meta["feature_index"] = point_idx.into();
-                meta["original_meta"] = point.meta.clone();
+        // Features are indexed on every visible world copy so hits work on all of them
+        for offset in self.world_copy_offsets() {
+            self.world_copy_offset = offset;
 
-                let feature = SpatialFeature {
-                    id: feature_id,
-                    bounds,
-                    meta,
-                };
-
-                new_index.insert(feature);
-                feature_id += 1;
-            }
-        }
-
-        // Index line features (simplified - index line segments)
-        for (layer_idx, layer) in self.line_layers.iter().enumerate() {
-            for (line_idx, line) in layer.lines.iter().enumerate() {
-                // Index each line segment with tolerance
-                for i in 0..line.points.len().saturating_sub(1) {
-                    let start = line.points[i];
-                    let end = line.points[i + 1];
-                    
-                    let start_screen = self.lat_lng_to_screen(start[0], start[1]);
-                    let end_screen = self.lat_lng_to_screen(end[0], end[1]);
-                    
-                    // Create bounds that encompass both points with tolerance
-                    let tolerance = 3.0;
-                    let min_x = start_screen.0.min(end_screen.0) - tolerance;
-                    let max_x = start_screen.0.max(end_screen.0) + tolerance;
-                    let min_y = start_screen.1.min(end_screen.1) - tolerance;
-                    let max_y = start_screen.1.max(end_screen.1) + tolerance;
+            // Index point features
+            for (layer_idx, layer) in self.point_layers.iter().enumerate() {
+                for (point_idx, point) in layer.points.iter().enumerate() {
+                    let screen_pos = self.lat_lng_to_screen(point.lat, point.lng);
+                    let tolerance = 3.0; // 3px tolerance as specified
 
-                    let bounds = AABB::from_corners([min_x, min_y], [max_x, max_y]);
+                    let bounds = AABB::from_corners(
+                        [screen_pos.0 - tolerance, screen_pos.1 - tolerance],
+                        [screen_pos.0 + tolerance, screen_pos.1 + tolerance]
+                    );
 
                     let mut meta = serde_json::json!({});
-                    meta["layer_type"] = "line".into();
+                    meta["layer_type"] = "point".into();
                     meta["layer_index"] = layer_idx.into();
-                    meta["feature_index"] = line_idx.into();
-                    meta["segment_index"] = i.into();
-                    meta["original_meta"] = line.meta.clone();
+                    meta["feature_index"] = point_idx.into();
+                    meta["original_meta"] = point.meta.clone();
 
                     let feature = SpatialFeature {
                         id: feature_id,
                         bounds,
                         meta,
+                        triangles: Vec::new(),
+                        segment: None,
                     };
 
                     new_index.insert(feature);
                     feature_id += 1;
                 }
             }
+
+            // Index line features (simplified - index line segments)
+            for (layer_idx, layer) in self.line_layers.iter().enumerate() {
+                for (line_idx, line) in layer.lines.iter().enumerate() {
+                    // Index each line segment with tolerance
+                    for i in 0..line.points.len().saturating_sub(1) {
+                        let start = line.points[i];
+                        let end = line.points[i + 1];
+
+                        let start_screen = self.lat_lng_to_screen(start[0], start[1]);
+                        let end_screen = self.lat_lng_to_screen(end[0], end[1]);
+
+                        // Create bounds that encompass both points with tolerance
+                        let tolerance = 3.0;
+                        let min_x = start_screen.0.min(end_screen.0) - tolerance;
+                        let max_x = start_screen.0.max(end_screen.0) + tolerance;
+                        let min_y = start_screen.1.min(end_screen.1) - tolerance;
+                        let max_y = start_screen.1.max(end_screen.1) + tolerance;
+
+                        let bounds = AABB::from_corners([min_x, min_y], [max_x, max_y]);
+
+                        let mut meta = serde_json::json!({});
+                        meta["layer_type"] = "line".into();
+                        meta["layer_index"] = layer_idx.into();
+                        meta["feature_index"] = line_idx.into();
+                        meta["segment_index"] = i.into();
+                        meta["original_meta"] = line.meta.clone();
+
+                        let feature = SpatialFeature {
+                            id: feature_id,
+                            bounds,
+                            meta,
+                            triangles: Vec::new(),
+                            segment: Some([[start_screen.0, start_screen.1], [end_screen.0, end_screen.1]]),
+                        };
+
+                        new_index.insert(feature);
+                        feature_id += 1;
+                    }
+                }
+            }
+        }
+        self.world_copy_offset = 0.0;
+
+        // Vector tile features drawn on screen, one entry per feature per tile
+        let layers: Vec<TileLayer> = self.tile_layers.iter().filter(|layer| layer.is_vector()).cloned().collect();
+        for layer in &layers {
+            if !layer.visible || layer.opacity <= 0.0 || self.layer_tile_zoom_and_scale(layer).is_none() {
+                continue;
+            }
+            let visible_tiles = self.layer_covering_tiles(layer);
+            for (key, copy_offset) in self.vector_tile_draws(layer, &visible_tiles) {
+                let features = match VECTOR_TILES.with(|store| store.borrow().peek(&key).cloned()) {
+                    Some(features) => features,
+                    None => continue,
+                };
+                self.world_copy_offset = copy_offset;
+                for feature in features.iter() {
+                    let rule = match layer.vector_style(feature) {
+                        Some(rule) => rule,
+                        None => continue,
+                    };
+                    // Only what is drawn can be hit. Points and each segment of
+                    // a line get an entry of their own, so a line's hits stay
+                    // near it rather than anywhere in its bounding box.
+                    let (paths, fill): (Vec<&[[f64; 2]]>, &[[f64; 2]]) = match &feature.geometry {
+                        mvt::Geometry::Points(coords) if rule.point_color.is_some() => (coords.chunks(1).collect(), &[]),
+                        mvt::Geometry::Lines(paths) if rule.line_color.is_some() => (paths.iter().map(Vec::as_slice).collect(), &[]),
+                        mvt::Geometry::Polygons { triangles, .. } if rule.polygon_color.is_some() => (Vec::new(), triangles),
+                        mvt::Geometry::Polygons { outlines, .. } if rule.line_color.is_some() => (outlines.iter().map(Vec::as_slice).collect(), &[]),
+                        _ => continue,
+                    };
+                    let to_screen = |&[lat, lng]: &[f64; 2]| {
+                        let (x, y) = self.lat_lng_to_screen(lat, lng);
+                        [x, y]
+                    };
+                    let bounds = |vertices: &[[f64; 2]]| {
+                        let tolerance = 3.0;
+                        let min_x = vertices.iter().map(|p| p[0]).fold(f64::INFINITY, f64::min) - tolerance;
+                        let max_x = vertices.iter().map(|p| p[0]).fold(f64::NEG_INFINITY, f64::max) + tolerance;
+                        let min_y = vertices.iter().map(|p| p[1]).fold(f64::INFINITY, f64::min) - tolerance;
+                        let max_y = vertices.iter().map(|p| p[1]).fold(f64::NEG_INFINITY, f64::max) + tolerance;
+                        AABB::from_corners([min_x, min_y], [max_x, max_y])
+                    };
+                    let mut meta = serde_json::json!({});
+                    meta["layer_type"] = "vector_tile".into();
+                    meta["layer_id"] = layer.id.into();
+                    meta["source_layer"] = feature.source_layer.clone().into();
+                    meta["feature_id"] = feature.id.into();
+                    meta["original_meta"] = feature.properties.clone();
+                    let mut insert = |bounds, triangles, segment| {
+                        new_index.insert(SpatialFeature { id: feature_id, bounds, meta: meta.clone(), triangles, segment });
+                        feature_id += 1;
+                    };
+
+                    let fill: Vec<[f64; 2]> = fill.iter().map(to_screen).collect();
+                    if !fill.is_empty() {
+                        insert(bounds(&fill), fill, None);
+                    }
+                    for path in paths {
+                        let screen: Vec<[f64; 2]> = path.iter().map(to_screen).collect();
+                        if let [point] = screen[..] {
+                            insert(bounds(&[point]), Vec::new(), None);
+                        }
+                        for segment in screen.windows(2) {
+                            insert(bounds(segment), Vec::new(), Some([segment[0], segment[1]]));
+                        }
+                    }
+                }
+            }
         }
+        self.world_copy_offset = 0.0;
 
         // Replace the old index with the new one
         SPATIAL_INDEX.with(|index| {
@@ -3373,80 +6761,34 @@ impl RustyleafMap {
 
     fn hit_test(&self, x: f64, y: f64) -> Option<serde_json::Value> {
         let search_radius = 3.0; // 3px tolerance
-        
+
         let results: Vec<SpatialFeature> = SPATIAL_INDEX.with(|index| {
             let search_bounds = AABB::from_corners(
                 [x - search_radius, y - search_radius],
                 [x + search_radius, y + search_radius]
             );
-            index.borrow().locate_in_envelope(&search_bounds).cloned().collect()
+            index.borrow().locate_in_envelope_intersecting(&search_bounds).cloned().collect()
         });
 
-        // Return the first matching feature (could be enhanced for priority/depth)
-        for feature in results {
-            // Additional precise hit-testing could be done here
+        // Points, and lines within the tolerance of one of their segments,
+        // take priority over the filled areas beneath them, which are only
+        // hit inside one of their triangles
+        let near = |feature: &&SpatialFeature| match feature.segment {
+            Some([start, end]) => distance_to_segment([x, y], start, end) <= search_radius,
+            None => feature.triangles.is_empty(),
+        };
+        if let Some(feature) = results.iter().find(near) {
             return Some(feature.meta.clone());
         }
-
-        None
+        results
+            .iter()
+            .find(|feature| feature.triangles.chunks_exact(3).any(|triangle| point_in_triangle([x, y], triangle)))
+            .map(|feature| feature.meta.clone())
     }
 
     // Parse CSS color string to RGBA array
     fn parse_color(&self, color_str: &str) -> [f32; 4] {
-        let s = color_str.trim().to_lowercase();
-        // Hex #RRGGBB or #RRGGBBAA
-        if let Some(stripped) = s.strip_prefix('#') {
-            if stripped.len() == 6 {
-                if let Ok(val) = u32::from_str_radix(stripped, 16) {
-                    let r = ((val >> 16) & 0xff) as f32 / 255.0;
-                    let g = ((val >> 8) & 0xff) as f32 / 255.0;
-                    let b = (val & 0xff) as f32 / 255.0;
-                    return [r, g, b, 1.0];
-                }
-            } else if stripped.len() == 8 {
-                if let Ok(val) = u32::from_str_radix(stripped, 16) {
-                    let r = ((val >> 24) & 0xff) as f32 / 255.0;
-                    let g = ((val >> 16) & 0xff) as f32 / 255.0;
-                    let b = ((val >> 8) & 0xff) as f32 / 255.0;
-                    let a = (val & 0xff) as f32 / 255.0;
-                    return [r, g, b, a];
-                }
-            } else if stripped.len() == 3 {
-                // #RGB
-                let r = u8::from_str_radix(&stripped[0..1], 16).unwrap_or(0);
-                let g = u8::from_str_radix(&stripped[1..2], 16).unwrap_or(0);
-                let b = u8::from_str_radix(&stripped[2..3], 16).unwrap_or(0);
-                return [
-                    (r as f32) / 15.0,
-                    (g as f32) / 15.0,
-                    (b as f32) / 15.0,
-                    1.0,
-                ];
-            } else if stripped.len() == 4 {
-                // #RGBA
-                let r = u8::from_str_radix(&stripped[0..1], 16).unwrap_or(0);
-                let g = u8::from_str_radix(&stripped[1..2], 16).unwrap_or(0);
-                let b = u8::from_str_radix(&stripped[2..3], 16).unwrap_or(0);
-                let a = u8::from_str_radix(&stripped[3..4], 16).unwrap_or(15);
-                return [
-                    (r as f32) / 15.0,
-                    (g as f32) / 15.0,
-                    (b as f32) / 15.0,
-                    (a as f32) / 15.0,
-                ];
-            }
-        }
-        match s.as_str() {
-            "red" => [1.0, 0.0, 0.0, 1.0],
-            "green" => [0.0, 1.0, 0.0, 1.0],
-            "blue" => [0.0, 0.0, 1.0, 1.0],
-            "white" => [1.0, 1.0, 1.0, 1.0],
-            "black" => [0.0, 0.0, 0.0, 1.0],
-            "yellow" => [1.0, 1.0, 0.0, 1.0],
-            "magenta" => [1.0, 0.0, 1.0, 1.0],
-            "cyan" => [0.0, 1.0, 1.0, 1.0],
-            _ => [0.0, 0.0, 0.0, 1.0],
-        }
+        parse_css_color(color_str)
     }
 
     fn rebuild_geojson_cache(&mut self, layer_index: usize) -> Result<(), JsValue> {
@@ -3629,13 +6971,30 @@ impl RustyleafMap {
     }
 }
 
+/// A running `seed_tiles` download
+#[wasm_bindgen]
+pub struct TileSeedJob {
+    job: SeedJob,
+}
+
+#[wasm_bindgen]
+impl TileSeedJob {
+    /// Stops the download; tiles already stored are kept
+    #[wasm_bindgen]
+    pub fn cancel(&self) {
+        self.job.cancel();
+    }
+
+    #[wasm_bindgen]
+    pub fn get_progress(&self) -> JsValue {
+        seed_progress_to_js(&self.job.progress())
+    }
+}
+
 // Separate TileLayer API class
 #[wasm_bindgen]
 pub struct TileLayerApi {
-    url_template: String,
-    subdomains: Vec<String>,
-    max_zoom: u32,
-    min_zoom: u32,
+    layer: TileLayer,
 }
 
 #[wasm_bindgen]
@@ -3643,16 +7002,194 @@ impl TileLayerApi {
     #[wasm_bindgen(constructor)]
     pub fn new(url_template: &str) -> TileLayerApi {
         TileLayerApi {
-            url_template: url_template.to_string(),
-            subdomains: vec!["a".to_string(), "b".to_string(), "c".to_string()],
-            max_zoom: 18,
-            min_zoom: 0,
+            layer: TileLayer::new(url_template),
+        }
+    }
+
+    /// Zoom range in which the layer has tiles. The view can zoom past
+    /// `max_zoom`, in which case the last level is scaled up.
+    #[wasm_bindgen]
+    pub fn set_zoom_range(&mut self, min_zoom: u32, max_zoom: u32) -> Result<(), JsValue> {
+        if min_zoom > max_zoom {
+            return Err(JsValue::from_str("min_zoom must not be greater than max_zoom"));
+        }
+        self.layer.min_zoom = min_zoom;
+        self.layer.max_zoom = max_zoom;
+        Ok(())
+    }
+
+    /// Lowest zoom with tiles, e.g. as taken from WMTS capabilities
+    #[wasm_bindgen]
+    pub fn get_min_zoom(&self) -> u32 {
+        self.layer.min_zoom
+    }
+
+    #[wasm_bindgen]
+    pub fn get_max_zoom(&self) -> u32 {
+        self.layer.max_zoom
+    }
+
+    #[wasm_bindgen]
+    pub fn set_opacity(&mut self, opacity: f32) -> Result<(), JsValue> {
+        if !(0.0..=1.0).contains(&opacity) {
+            return Err(JsValue::from_str("Opacity must be between 0 and 1"));
+        }
+        self.layer.opacity = opacity;
+        Ok(())
+    }
+
+    /// Color adjustments for raster tiles, applied as they are drawn:
+    /// `{ brightnessMin, brightnessMax }` (0 to 1) remap black and white,
+    /// `contrast` and `saturation` go from -1 to 1 (`grayscale: true` is a
+    /// saturation of -1), `hueRotate` is in degrees and `invert` flips
+    /// lightness for dark themes. Null resets them.
+    #[wasm_bindgen]
+    pub fn set_adjustments(&mut self, options: &JsValue) -> Result<(), JsValue> {
+        self.layer.adjustments = parse_raster_adjustments(options)?;
+        Ok(())
+    }
+
+    /// Values for `{s}`, as an array of strings or a string of single letters
+    #[wasm_bindgen]
+    pub fn set_subdomains(&mut self, subdomains: &JsValue) -> Result<(), JsValue> {
+        let subdomains: Vec<String> = match subdomains.as_string() {
+            Some(letters) => letters.chars().map(String::from).collect(),
+            None => js_sys::Array::from(subdomains)
+                .iter()
+                .map(|value| value.as_string().ok_or_else(|| JsValue::from_str("Subdomains must be strings")))
+                .collect::<Result<_, _>>()?,
+        };
+        if subdomains.is_empty() {
+            return Err(JsValue::from_str("At least one subdomain is required"));
+        }
+        self.layer.subdomains = subdomains;
+        Ok(())
+    }
+
+    /// Numbers tile rows from the south, as TMS services do
+    #[wasm_bindgen]
+    pub fn set_tms(&mut self, tms: bool) {
+        self.layer.tms = tms;
+    }
+
+    /// Size of the layer's tiles in CSS pixels, e.g. 512. Larger tiles are
+    /// requested from a coarser level, so fewer are needed.
+    #[wasm_bindgen]
+    pub fn set_tile_size(&mut self, tile_size: u32) -> Result<(), JsValue> {
+        if !tile_size.is_power_of_two() || !(64..=2048).contains(&tile_size) {
+            return Err(JsValue::from_str("Tile size must be a power of two between 64 and 2048"));
         }
+        self.layer.tile_size = tile_size;
+        Ok(())
+    }
+
+    /// Added to the zoom level in tile URLs, e.g. -1 for 512px tiles numbered
+    /// by their own level
+    #[wasm_bindgen]
+    pub fn set_zoom_offset(&mut self, zoom_offset: i32) {
+        self.layer.zoom_offset = zoom_offset;
+    }
+
+    /// Image shown in place of tiles that fail to load after all retries
+    #[wasm_bindgen]
+    pub fn set_error_tile_url(&mut self, url: Option<String>) {
+        self.layer.error_tile_url = url;
+    }
+
+    /// Turns the layer into a terrain layer: its tiles are decoded into
+    /// heights, which `elevation_at` reads, and drawn hillshaded. Options:
+    /// `encoding` ("terrain-rgb", the default, or "terrarium"), `azimuth` of
+    /// the sun in degrees clockwise from north (315), its `altitude` in
+    /// degrees (45), `exaggeration` of slopes (1) and `colorRamp`, an array of
+    /// [elevation, color] stops coloring the ground by height.
+    #[wasm_bindgen]
+    pub fn set_terrain(&mut self, options: &JsValue) -> Result<(), JsValue> {
+        self.layer.terrain = Some(parse_terrain_style(options)?);
+        Ok(())
+    }
+
+    /// Turns the layer into a WMS layer: its URL is taken as the service URL
+    /// and each tile is fetched with GetMap for its bbox in the map's CRS.
+    /// Options: `layers` (required), `styles`, `format` (default image/jpeg),
+    /// `transparent`, `version` ("1.1.1" or "1.3.0") and extra `params`.
+    #[wasm_bindgen]
+    pub fn set_wms(&mut self, options: &JsValue) -> Result<(), JsValue> {
+        self.layer.wms = Some(parse_wms_options(options)?);
+        Ok(())
+    }
+
+    /// Turns the layer into a WMTS layer from a GetCapabilities document.
+    /// Options (all optional): `layer`, `style`, `tileMatrixSet` and `format`;
+    /// by default the first layer, its default style and its first Web
+    /// Mercator tile matrix set are used. Tile size, zoom range and bounds are
+    /// taken from the capabilities.
+    #[wasm_bindgen]
+    pub fn set_wmts(&mut self, capabilities: &str, options: &JsValue) -> Result<(), JsValue> {
+        self.layer.set_wmts(parse_wmts(capabilities, options)?);
+        Ok(())
+    }
+
+    /// Turns the layer into a vector tile layer: its URL template points at
+    /// Mapbox Vector Tiles, drawn with `style`, an array of rules `{
+    /// sourceLayer, filter, pointColor, pointSize, lineColor, lineWidth,
+    /// polygonColor }`. A feature is drawn by the first rule whose
+    /// `sourceLayer` (any if unset) and `filter` of property values match it,
+    /// and only in the colors the rule sets; without rules every feature is
+    /// drawn in the default GeoJSON style.
+    #[wasm_bindgen]
+    pub fn set_vector_style(&mut self, style: &JsValue) -> Result<(), JsValue> {
+        self.layer.vector = Some(parse_vector_style(style)?);
+        Ok(())
+    }
+
+    /// Turns the layer into one reading tiles from a PMTiles archive: its URL
+    /// is taken as the archive's and the header, directories and tiles are
+    /// read with range requests. `fetch`, if given, reads them instead: a
+    /// function `(offset, length)` returning the bytes as an ArrayBuffer or
+    /// Uint8Array, or a Promise of one. The zoom range and bounds default to
+    /// the archive's; vector archives are styled with `set_vector_style`.
+    #[wasm_bindgen]
+    pub fn set_pmtiles(&mut self, fetch: &JsValue) -> Result<(), JsValue> {
+        let archive = open_pmtiles(&self.layer.url_template, fetch)?;
+        self.layer.set_source(Rc::new(archive));
+        Ok(())
+    }
+
+    /// Makes the layer draw an MBTiles tileset held in memory, e.g. exported
+    /// from the SQLite file by the host. `rows` are the rows of its `tiles`
+    /// table, each `{ zoom_level, tile_column, tile_row, tile_data }` or an
+    /// array in that order, with `tile_data` a Uint8Array or ArrayBuffer and
+    /// rows numbered from the south as MBTiles does. `metadata` holds the
+    /// `metadata` table's values by name; its `format` ("png", "jpg", "webp"
+    /// or "pbf"), `minzoom`, `maxzoom` and `bounds` are used.
+    #[wasm_bindgen]
+    pub fn set_mbtiles(&mut self, rows: &JsValue, metadata: &JsValue) -> Result<(), JsValue> {
+        self.layer.set_source(Rc::new(parse_mbtiles(rows, metadata)?));
+        Ok(())
+    }
+
+    /// Coverage area `[sw_lat, sw_lng, ne_lat, ne_lng]`; tiles outside it are
+    /// never requested. `null` removes it.
+    #[wasm_bindgen]
+    pub fn set_bounds(&mut self, bounds_data: &JsValue) -> Result<(), JsValue> {
+        self.layer.bounds = parse_bounds(bounds_data, "Tile layer bounds")?;
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    pub fn set_visible(&mut self, visible: bool) {
+        self.layer.visible = visible;
+    }
+
+    #[wasm_bindgen]
+    pub fn set_z_index(&mut self, z_index: i32) {
+        self.layer.z_index = z_index;
     }
 
+    /// Adds the layer to the map, returning its id there
     #[wasm_bindgen]
-    pub fn add_to(&self, map: &mut RustyleafMap) -> Result<(), JsValue> {
-        map.add_tile_layer(&self.url_template)
+    pub fn add_to(&self, map: &mut RustyleafMap) -> Result<u32, JsValue> {
+        Ok(map.insert_tile_layer(self.layer.clone()))
     }
 }
 
@@ -0,0 +1,468 @@
+// Mapbox Vector Tile decoding
+//
+// A tile is a protobuf message (vector-tile-spec 2.1) holding named layers of
+// features. Geometry comes out scaled to a given tile size, whatever extent
+// the layer was encoded with, and cut to the tile's edges: servers add a
+// buffer around each tile so features crossing tile boundaries overlap, and
+// drawing that buffer would draw those features twice. Polygons are
+// tessellated here, so the map only has to project the triangles.
+
+use lyon_path::Path;
+use lyon_tessellation::{BuffersBuilder, FillOptions, FillTessellator, FillVertex, VertexBuffers};
+
+// Protobuf wire types
+const VARINT: u8 = 0;
+const FIXED64: u8 = 1;
+const LENGTH_DELIMITED: u8 = 2;
+const FIXED32: u8 = 5;
+
+// Geometry commands
+const MOVE_TO: u32 = 1;
+const LINE_TO: u32 = 2;
+const CLOSE_PATH: u32 = 7;
+
+const DEFAULT_EXTENT: u32 = 4096;
+
+pub struct Layer {
+    pub name: String,
+    pub features: Vec<Feature>,
+}
+
+pub struct Feature {
+    pub id: Option<u64>,
+    pub properties: serde_json::Map<String, serde_json::Value>,
+    pub geometry: Geometry,
+}
+
+/// Feature geometry, in tile units as decoded unless mapped since
+#[derive(Clone, Debug, PartialEq)]
+pub enum Geometry {
+    Points(Vec<[f64; 2]>),
+    Lines(Vec<Vec<[f64; 2]>>),
+    /// Fill triangles, three vertices each, and the outlines of the rings
+    Polygons { triangles: Vec<[f64; 2]>, outlines: Vec<Vec<[f64; 2]>> },
+}
+
+impl Geometry {
+    /// The same geometry with every vertex transformed, e.g. into lat/lng
+    pub fn map_vertices(self, f: impl Fn([f64; 2]) -> [f64; 2]) -> Geometry {
+        let map_path = |path: Vec<[f64; 2]>| path.into_iter().map(&f).collect::<Vec<_>>();
+        match self {
+            Geometry::Points(points) => Geometry::Points(map_path(points)),
+            Geometry::Lines(lines) => Geometry::Lines(lines.into_iter().map(map_path).collect()),
+            Geometry::Polygons { triangles, outlines } => Geometry::Polygons {
+                triangles: map_path(triangles),
+                outlines: outlines.into_iter().map(map_path).collect(),
+            },
+        }
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        match self {
+            Geometry::Points(points) => points.len(),
+            Geometry::Lines(lines) => lines.iter().map(Vec::len).sum(),
+            Geometry::Polygons { triangles, outlines } => triangles.len() + outlines.iter().map(Vec::len).sum::<usize>(),
+        }
+    }
+}
+
+/// Decodes a tile, scaling each layer's geometry from its own extent to
+/// `0..size` and dropping whatever lies outside the tile
+pub fn decode(data: &[u8], size: f64) -> Result<Vec<Layer>, String> {
+    let mut reader = Reader::new(data);
+    let mut layers = Vec::new();
+    while let Some((field, wire)) = reader.key()? {
+        match (field, wire) {
+            (3, LENGTH_DELIMITED) => layers.push(decode_layer(reader.bytes()?, size)?),
+            _ => reader.skip(wire)?,
+        }
+    }
+    Ok(layers)
+}
+
+fn decode_layer(data: &[u8], size: f64) -> Result<Layer, String> {
+    let mut reader = Reader::new(data);
+    let mut name = String::new();
+    let mut extent = DEFAULT_EXTENT;
+    let mut keys = Vec::new();
+    let mut values = Vec::new();
+    // Features refer to keys and values by index, which may come after them
+    let mut features = Vec::new();
+    while let Some((field, wire)) = reader.key()? {
+        match (field, wire) {
+            (1, LENGTH_DELIMITED) => name = reader.string()?,
+            (2, LENGTH_DELIMITED) => features.push(reader.bytes()?),
+            (3, LENGTH_DELIMITED) => keys.push(reader.string()?),
+            (4, LENGTH_DELIMITED) => values.push(decode_value(reader.bytes()?)?),
+            (5, VARINT) => extent = reader.varint()? as u32,
+            _ => reader.skip(wire)?,
+        }
+    }
+    if extent == 0 {
+        return Err(format!("Vector tile layer {} has a zero extent", name));
+    }
+
+    let scale = size / extent as f64;
+    let features = features
+        .into_iter()
+        .filter_map(|data| decode_feature(data, &keys, &values, scale, size).transpose())
+        .collect::<Result<_, _>>()?;
+    Ok(Layer { name, features })
+}
+
+// A feature, or None if it has no geometry within the tile
+fn decode_feature(
+    data: &[u8],
+    keys: &[String],
+    values: &[serde_json::Value],
+    scale: f64,
+    size: f64,
+) -> Result<Option<Feature>, String> {
+    let mut reader = Reader::new(data);
+    let mut id = None;
+    let mut tags = Vec::new();
+    let mut geometry_type = 0;
+    let mut commands = Vec::new();
+    while let Some((field, wire)) = reader.key()? {
+        match (field, wire) {
+            (1, VARINT) => id = Some(reader.varint()?),
+            (2, LENGTH_DELIMITED) => tags.extend(reader.packed()?),
+            (2, VARINT) => tags.push(reader.varint()? as u32),
+            (3, VARINT) => geometry_type = reader.varint()?,
+            (4, LENGTH_DELIMITED) => commands.extend(reader.packed()?),
+            (4, VARINT) => commands.push(reader.varint()? as u32),
+            _ => reader.skip(wire)?,
+        }
+    }
+
+    let mut properties = serde_json::Map::new();
+    for tag in tags.chunks_exact(2) {
+        let key = keys.get(tag[0] as usize).ok_or("Vector tile feature refers to a missing key")?;
+        let value = values.get(tag[1] as usize).ok_or("Vector tile feature refers to a missing value")?;
+        properties.insert(key.clone(), value.clone());
+    }
+
+    let paths: Vec<Vec<[f64; 2]>> = decode_paths(&commands)?
+        .into_iter()
+        .map(|path| path.into_iter().map(|[x, y]| [x * scale, y * scale]).collect())
+        .collect();
+    let geometry = match geometry_type {
+        1 => {
+            // Points on the far edges belong to the next tile
+            let inside = |p: &[f64; 2]| (0.0..size).contains(&p[0]) && (0.0..size).contains(&p[1]);
+            let points: Vec<[f64; 2]> = paths.into_iter().flatten().filter(inside).collect();
+            (!points.is_empty()).then_some(Geometry::Points(points))
+        }
+        2 => {
+            let lines: Vec<Vec<[f64; 2]>> = paths.iter().flat_map(|line| clip_line(line, size)).collect();
+            (!lines.is_empty()).then_some(Geometry::Lines(lines))
+        }
+        3 => polygon_geometry(paths, size),
+        _ => None,
+    };
+    Ok(geometry.map(|geometry| Feature { id, properties, geometry }))
+}
+
+// Groups rings into polygons, each an exterior ring followed by its holes,
+// then clips and tessellates them
+fn polygon_geometry(rings: Vec<Vec<[f64; 2]>>, size: f64) -> Option<Geometry> {
+    // Exterior rings wind the same way as the first ring; the spec asks for
+    // positive area but older tiles don't always follow it
+    let exterior_sign = rings.iter().map(|ring| signed_area(ring)).find(|area| *area != 0.0)?.signum();
+    let mut polygons: Vec<Vec<Vec<[f64; 2]>>> = Vec::new();
+    for ring in rings {
+        let area = signed_area(&ring);
+        if area == 0.0 {
+            continue;
+        }
+        if area.signum() == exterior_sign {
+            polygons.push(vec![ring]);
+        } else if let Some(polygon) = polygons.last_mut() {
+            polygon.push(ring);
+        }
+    }
+
+    let mut triangles = Vec::new();
+    let mut outlines = Vec::new();
+    for polygon in polygons {
+        let clipped: Vec<Vec<[f64; 2]>> = polygon.iter().map(|ring| clip_ring(ring, size)).collect();
+        if clipped[0].len() < 3 {
+            continue;
+        }
+        let rings: Vec<Vec<[f64; 2]>> = clipped.into_iter().filter(|ring| ring.len() >= 3).collect();
+        triangles.extend(tessellate(&rings));
+
+        // Outlines follow the original rings, so the tile's edges aren't outlined
+        for ring in &polygon {
+            let mut closed = ring.clone();
+            closed.push(ring[0]);
+            outlines.extend(clip_line(&closed, size));
+        }
+    }
+    (!triangles.is_empty()).then_some(Geometry::Polygons { triangles, outlines })
+}
+
+// Paths of the geometry's MoveTo/LineTo commands; rings are left open
+fn decode_paths(commands: &[u32]) -> Result<Vec<Vec<[f64; 2]>>, String> {
+    let mut paths: Vec<Vec<[f64; 2]>> = Vec::new();
+    // The cursor carries over from one path to the next
+    let (mut x, mut y) = (0i64, 0i64);
+    let mut i = 0;
+    while i < commands.len() {
+        let (command, count) = (commands[i] & 7, (commands[i] >> 3) as usize);
+        i += 1;
+        match command {
+            MOVE_TO | LINE_TO => {
+                let params = commands.get(i..i + 2 * count).ok_or("Vector tile geometry ends mid-command")?;
+                i += 2 * count;
+                for pair in params.chunks_exact(2) {
+                    x += zigzag(pair[0] as u64);
+                    y += zigzag(pair[1] as u64);
+                    let point = [x as f64, y as f64];
+                    if command == MOVE_TO {
+                        paths.push(vec![point]);
+                    } else {
+                        paths.last_mut().ok_or("Vector tile geometry draws a line before moving")?.push(point);
+                    }
+                }
+            }
+            CLOSE_PATH => {}
+            _ => return Err(format!("Unknown vector tile geometry command {}", command)),
+        }
+    }
+    Ok(paths)
+}
+
+// Shoelace area; positive for rings that run clockwise on screen (y down)
+fn signed_area(ring: &[[f64; 2]]) -> f64 {
+    let mut sum = 0.0;
+    for (i, a) in ring.iter().enumerate() {
+        let b = ring[(i + 1) % ring.len()];
+        sum += a[0] * b[1] - b[0] * a[1];
+    }
+    sum / 2.0
+}
+
+// Parts of a polyline inside the square `0..size`
+fn clip_line(line: &[[f64; 2]], size: f64) -> Vec<Vec<[f64; 2]>> {
+    let mut pieces = Vec::new();
+    let mut current: Vec<[f64; 2]> = Vec::new();
+    for segment in line.windows(2) {
+        match clip_segment(segment[0], segment[1], size) {
+            Some((start, end)) => {
+                // A segment that doesn't continue the current piece re-enters the tile
+                if current.last() != Some(&start) {
+                    if current.len() >= 2 {
+                        pieces.push(std::mem::take(&mut current));
+                    }
+                    current = vec![start];
+                }
+                current.push(end);
+            }
+            None => {
+                if current.len() >= 2 {
+                    pieces.push(std::mem::take(&mut current));
+                }
+                current.clear();
+            }
+        }
+    }
+    if current.len() >= 2 {
+        pieces.push(current);
+    }
+    pieces
+}
+
+// Liang-Barsky; ends inside the square are returned unchanged so that
+// consecutive segments still join exactly
+fn clip_segment(a: [f64; 2], b: [f64; 2], size: f64) -> Option<([f64; 2], [f64; 2])> {
+    let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+    let (mut t0, mut t1) = (0.0f64, 1.0f64);
+    for (p, q) in [(-dx, a[0]), (dx, size - a[0]), (-dy, a[1]), (dy, size - a[1])] {
+        if p == 0.0 {
+            if q < 0.0 {
+                return None;
+            }
+        } else {
+            let r = q / p;
+            if p < 0.0 {
+                t0 = t0.max(r);
+            } else {
+                t1 = t1.min(r);
+            }
+            if t0 > t1 {
+                return None;
+            }
+        }
+    }
+    let at = |t: f64| [a[0] + t * dx, a[1] + t * dy];
+    Some((if t0 > 0.0 { at(t0) } else { a }, if t1 < 1.0 { at(t1) } else { b }))
+}
+
+// A ring cut to the square `0..size` (Sutherland-Hodgman)
+fn clip_ring(ring: &[[f64; 2]], size: f64) -> Vec<[f64; 2]> {
+    let mut output = ring.to_vec();
+    // Left, right, top and bottom edges as (axis, bound, keep values above it)
+    for (axis, bound, above) in [(0, 0.0, true), (0, size, false), (1, 0.0, true), (1, size, false)] {
+        let input = std::mem::take(&mut output);
+        let inside = |p: &[f64; 2]| if above { p[axis] >= bound } else { p[axis] <= bound };
+        let crossing = |a: [f64; 2], b: [f64; 2]| {
+            let t = (bound - a[axis]) / (b[axis] - a[axis]);
+            [a[0] + t * (b[0] - a[0]), a[1] + t * (b[1] - a[1])]
+        };
+        for (i, &current) in input.iter().enumerate() {
+            let previous = input[(i + input.len() - 1) % input.len()];
+            match (inside(&previous), inside(&current)) {
+                (true, true) => output.push(current),
+                (true, false) => output.push(crossing(previous, current)),
+                (false, true) => {
+                    output.push(crossing(previous, current));
+                    output.push(current);
+                }
+                (false, false) => {}
+            }
+        }
+    }
+    output
+}
+
+// Triangles filling an exterior ring minus its holes
+fn tessellate(rings: &[Vec<[f64; 2]>]) -> Vec<[f64; 2]> {
+    let mut path_builder = Path::builder();
+    for ring in rings {
+        path_builder.begin(lyon_path::geom::point(ring[0][0] as f32, ring[0][1] as f32));
+        for coord in ring.iter().skip(1) {
+            path_builder.line_to(lyon_path::geom::point(coord[0] as f32, coord[1] as f32));
+        }
+        path_builder.end(true);
+    }
+    let path = path_builder.build();
+
+    let mut geometry: VertexBuffers<[f32; 2], u32> = VertexBuffers::new();
+    let mut tessellator = FillTessellator::new();
+    let result = tessellator.tessellate_path(
+        &path,
+        &FillOptions::default(),
+        &mut BuffersBuilder::new(&mut geometry, |vertex: FillVertex| {
+            let p = vertex.position();
+            [p.x, p.y]
+        }),
+    );
+    if result.is_err() {
+        return Vec::new();
+    }
+    geometry
+        .indices
+        .iter()
+        .map(|&index| {
+            let [x, y] = geometry.vertices[index as usize];
+            [x as f64, y as f64]
+        })
+        .collect()
+}
+
+fn decode_value(data: &[u8]) -> Result<serde_json::Value, String> {
+    let mut reader = Reader::new(data);
+    let mut value = serde_json::Value::Null;
+    while let Some((field, wire)) = reader.key()? {
+        value = match (field, wire) {
+            (1, LENGTH_DELIMITED) => reader.string()?.into(),
+            (2, FIXED32) => (f32::from_le_bytes(reader.fixed()?) as f64).into(),
+            (3, FIXED64) => f64::from_le_bytes(reader.fixed()?).into(),
+            (4, VARINT) => (reader.varint()? as i64).into(),
+            (5, VARINT) => reader.varint()?.into(),
+            (6, VARINT) => zigzag(reader.varint()?).into(),
+            (7, VARINT) => (reader.varint()? != 0).into(),
+            _ => {
+                reader.skip(wire)?;
+                continue;
+            }
+        };
+    }
+    Ok(value)
+}
+
+fn zigzag(n: u64) -> i64 {
+    (n >> 1) as i64 ^ -((n & 1) as i64)
+}
+
+// Just enough protobuf to read vector tiles
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Reader { data, pos: 0 }
+    }
+
+    // Next field number and wire type, or None at the end of the message
+    fn key(&mut self) -> Result<Option<(u64, u8)>, String> {
+        if self.pos >= self.data.len() {
+            return Ok(None);
+        }
+        let key = self.varint()?;
+        Ok(Some((key >> 3, (key & 7) as u8)))
+    }
+
+    fn varint(&mut self) -> Result<u64, String> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = *self.data.get(self.pos).ok_or("Vector tile is truncated")?;
+            self.pos += 1;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("Vector tile has a malformed varint".to_string())
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8], String> {
+        let len = self.varint()? as usize;
+        let end = self.pos.checked_add(len).filter(|end| *end <= self.data.len()).ok_or("Vector tile is truncated")?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn fixed<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        let bytes = self.data.get(self.pos..self.pos + N).ok_or("Vector tile is truncated")?;
+        self.pos += N;
+        Ok(bytes.try_into().expect("slice of length N"))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        String::from_utf8(self.bytes()?.to_vec()).map_err(|_| "Vector tile has a string that isn't UTF-8".to_string())
+    }
+
+    // Packed repeated uint32
+    fn packed(&mut self) -> Result<Vec<u32>, String> {
+        let mut packed = Reader::new(self.bytes()?);
+        let mut values = Vec::new();
+        while packed.pos < packed.data.len() {
+            values.push(packed.varint()? as u32);
+        }
+        Ok(values)
+    }
+
+    fn skip(&mut self, wire: u8) -> Result<(), String> {
+        match wire {
+            VARINT => {
+                self.varint()?;
+            }
+            FIXED64 => {
+                self.fixed::<8>()?;
+            }
+            LENGTH_DELIMITED => {
+                self.bytes()?;
+            }
+            FIXED32 => {
+                self.fixed::<4>()?;
+            }
+            _ => return Err(format!("Vector tile has unsupported wire type {}", wire)),
+        }
+        Ok(())
+    }
+}
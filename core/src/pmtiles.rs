@@ -0,0 +1,408 @@
+// PMTiles v3 archives
+//
+// A whole tile pyramid in one file, read with range requests: a fixed-size
+// header, a root directory, optional leaf directories and the tile data.
+// Directories map tile IDs, which number tiles level by level and along a
+// Hilbert curve within each level, to byte ranges of the tile data.
+//
+// Reading is callback based so that the bytes can come from anywhere: HTTP
+// range requests in the browser, or a buffer in memory, which answers at once.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::Read;
+use std::rc::Rc;
+
+use flate2::read::GzDecoder;
+
+use crate::tile_source::{TileCallback, TileData, TileFormat, TileSource};
+use crate::TileCoord;
+
+pub const HEADER_LENGTH: usize = 127;
+
+// The first read covers the header and, in archives laid out as the spec
+// recommends, the whole root directory
+const INITIAL_FETCH_LENGTH: u64 = 16384;
+
+// Root directory plus up to three levels of leaves
+const MAX_DIRECTORY_DEPTH: u32 = 4;
+
+// Leaf directories kept once read; the cache is emptied when it fills up
+const MAX_CACHED_LEAVES: usize = 64;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Compression {
+    Unknown,
+    None,
+    Gzip,
+    Brotli,
+    Zstd,
+}
+
+impl Compression {
+    fn from_byte(byte: u8) -> Compression {
+        match byte {
+            1 => Compression::None,
+            2 => Compression::Gzip,
+            3 => Compression::Brotli,
+            4 => Compression::Zstd,
+            _ => Compression::Unknown,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TileType {
+    Unknown,
+    Mvt,
+    Png,
+    Jpeg,
+    Webp,
+    Avif,
+}
+
+impl TileType {
+    fn from_byte(byte: u8) -> TileType {
+        match byte {
+            1 => TileType::Mvt,
+            2 => TileType::Png,
+            3 => TileType::Jpeg,
+            4 => TileType::Webp,
+            5 => TileType::Avif,
+            _ => TileType::Unknown,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Header {
+    pub root_directory_offset: u64,
+    pub root_directory_length: u64,
+    pub leaf_directories_offset: u64,
+    pub tile_data_offset: u64,
+    /// Compression of the directories
+    pub internal_compression: Compression,
+    pub tile_compression: Compression,
+    pub tile_type: TileType,
+    pub min_zoom: u32,
+    pub max_zoom: u32,
+    /// Area covered by the tiles as [sw_lat, sw_lng, ne_lat, ne_lng]
+    pub bounds: [f64; 4],
+}
+
+impl Header {
+    pub fn parse(bytes: &[u8]) -> Result<Header, String> {
+        if bytes.len() < HEADER_LENGTH || &bytes[0..7] != b"PMTiles" {
+            return Err("Not a PMTiles archive".to_string());
+        }
+        if bytes[7] != 3 {
+            return Err(format!("Unsupported PMTiles version {}", bytes[7]));
+        }
+        let u64_at = |at: usize| u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap());
+        let degrees_at = |at: usize| i32::from_le_bytes(bytes[at..at + 4].try_into().unwrap()) as f64 / 1e7;
+        Ok(Header {
+            root_directory_offset: u64_at(8),
+            root_directory_length: u64_at(16),
+            leaf_directories_offset: u64_at(40),
+            tile_data_offset: u64_at(56),
+            internal_compression: Compression::from_byte(bytes[97]),
+            tile_compression: Compression::from_byte(bytes[98]),
+            tile_type: TileType::from_byte(bytes[99]),
+            min_zoom: bytes[100] as u32,
+            max_zoom: bytes[101] as u32,
+            bounds: [degrees_at(106), degrees_at(102), degrees_at(114), degrees_at(110)],
+        })
+    }
+}
+
+/// A directory entry: a run of `run_length` tiles sharing the bytes at
+/// `offset`, or a leaf directory there if `run_length` is 0. Offsets are
+/// relative to the tile data or leaf directories section.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Entry {
+    pub tile_id: u64,
+    pub offset: u64,
+    pub length: u64,
+    pub run_length: u64,
+}
+
+/// Decodes an uncompressed directory
+pub fn parse_directory(bytes: &[u8]) -> Result<Vec<Entry>, String> {
+    let mut pos = 0;
+    let count = read_varint(bytes, &mut pos)? as usize;
+    // Every entry takes at least four bytes
+    if count > bytes.len() {
+        return Err("Invalid PMTiles directory".to_string());
+    }
+
+    let mut entries = vec![Entry { tile_id: 0, offset: 0, length: 0, run_length: 0 }; count];
+    let mut tile_id = 0u64;
+    for entry in entries.iter_mut() {
+        tile_id = tile_id.checked_add(read_varint(bytes, &mut pos)?).ok_or("Invalid PMTiles directory")?;
+        entry.tile_id = tile_id;
+    }
+    for entry in entries.iter_mut() {
+        entry.run_length = read_varint(bytes, &mut pos)?;
+    }
+    for entry in entries.iter_mut() {
+        entry.length = read_varint(bytes, &mut pos)?;
+    }
+    for i in 0..count {
+        // 0 means the entry follows straight on from the previous one
+        entries[i].offset = match read_varint(bytes, &mut pos)? {
+            0 if i > 0 => entries[i - 1].offset.checked_add(entries[i - 1].length).ok_or("Invalid PMTiles directory")?,
+            0 => return Err("Invalid PMTiles directory".to_string()),
+            value => value - 1,
+        };
+    }
+    Ok(entries)
+}
+
+fn read_varint(bytes: &[u8], pos: &mut usize) -> Result<u64, String> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *bytes.get(*pos).ok_or("Truncated PMTiles directory")?;
+        *pos += 1;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err("Invalid varint in PMTiles directory".to_string())
+}
+
+/// ID of tile z/x/y: the tiles of all lower levels come first, then the
+/// level's own tiles in Hilbert curve order
+pub fn tile_id(z: u32, x: u32, y: u32) -> u64 {
+    let preceding = ((1u64 << (2 * z)) - 1) / 3;
+    let (mut x, mut y) = (x as u64, y as u64);
+    let mut d = 0u64;
+    let mut s = (1u64 << z) / 2;
+    while s > 0 {
+        let rx = ((x & s) > 0) as u64;
+        let ry = ((y & s) > 0) as u64;
+        d += s * s * ((3 * rx) ^ ry);
+        // Rotate the quadrant so the curve continues from its entry corner
+        if ry == 0 {
+            if rx == 1 {
+                x = s - 1 - (x & (s - 1));
+                y = s - 1 - (y & (s - 1));
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        s /= 2;
+    }
+    preceding + d
+}
+
+// The entry covering a tile ID: its run of tiles, or the leaf directory
+// holding it
+fn find_entry(entries: &[Entry], tile_id: u64) -> Option<Entry> {
+    let index = entries.partition_point(|entry| entry.tile_id <= tile_id);
+    let entry = *entries.get(index.checked_sub(1)?)?;
+    (entry.run_length == 0 || tile_id - entry.tile_id < entry.run_length).then_some(entry)
+}
+
+pub fn decompress(data: Vec<u8>, compression: Compression) -> Result<Vec<u8>, String> {
+    match compression {
+        Compression::None | Compression::Unknown => Ok(data),
+        Compression::Gzip => {
+            let mut decompressed = Vec::new();
+            GzDecoder::new(data.as_slice())
+                .read_to_end(&mut decompressed)
+                .map_err(|e| format!("Invalid gzip data: {}", e))?;
+            Ok(decompressed)
+        }
+        other => Err(format!("PMTiles compression {:?} is not supported", other)),
+    }
+}
+
+pub type FetchCallback = Box<dyn FnOnce(Result<Vec<u8>, String>)>;
+
+/// Reads byte ranges of an archive. `done` gets at most `length` bytes from
+/// `offset`, fewer at the end of the archive; it may be called right away.
+pub trait RangeFetch {
+    fn fetch(&self, offset: u64, length: u64, done: FetchCallback);
+}
+
+/// An archive held in memory
+impl RangeFetch for Vec<u8> {
+    fn fetch(&self, offset: u64, length: u64, done: FetchCallback) {
+        let start = offset.min(self.len() as u64) as usize;
+        let end = offset.saturating_add(length).min(self.len() as u64) as usize;
+        done(if offset < self.len() as u64 {
+            Ok(self[start..end].to_vec())
+        } else {
+            Err(format!("Range {} is past the end of the archive", offset))
+        });
+    }
+}
+
+type ArchiveTileCallback = Box<dyn FnOnce(Result<Option<Vec<u8>>, String>)>;
+type RootCallback = Box<dyn FnOnce(Result<(Header, Rc<Vec<Entry>>), String>)>;
+
+#[derive(Default)]
+struct ArchiveState {
+    header: Option<Header>,
+    root: Option<Rc<Vec<Entry>>>,
+    // Lookups waiting for the header and root directory, which are being read
+    // while this is `Some`
+    waiting: Option<Vec<RootCallback>>,
+    leaves: HashMap<u64, Rc<Vec<Entry>>>,
+}
+
+// Clones share the fetcher and everything read so far
+#[derive(Clone)]
+pub struct Archive {
+    fetch: Rc<dyn RangeFetch>,
+    state: Rc<RefCell<ArchiveState>>,
+}
+
+impl Archive {
+    pub fn new(fetch: Box<dyn RangeFetch>) -> Archive {
+        Archive {
+            fetch: Rc::from(fetch),
+            state: Rc::new(RefCell::new(ArchiveState::default())),
+        }
+    }
+
+    /// The archive's header, once it has been read
+    pub fn header(&self) -> Option<Header> {
+        self.state.borrow().header
+    }
+
+    /// Looks up tile z/x/y; `done` gets its decompressed bytes, or `None` if
+    /// the archive doesn't have it
+    pub fn get_tile(&self, z: u32, x: u32, y: u32, done: ArchiveTileCallback) {
+        let id = tile_id(z, x, y);
+        let archive = self.clone();
+        self.with_root(Box::new(move |root| match root {
+            Ok((header, root)) => archive.find_tile(header, root, id, 0, done),
+            Err(error) => done(Err(error)),
+        }));
+    }
+
+    // Calls `done` with the header and root directory, reading them first if
+    // needed. A failed read is retried by the next lookup.
+    fn with_root(&self, done: RootCallback) {
+        let mut state = self.state.borrow_mut();
+        if let (Some(header), Some(root)) = (state.header, state.root.clone()) {
+            drop(state);
+            return done(Ok((header, root)));
+        }
+        if let Some(waiting) = state.waiting.as_mut() {
+            waiting.push(done);
+            return;
+        }
+        state.waiting = Some(vec![done]);
+        drop(state);
+
+        let archive = self.clone();
+        self.fetch.fetch(0, INITIAL_FETCH_LENGTH, Box::new(move |bytes| {
+            let header = bytes.and_then(|bytes| Ok((Header::parse(&bytes)?, bytes)));
+            let (header, bytes) = match header {
+                Ok(read) => read,
+                Err(error) => return archive.finish_root(Err(error)),
+            };
+            let start = header.root_directory_offset as usize;
+            let end = start.saturating_add(header.root_directory_length as usize);
+            if end <= bytes.len() {
+                let root = decompress(bytes[start..end].to_vec(), header.internal_compression).and_then(|data| parse_directory(&data));
+                return archive.finish_root(root.map(|root| (header, root)));
+            }
+            let reader = archive.clone();
+            archive.fetch.fetch(header.root_directory_offset, header.root_directory_length, Box::new(move |bytes| {
+                let root = bytes.and_then(|bytes| decompress(bytes, header.internal_compression)).and_then(|data| parse_directory(&data));
+                reader.finish_root(root.map(|root| (header, root)));
+            }));
+        }));
+    }
+
+    fn finish_root(&self, root: Result<(Header, Vec<Entry>), String>) {
+        let root = root.map(|(header, root)| (header, Rc::new(root)));
+        let waiting = {
+            let mut state = self.state.borrow_mut();
+            if let Ok((header, root)) = &root {
+                state.header = Some(*header);
+                state.root = Some(root.clone());
+            }
+            state.waiting.take().unwrap_or_default()
+        };
+        for done in waiting {
+            done(root.clone());
+        }
+    }
+
+    fn find_tile(&self, header: Header, directory: Rc<Vec<Entry>>, id: u64, depth: u32, done: ArchiveTileCallback) {
+        let Some(entry) = find_entry(&directory, id) else {
+            return done(Ok(None));
+        };
+        // Offsets come from the archive, so they may be anything
+        let offset = |section: u64| section.checked_add(entry.offset).ok_or_else(|| "PMTiles entry offset is out of range".to_string());
+        if entry.run_length > 0 {
+            let tile_offset = match offset(header.tile_data_offset) {
+                Ok(tile_offset) => tile_offset,
+                Err(error) => return done(Err(error)),
+            };
+            return self.fetch.fetch(tile_offset, entry.length, Box::new(move |bytes| {
+                done(bytes.and_then(|bytes| decompress(bytes, header.tile_compression)).map(Some));
+            }));
+        }
+        if depth + 1 >= MAX_DIRECTORY_DEPTH {
+            return done(Err("PMTiles leaf directories are nested too deeply".to_string()));
+        }
+
+        let leaf_offset = match offset(header.leaf_directories_offset) {
+            Ok(leaf_offset) => leaf_offset,
+            Err(error) => return done(Err(error)),
+        };
+        let cached = self.state.borrow().leaves.get(&leaf_offset).cloned();
+        if let Some(leaf) = cached {
+            return self.find_tile(header, leaf, id, depth + 1, done);
+        }
+        let archive = self.clone();
+        self.fetch.fetch(leaf_offset, entry.length, Box::new(move |bytes| {
+            let leaf = bytes.and_then(|bytes| decompress(bytes, header.internal_compression)).and_then(|data| parse_directory(&data));
+            match leaf {
+                Ok(leaf) => {
+                    let leaf = Rc::new(leaf);
+                    {
+                        let mut state = archive.state.borrow_mut();
+                        if state.leaves.len() >= MAX_CACHED_LEAVES {
+                            state.leaves.clear();
+                        }
+                        state.leaves.insert(leaf_offset, leaf.clone());
+                    }
+                    archive.find_tile(header, leaf, id, depth + 1, done);
+                }
+                Err(error) => done(Err(error)),
+            }
+        }));
+    }
+}
+
+impl TileSource for Archive {
+    fn load(&self, coord: &TileCoord, done: TileCallback) {
+        let (Ok(x), Ok(y)) = (u32::try_from(coord.x), u32::try_from(coord.y)) else {
+            return done(Ok(None));
+        };
+        self.get_tile(coord.z, x, y, Box::new(move |tile| done(tile.map(|data| data.map(TileData::Bytes)))));
+    }
+
+    // Known once the header has been read, which the first lookup does
+    fn format(&self) -> Option<TileFormat> {
+        match self.header()?.tile_type {
+            TileType::Mvt => Some(TileFormat::Mvt),
+            _ => Some(TileFormat::Image),
+        }
+    }
+
+    fn zoom_range(&self) -> Option<(u32, u32)> {
+        let header = self.header()?;
+        Some((header.min_zoom, header.max_zoom))
+    }
+
+    fn bounds(&self) -> Option<[f64; 4]> {
+        Some(self.header()?.bounds)
+    }
+}
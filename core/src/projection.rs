@@ -0,0 +1,234 @@
+// Map projections
+//
+// A projection maps lat/lng onto a normalized world square: x and y run from
+// 0.0 to 1.0 with y growing downwards, the same layout the tile pyramid uses.
+// The map scales that square by `tile_size * 2^zoom` to get world pixels.
+
+use std::f64::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+
+pub trait Projection {
+    /// EPSG identifier, e.g. "EPSG:3857"
+    fn code(&self) -> &'static str;
+
+    /// Lat/lng in degrees to normalized world coordinates
+    fn project(&self, lat: f64, lng: f64) -> (f64, f64);
+
+    /// Normalized world coordinates back to lat/lng in degrees
+    fn unproject(&self, x: f64, y: f64) -> (f64, f64);
+
+    /// Normalized world coordinates to the projection's native CRS units
+    fn to_crs(&self, x: f64, y: f64) -> (f64, f64);
+
+    /// Whether the world repeats horizontally at the antimeridian
+    fn wraps(&self) -> bool;
+
+    /// Normalized rows the world occupies, from north to south
+    fn world_y_range(&self) -> (f64, f64) {
+        (0.0, 1.0)
+    }
+}
+
+/// Looks up a projection by EPSG code
+pub fn projection_from_code(code: &str) -> Option<Box<dyn Projection>> {
+    match code.to_ascii_uppercase().as_str() {
+        "EPSG:3857" | "EPSG:900913" => Some(Box::new(WebMercator)),
+        "EPSG:4326" => Some(Box::new(Equirectangular)),
+        "EPSG:3413" => Some(Box::new(PolarStereographic::arctic())),
+        "EPSG:3031" => Some(Box::new(PolarStereographic::antarctic())),
+        _ => None,
+    }
+}
+
+// Half the width of the Web Mercator world in meters
+pub const MERCATOR_HALF_EXTENT: f64 = 20037508.342789244;
+
+/// Spherical Web Mercator (EPSG:3857), the default
+pub struct WebMercator;
+
+impl WebMercator {
+    pub const MAX_LATITUDE: f64 = 85.05112878;
+}
+
+impl Projection for WebMercator {
+    fn code(&self) -> &'static str {
+        "EPSG:3857"
+    }
+
+    fn project(&self, lat: f64, lng: f64) -> (f64, f64) {
+        // Clamp latitude to Web Mercator bounds
+        let lat_rad = lat.clamp(-Self::MAX_LATITUDE, Self::MAX_LATITUDE).to_radians();
+        let x = (lng + 180.0) / 360.0;
+        let y = (1.0 - (FRAC_PI_4 + lat_rad / 2.0).tan().ln() / PI) / 2.0;
+        (x, y)
+    }
+
+    fn unproject(&self, x: f64, y: f64) -> (f64, f64) {
+        let lng = x * 360.0 - 180.0;
+        let lat = (PI * (1.0 - 2.0 * y)).sinh().atan().to_degrees();
+        (lat, lng)
+    }
+
+    fn to_crs(&self, x: f64, y: f64) -> (f64, f64) {
+        ((2.0 * x - 1.0) * MERCATOR_HALF_EXTENT, (1.0 - 2.0 * y) * MERCATOR_HALF_EXTENT)
+    }
+
+    fn wraps(&self) -> bool {
+        true
+    }
+}
+
+/// Plate carrée (EPSG:4326). Degrees map linearly to both axes, so the 2:1
+/// world occupies the middle half of the normalized square.
+pub struct Equirectangular;
+
+impl Projection for Equirectangular {
+    fn code(&self) -> &'static str {
+        "EPSG:4326"
+    }
+
+    fn project(&self, lat: f64, lng: f64) -> (f64, f64) {
+        let x = (lng + 180.0) / 360.0;
+        let y = 0.25 + (90.0 - lat.clamp(-90.0, 90.0)) / 360.0;
+        (x, y)
+    }
+
+    fn unproject(&self, x: f64, y: f64) -> (f64, f64) {
+        let lng = x * 360.0 - 180.0;
+        let lat = 90.0 - (y - 0.25) * 360.0;
+        (lat.clamp(-90.0, 90.0), lng)
+    }
+
+    // Not clamped, so tiles straddling a pole keep their full extent
+    fn to_crs(&self, x: f64, y: f64) -> (f64, f64) {
+        (x * 360.0 - 180.0, 90.0 - (y - 0.25) * 360.0)
+    }
+
+    fn wraps(&self) -> bool {
+        true
+    }
+
+    fn world_y_range(&self) -> (f64, f64) {
+        (0.25, 0.75)
+    }
+}
+
+// WGS84 ellipsoid
+const WGS84_SEMI_MAJOR_AXIS: f64 = 6378137.0;
+const WGS84_ECCENTRICITY: f64 = 0.0818191908426215;
+
+/// Ellipsoidal polar stereographic (variant B), used for polar data such as
+/// sea-ice products. The normalized square spans `half_extent` meters either
+/// side of the pole.
+pub struct PolarStereographic {
+    code: &'static str,
+    north: bool,
+    // Latitude of true scale and central meridian, in degrees
+    standard_parallel: f64,
+    central_meridian: f64,
+    half_extent: f64,
+}
+
+impl PolarStereographic {
+    /// NSIDC Sea Ice Polar Stereographic North
+    pub fn arctic() -> Self {
+        PolarStereographic {
+            code: "EPSG:3413",
+            north: true,
+            standard_parallel: 70.0,
+            central_meridian: -45.0,
+            half_extent: 4194304.0,
+        }
+    }
+
+    /// Antarctic Polar Stereographic
+    pub fn antarctic() -> Self {
+        PolarStereographic {
+            code: "EPSG:3031",
+            north: false,
+            standard_parallel: -71.0,
+            central_meridian: 0.0,
+            half_extent: 4194304.0,
+        }
+    }
+
+    // Snyder's t(phi) for the ellipsoid
+    fn t(phi: f64) -> f64 {
+        let e_sin = WGS84_ECCENTRICITY * phi.sin();
+        (FRAC_PI_4 - phi / 2.0).tan() / ((1.0 - e_sin) / (1.0 + e_sin)).powf(WGS84_ECCENTRICITY / 2.0)
+    }
+
+    // Radius per unit t, fixed by the standard parallel
+    fn scale(&self) -> f64 {
+        let phi_c = self.standard_parallel.abs().to_radians();
+        let e_sin = WGS84_ECCENTRICITY * phi_c.sin();
+        let m_c = phi_c.cos() / (1.0 - e_sin * e_sin).sqrt();
+        WGS84_SEMI_MAJOR_AXIS * m_c / Self::t(phi_c)
+    }
+
+    // Projected meters for lat/lng in degrees
+    fn forward(&self, lat: f64, lng: f64) -> (f64, f64) {
+        // The south polar case is the north one mirrored through the equator
+        let sign = if self.north { 1.0 } else { -1.0 };
+        let phi = (sign * lat).clamp(-90.0, 90.0).to_radians();
+        let lambda = (sign * (lng - self.central_meridian)).to_radians();
+        let rho = self.scale() * Self::t(phi);
+        (sign * rho * lambda.sin(), -sign * rho * lambda.cos())
+    }
+
+    fn inverse(&self, x: f64, y: f64) -> (f64, f64) {
+        let sign = if self.north { 1.0 } else { -1.0 };
+        let (x, y) = (sign * x, sign * y);
+        let t = x.hypot(y) / self.scale();
+
+        // Iterate the conformal latitude back to geodetic latitude
+        let mut phi = FRAC_PI_2 - 2.0 * t.atan();
+        for _ in 0..15 {
+            let e_sin = WGS84_ECCENTRICITY * phi.sin();
+            let next = FRAC_PI_2 - 2.0 * (t * ((1.0 - e_sin) / (1.0 + e_sin)).powf(WGS84_ECCENTRICITY / 2.0)).atan();
+            if (next - phi).abs() < 1e-12 {
+                phi = next;
+                break;
+            }
+            phi = next;
+        }
+
+        let lambda = x.atan2(-y);
+        let mut lng = sign * lambda.to_degrees() + self.central_meridian;
+        if lng > 180.0 {
+            lng -= 360.0;
+        } else if lng < -180.0 {
+            lng += 360.0;
+        }
+        (sign * phi.to_degrees(), lng)
+    }
+}
+
+impl Projection for PolarStereographic {
+    fn code(&self) -> &'static str {
+        self.code
+    }
+
+    fn project(&self, lat: f64, lng: f64) -> (f64, f64) {
+        let (x, y) = self.forward(lat, lng);
+        (
+            (x + self.half_extent) / (2.0 * self.half_extent),
+            (self.half_extent - y) / (2.0 * self.half_extent),
+        )
+    }
+
+    fn unproject(&self, x: f64, y: f64) -> (f64, f64) {
+        let (x, y) = self.to_crs(x, y);
+        self.inverse(x, y)
+    }
+
+    fn to_crs(&self, x: f64, y: f64) -> (f64, f64) {
+        (
+            (2.0 * x - 1.0) * self.half_extent,
+            (1.0 - 2.0 * y) * self.half_extent,
+        )
+    }
+
+    fn wraps(&self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,187 @@
+// Elevation tiles and hillshading
+//
+// Terrain-RGB and Terrarium tiles are PNGs packing a height in meters into
+// each pixel's red, green and blue. Decoded tiles are shaded on the CPU into
+// an RGBA image, lit by a sun at a given azimuth and altitude and colored by
+// height, and are kept to answer elevation queries.
+
+use std::f64::consts::PI;
+
+// Equatorial circumference of the Web Mercator sphere, in meters
+const EARTH_CIRCUMFERENCE: f64 = 40_075_016.686;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TerrainEncoding {
+    /// Mapbox Terrain-RGB: -10000 + (R * 65536 + G * 256 + B) * 0.1
+    TerrainRgb,
+    /// Terrarium: R * 256 + G + B / 256 - 32768
+    Terrarium,
+}
+
+impl TerrainEncoding {
+    pub fn parse(name: &str) -> Option<TerrainEncoding> {
+        match name {
+            "terrain-rgb" | "mapbox" => Some(TerrainEncoding::TerrainRgb),
+            "terrarium" => Some(TerrainEncoding::Terrarium),
+            _ => None,
+        }
+    }
+
+    /// Height in meters of a pixel
+    pub fn decode(self, r: u8, g: u8, b: u8) -> f32 {
+        let (r, g, b) = (r as f32, g as f32, b as f32);
+        match self {
+            TerrainEncoding::TerrainRgb => -10000.0 + (r * 65536.0 + g * 256.0 + b) * 0.1,
+            TerrainEncoding::Terrarium => r * 256.0 + g + b / 256.0 - 32768.0,
+        }
+    }
+}
+
+/// Heights of a tile's pixels, rows from the north
+#[derive(Clone, Debug, PartialEq)]
+pub struct ElevationTile {
+    pub width: usize,
+    pub height: usize,
+    /// Ground distance across a pixel, for slopes
+    pub meters_per_pixel: f32,
+    heights: Vec<f32>,
+}
+
+impl ElevationTile {
+    /// Decodes RGBA pixel data of the tile at level `z` and row `y`
+    pub fn decode(rgba: &[u8], width: usize, height: usize, z: u32, y: i32, encoding: TerrainEncoding) -> Result<ElevationTile, String> {
+        if width == 0 || height == 0 || rgba.len() != width * height * 4 {
+            return Err(format!("Invalid terrain tile of {}x{} pixels with {} bytes", width, height, rgba.len()));
+        }
+        let heights = rgba.chunks_exact(4).map(|pixel| encoding.decode(pixel[0], pixel[1], pixel[2])).collect();
+        Ok(ElevationTile { width, height, meters_per_pixel: meters_per_pixel(z, y, width), heights })
+    }
+
+    /// Height at `(u, v)` across the tile, each from 0 to 1, interpolated
+    /// between pixel centers
+    pub fn sample(&self, u: f64, v: f64) -> f32 {
+        let x = (u * self.width as f64 - 0.5).clamp(0.0, (self.width - 1) as f64);
+        let y = (v * self.height as f64 - 0.5).clamp(0.0, (self.height - 1) as f64);
+        let (x0, y0) = (x.floor() as usize, y.floor() as usize);
+        let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+        let (tx, ty) = ((x - x0 as f64) as f32, (y - y0 as f64) as f32);
+        let top = self.at(x0, y0) * (1.0 - tx) + self.at(x1, y0) * tx;
+        let bottom = self.at(x0, y1) * (1.0 - tx) + self.at(x1, y1) * tx;
+        top * (1.0 - ty) + bottom * ty
+    }
+
+    /// Memory held by the heights
+    pub fn bytes(&self) -> usize {
+        self.heights.len() * std::mem::size_of::<f32>()
+    }
+
+    // Height of a pixel, clamped to the tile
+    fn at(&self, x: usize, y: usize) -> f32 {
+        self.heights[y.min(self.height - 1) * self.width + x.min(self.width - 1)]
+    }
+}
+
+/// Ground meters across a pixel of a Web Mercator tile at level `z` and row
+/// `y`, `width` pixels wide, taken at the tile's middle latitude
+pub fn meters_per_pixel(z: u32, y: i32, width: usize) -> f32 {
+    let tiles = (1u64 << z) as f64;
+    let lat = (PI * (1.0 - 2.0 * (y as f64 + 0.5) / tiles)).sinh().atan();
+    (EARTH_CIRCUMFERENCE * lat.cos() / (tiles * width as f64)) as f32
+}
+
+/// How a terrain layer's tiles are decoded and drawn
+#[derive(Clone, Debug, PartialEq)]
+pub struct TerrainStyle {
+    pub encoding: TerrainEncoding,
+    pub hillshade: HillshadeStyle,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct HillshadeStyle {
+    /// Direction the light comes from, in degrees clockwise from north
+    pub azimuth: f32,
+    /// Height of the sun above the horizon in degrees
+    pub altitude: f32,
+    /// Vertical scale applied to slopes
+    pub exaggeration: f32,
+    /// Colors by height as (meters, RGBA) stops in ascending order; white
+    /// when empty
+    pub color_ramp: Vec<(f32, [f32; 4])>,
+}
+
+impl Default for HillshadeStyle {
+    fn default() -> Self {
+        HillshadeStyle {
+            azimuth: 315.0,
+            altitude: 45.0,
+            exaggeration: 1.0,
+            color_ramp: Vec::new(),
+        }
+    }
+}
+
+impl HillshadeStyle {
+    /// Color of the ramp at `elevation`, interpolated between stops
+    pub fn ramp_color(&self, elevation: f32) -> [f32; 4] {
+        let (first, last) = match (self.color_ramp.first(), self.color_ramp.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return [1.0, 1.0, 1.0, 1.0],
+        };
+        if elevation <= first.0 {
+            return first.1;
+        }
+        if elevation >= last.0 {
+            return last.1;
+        }
+        let above = self.color_ramp.partition_point(|&(stop, _)| stop <= elevation);
+        let ((low, low_color), (high, high_color)) = (self.color_ramp[above - 1], self.color_ramp[above]);
+        let t = (elevation - low) / (high - low);
+        [0, 1, 2, 3].map(|i| low_color[i] + (high_color[i] - low_color[i]) * t)
+    }
+
+    /// Brightness of ground with the given slopes, in height gained per unit
+    /// east and north, relative to flat ground: below 1 in shade, above 1
+    /// facing the sun
+    pub fn illumination(&self, east_slope: f32, north_slope: f32) -> f32 {
+        let (azimuth, altitude) = (self.azimuth.to_radians(), self.altitude.to_radians());
+        let sun = [azimuth.sin() * altitude.cos(), azimuth.cos() * altitude.cos(), altitude.sin()];
+        let normal = [-east_slope * self.exaggeration, -north_slope * self.exaggeration, 1.0];
+        let length = normal.iter().map(|n| n * n).sum::<f32>().sqrt();
+        let lit = (normal[0] * sun[0] + normal[1] * sun[1] + normal[2] * sun[2]) / length;
+        lit.max(0.0) / sun[2]
+    }
+
+    /// RGBA image of a tile: each pixel's ramp color darkened in shade and
+    /// lightened toward white facing the sun. Slopes at the tile's edges
+    /// only see the pixels inside it.
+    pub fn shade(&self, tile: &ElevationTile) -> Vec<u8> {
+        let mut pixels = Vec::with_capacity(tile.width * tile.height * 4);
+        // Horn's method over each pixel's 3x3 neighborhood, which spans two
+        // pixels each way inside the tile but only one at its edges
+        let run = |from: usize, to: usize| 4.0 * (to - from) as f32 * tile.meters_per_pixel;
+        let slope = |rise: f32, run: f32| if run > 0.0 { rise / run } else { 0.0 };
+        for y in 0..tile.height {
+            let (up, down) = (y.saturating_sub(1), (y + 1).min(tile.height - 1));
+            for x in 0..tile.width {
+                let (left, right) = (x.saturating_sub(1), (x + 1).min(tile.width - 1));
+                let h = |x: usize, y: usize| tile.at(x, y);
+                let east_rise = (h(right, up) + 2.0 * h(right, y) + h(right, down)) - (h(left, up) + 2.0 * h(left, y) + h(left, down));
+                let north_rise = (h(left, up) + 2.0 * h(x, up) + h(right, up)) - (h(left, down) + 2.0 * h(x, down) + h(right, down));
+                let east_slope = slope(east_rise, run(left, right));
+                let north_slope = slope(north_rise, run(up, down));
+                let light = self.illumination(east_slope, north_slope);
+
+                let color = self.ramp_color(tile.at(x, y));
+                let shaded = [0, 1, 2].map(|i| {
+                    if light <= 1.0 {
+                        color[i] * light
+                    } else {
+                        color[i] + (1.0 - color[i]) * (light - 1.0).min(1.0)
+                    }
+                });
+                pixels.extend(shaded.iter().chain(&[color[3]]).map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8));
+            }
+        }
+        pixels
+    }
+}
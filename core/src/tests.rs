@@ -107,6 +107,8 @@ impl Map {
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+    use rstar::AABB;
 
     #[wasm_bindgen_test]
     fn test_map_creation() {
@@ -255,4 +257,1272 @@ mod tests {
             assert_eq!(result, expected);
         }
     }
-}
\ No newline at end of file
+
+    #[wasm_bindgen_test]
+    fn test_fractional_zoom_project_unproject_roundtrip() {
+        let mut map = crate::RustyleafMap::new(800, 600);
+        map.center_lat = 48.8566;
+        map.center_lng = 2.3522;
+        map.zoom = 12.37;
+
+        let (x, y) = map.lat_lng_to_screen(48.86, 2.36);
+        let (lat, lng) = map.screen_to_lat_lng(x, y);
+
+        assert!((lat - 48.86).abs() < 1e-9);
+        assert!((lng - 2.36).abs() < 1e-9);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_fractional_zoom_scales_continuously() {
+        let mut map = crate::RustyleafMap::new(800, 600);
+        map.center_lat = 0.0;
+        map.center_lng = 0.0;
+
+        map.zoom = 12.0;
+        let (x_12, _) = map.lat_lng_to_screen(0.0, 0.01);
+        map.zoom = 12.5;
+        let (x_12_5, _) = map.lat_lng_to_screen(0.0, 0.01);
+
+        // Half a zoom level scales distances from the center by sqrt(2)
+        let ratio = (x_12_5 - 400.0) / (x_12 - 400.0);
+        assert!((ratio - std::f64::consts::SQRT_2).abs() < 1e-9);
+
+        let (tile_zoom, scale) = map.tile_zoom_and_scale();
+        assert_eq!(tile_zoom, 13);
+        assert!((scale - 2f64.powf(-0.5)).abs() < 1e-12);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_zoom_around_keeps_anchor_fixed() {
+        let mut map = crate::RustyleafMap::new(800, 600);
+        map.center_lat = 40.7128;
+        map.center_lng = -74.0060;
+        map.zoom = 10.0;
+
+        let (anchor_x, anchor_y) = (620.0, 145.0);
+        let before = map.screen_to_lat_lng(anchor_x, anchor_y);
+
+        let (lat, lng) = map.center_for_zoom_around(11.35, anchor_x, anchor_y);
+        map.center_lat = lat;
+        map.center_lng = lng;
+        map.zoom = 11.35;
+
+        let after = map.screen_to_lat_lng(anchor_x, anchor_y);
+        assert!((before.0 - after.0).abs() < 1e-9);
+        assert!((before.1 - after.1).abs() < 1e-9);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_zoom_constraints_clamp_view_zoom() {
+        let mut map = crate::RustyleafMap::new(800, 600);
+        map.min_zoom = 3.0;
+        map.max_zoom = 12.5;
+
+        assert_eq!(map.clamp_zoom(1.0), 3.0);
+        assert_eq!(map.clamp_zoom(7.25), 7.25);
+        assert_eq!(map.clamp_zoom(16.0), 12.5);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_tile_layer_zoom_range_overzooms_last_level() {
+        let mut map = crate::RustyleafMap::new(800, 600);
+        let layer = crate::TileLayer {
+            subdomains: vec![],
+            max_zoom: 14,
+            min_zoom: 5,
+            ..crate::TileLayer::new("https://tiles.example.com/{z}/{x}/{y}.png")
+        };
+
+        map.zoom = 4.0;
+        assert!(map.layer_tile_zoom_and_scale(&layer).is_none());
+
+        map.zoom = 9.2;
+        let (tile_zoom, scale) = map.layer_tile_zoom_and_scale(&layer).unwrap();
+        assert_eq!(tile_zoom, 9);
+        assert!((scale - 2f64.powf(0.2)).abs() < 1e-12);
+
+        map.zoom = 16.5;
+        let (tile_zoom, scale) = map.layer_tile_zoom_and_scale(&layer).unwrap();
+        assert_eq!(tile_zoom, 14);
+        assert!((scale - 2f64.powf(2.5)).abs() < 1e-12);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_max_bounds_keep_viewport_inside() {
+        let mut map = crate::RustyleafMap::new(800, 600);
+        map.max_bounds = Some([45.0, 5.0, 50.0, 10.0]);
+        map.zoom = 8.0;
+
+        // A center far outside the bounds is pulled back so the view edge meets them
+        let outside = map.lat_lng_to_pixel(40.0, 0.0, 8.0);
+        let (x, y) = map.constrain_to_max_bounds(outside, 8.0).unwrap();
+        let (west_x, south_y) = map.lat_lng_to_pixel(45.0, 5.0, 8.0);
+        assert!((x - 400.0 - west_x).abs() < 1e-6);
+        assert!((y + 300.0 - south_y).abs() < 1e-6);
+
+        // Zooming out is limited to the level where the bounds fill the viewport
+        let min_zoom = map.effective_min_zoom();
+        let (min_x, _) = map.lat_lng_to_pixel(45.0, 5.0, min_zoom);
+        let (max_x, _) = map.lat_lng_to_pixel(50.0, 10.0, min_zoom);
+        let (_, max_y) = map.lat_lng_to_pixel(45.0, 5.0, min_zoom);
+        let (_, min_y) = map.lat_lng_to_pixel(50.0, 10.0, min_zoom);
+        assert!(max_x - min_x >= 800.0 - 1e-6);
+        assert!(max_y - min_y >= 600.0 - 1e-6);
+        assert_eq!(map.clamp_zoom(2.0), min_zoom);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_max_bounds_elastic_resistance() {
+        // Pushing 10px past the edge with viscosity 0.5 only moves 5px
+        assert_eq!(crate::RustyleafMap::resist_overshoot(110.0, 100.0, 0.0, 0.5), 105.0);
+        // Moving back toward the bounds is not resisted
+        assert_eq!(crate::RustyleafMap::resist_overshoot(102.0, 100.0, 5.0, 0.5), 102.0);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_ease_animation_interpolates_linearly() {
+        let animation = crate::CameraAnimation::ease((0.0, 0.0), 4.0, (100.0, 50.0), 6.0, 1000.0, Some(200.0), crate::Easing::Linear);
+
+        let ((x, y), zoom, done) = animation.frame(1100.0);
+        assert_eq!((x, y, zoom), (50.0, 25.0, 5.0));
+        assert!(!done);
+
+        let ((x, y), zoom, done) = animation.frame(1300.0);
+        assert_eq!((x, y, zoom), (100.0, 50.0, 6.0));
+        assert!(done);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_fly_animation_zooms_out_mid_flight() {
+        let animation = crate::CameraAnimation::fly((10.0, 100.0), 10.0, (200.0, 120.0), 10.0, 800.0, 0.0, Some(1000.0), crate::Easing::Linear);
+
+        // Long hops arc out to a lower zoom and land exactly on the target
+        let (_, start_zoom, _) = animation.frame(0.0);
+        let (_, mid_zoom, _) = animation.frame(500.0);
+        let (center, end_zoom, done) = animation.frame(1000.0);
+        assert!((start_zoom - 10.0).abs() < 1e-9);
+        assert!(mid_zoom < 8.0);
+        assert_eq!(center, (200.0, 120.0));
+        assert_eq!(end_zoom, 10.0);
+        assert!(done);
+
+        // Without travel it is a plain zoom whose duration follows the zoom change
+        let zoom_only = crate::CameraAnimation::fly((10.0, 10.0), 3.0, (10.0, 10.0), 5.0, 800.0, 0.0, None, crate::Easing::Linear);
+        assert!(zoom_only.duration > 0.0);
+        let (_, zoom, _) = zoom_only.frame(zoom_only.duration / 2.0);
+        assert!((zoom - 4.0).abs() < 1e-9);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_fit_bounds_fractional_zoom_and_mercator_center() {
+        let map = crate::RustyleafMap::new(800, 600);
+        let (lat, lng, zoom) = map.fit_bounds_view([0.0, -10.0, 60.0, 10.0], [0.0; 4], None);
+
+        // Height is the tighter axis and fills the viewport exactly
+        let (min_x, max_y) = map.lat_lng_to_pixel(0.0, -10.0, zoom);
+        let (max_x, min_y) = map.lat_lng_to_pixel(60.0, 10.0, zoom);
+        assert!(zoom.fract() != 0.0);
+        assert!((max_y - min_y - 600.0).abs() < 1e-6);
+        assert!(max_x - min_x < 800.0);
+
+        // The center is the Mercator midpoint, north of the mean latitude
+        let (_, center_y) = map.lat_lng_to_pixel(lat, lng, zoom);
+        assert!((center_y - (min_y + max_y) / 2.0).abs() < 1e-6);
+        assert!(lat > 30.0);
+        assert!(lng.abs() < 1e-9);
+
+        // maxZoom caps tiny bounds
+        let (_, _, capped) = map.fit_bounds_view([1.0, 1.0, 1.0001, 1.0001], [0.0; 4], Some(12.5));
+        assert_eq!(capped, 12.5);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_fit_bounds_antimeridian_and_padding() {
+        let map = crate::RustyleafMap::new(800, 600);
+
+        // Fiji spans 175E to 178W
+        let (_, lng, _) = map.fit_bounds_view([-21.0, 175.0, -12.0, -178.0], [0.0; 4], None);
+        assert!((lng - 178.5).abs() < 1e-9);
+
+        // Left padding pushes the bounds right, so the center moves west
+        let (_, unpadded_lng, unpadded_zoom) = map.fit_bounds_view([0.0, -10.0, 10.0, 10.0], [0.0; 4], None);
+        let (_, padded_lng, padded_zoom) = map.fit_bounds_view([0.0, -10.0, 10.0, 10.0], [0.0, 0.0, 0.0, 200.0], None);
+        assert!(padded_zoom < unpadded_zoom);
+        assert!(padded_lng < unpadded_lng);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_world_copies_wrap_tiles_and_hit_test() {
+        let mut map = crate::RustyleafMap::new(800, 600);
+        map.zoom = 1.0;
+        map.center_lat = 0.0;
+        map.center_lng = 180.0;
+
+        // Off by default: only the canonical world, and no tiles past its edges
+        assert_eq!(map.world_copy_offsets(), vec![0.0]);
+        assert_eq!(map.wrap_tile_x(2, 1), None);
+
+        map.render_world_copies = true;
+        assert_eq!(map.world_copy_offsets(), vec![0.0, 512.0]);
+        assert_eq!(map.wrap_tile_x(2, 1), Some(0));
+        assert_eq!(map.wrap_tile_x(-1, 1), Some(1));
+
+        // A point just east of the antimeridian is hit on the copy right of center
+        map.point_layers.push(crate::PointLayer {
+            points: vec![crate::PointFeature { lat: 0.0, lng: -179.0, size: 4.0, color: [1.0; 4], meta: serde_json::json!({"id": 7}) }],
+            visible: true,
+        });
+        map.update_spatial_index();
+        let (x, y) = map.lat_lng_to_screen(0.0, -179.0);
+        assert!(x < 0.0);
+        let hit = map.hit_test(x + 512.0, y).expect("point on the eastern copy");
+        assert_eq!(hit["original_meta"]["id"], 7);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_projections_roundtrip() {
+        use crate::projection::{projection_from_code, Projection, WebMercator};
+
+        // Web Mercator stays the default and matches the classic tile math
+        let map = crate::RustyleafMap::new(800, 600);
+        assert_eq!(map.projection.code(), "EPSG:3857");
+        let (x, y) = WebMercator.project(0.0, 0.0);
+        assert!((x - 0.5).abs() < 1e-12 && (y - 0.5).abs() < 1e-12);
+
+        for code in ["EPSG:3857", "EPSG:4326", "EPSG:3413", "EPSG:3031"] {
+            let projection = projection_from_code(code).unwrap();
+            let lat = if code == "EPSG:3031" { -75.0 } else { 75.0 };
+            let (x, y) = projection.project(lat, 30.0);
+            assert!((0.0..=1.0).contains(&x) && (0.0..=1.0).contains(&y), "{} out of the world square", code);
+            let (lat2, lng2) = projection.unproject(x, y);
+            assert!((lat2 - lat).abs() < 1e-9, "{} latitude roundtrip", code);
+            assert!((lng2 - 30.0).abs() < 1e-9, "{} longitude roundtrip", code);
+        }
+        assert!(projection_from_code("EPSG:9999").is_none());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_polar_stereographic_centers_on_pole() {
+        use crate::projection::projection_from_code;
+
+        let arctic = projection_from_code("EPSG:3413").unwrap();
+        assert!(!arctic.wraps());
+
+        // The pole sits in the middle of the world square, y grows toward the central meridian
+        let (x, y) = arctic.project(90.0, 0.0);
+        assert!((x - 0.5).abs() < 1e-12 && (y - 0.5).abs() < 1e-12);
+        let (_, below) = arctic.project(80.0, -45.0);
+        assert!(below > 0.5);
+
+        // True scale at 70N: one degree of latitude there is about 111.4 km
+        let (_, y70) = arctic.to_crs(0.5, arctic.project(70.0, -45.0).1);
+        let (_, y71) = arctic.to_crs(0.5, arctic.project(71.0, -45.0).1);
+        assert!(((y71 - y70) - 111_440.0).abs() < 500.0);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_bearing_rotates_screen_projection() {
+        let mut map = crate::RustyleafMap::new(800, 600);
+        map.center_lat = 0.0;
+        map.center_lng = 0.0;
+        map.zoom = 5.0;
+        map.bearing = 90.0;
+
+        // With a bearing of 90 east is at the top of the screen
+        let (x, y) = map.lat_lng_to_screen(0.0, 1.0);
+        assert!((x - 400.0).abs() < 1e-6);
+        assert!(y < 300.0);
+
+        // Screen and lat/lng conversions stay inverse under rotation
+        let (lat, lng) = map.screen_to_lat_lng(123.0, 456.0);
+        let (x, y) = map.lat_lng_to_screen(lat, lng);
+        assert!((x - 123.0).abs() < 1e-6 && (y - 456.0).abs() < 1e-6);
+
+        // The rotated viewport covers a taller-than-wide box of the world
+        let (extent_width, extent_height) = map.viewport_extent();
+        assert!((extent_width - 600.0).abs() < 1e-6 && (extent_height - 800.0).abs() < 1e-6);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_fit_bounds_under_bearing() {
+        let mut map = crate::RustyleafMap::new(800, 400);
+        let bounds = [-10.0, -40.0, 10.0, 40.0];
+        let (_, _, north_up_zoom) = map.fit_bounds_view(bounds, [0.0; 4], None);
+
+        // A wide box fits less tightly once the map is turned on its side
+        map.bearing = 90.0;
+        let (lat, lng, rotated_zoom) = map.fit_bounds_view(bounds, [0.0; 4], None);
+        assert!(rotated_zoom < north_up_zoom);
+        map.center_lat = lat;
+        map.center_lng = lng;
+        map.zoom = rotated_zoom;
+        for (lat, lng) in [(-10.0, -40.0), (10.0, 40.0)] {
+            let (x, y) = map.lat_lng_to_screen(lat, lng);
+            assert!((-1e-6..=800.0 + 1e-6).contains(&x) && (-1e-6..=400.0 + 1e-6).contains(&y));
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn test_pitch_perspective_roundtrip() {
+        let mut map = crate::RustyleafMap::new(800, 600);
+        map.center_lat = 45.0;
+        map.center_lng = 10.0;
+        map.zoom = 8.0;
+        map.bearing = 30.0;
+        map.pitch = 60.0;
+
+        // Unprojecting casts onto the ground plane, so projecting back lands
+        // on the same screen point
+        for (x, y) in [(0.0, 0.0), (800.0, 0.0), (400.0, 300.0), (123.0, 456.0), (800.0, 600.0)] {
+            let (lat, lng) = map.screen_to_lat_lng(x, y);
+            let (back_x, back_y) = map.lat_lng_to_screen(lat, lng);
+            assert!((back_x - x).abs() < 1e-6 && (back_y - y).abs() < 1e-6);
+        }
+
+        // The top edge of the screen reaches much further than the bottom
+        let footprint = map.ground_footprint();
+        let top_width = (footprint[1].0 - footprint[0].0).hypot(footprint[1].1 - footprint[0].1);
+        let bottom_width = (footprint[2].0 - footprint[3].0).hypot(footprint[2].1 - footprint[3].1);
+        assert!(top_width > 2.0 * bottom_width);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_pitch_covering_tiles_coarsen_toward_horizon() {
+        let mut map = crate::RustyleafMap::new(800, 600);
+        map.center_lat = 0.0;
+        map.center_lng = 0.0;
+        map.zoom = 10.0;
+
+        // Looking straight down every tile comes from the current zoom
+        let flat = map.covering_tiles(256, 0, 18);
+        assert!(flat.iter().all(|tile| tile.z == 10));
+        assert_eq!(flat.len(), 16);
+
+        map.pitch = 60.0;
+        let pitched = map.covering_tiles(256, 0, 18);
+        let min_z = pitched.iter().map(|tile| tile.z).min().unwrap();
+        let max_z = pitched.iter().map(|tile| tile.z).max().unwrap();
+        assert!(min_z < 10 && max_z >= 10);
+
+        // Coarser tiles lie north of (further away than) finer ones
+        let north_edge = |z: u32| {
+            pitched
+                .iter()
+                .filter(|tile| tile.z == z)
+                .map(|tile| tile.y as f64 / (1u32 << tile.z) as f64)
+                .fold(f64::INFINITY, f64::min)
+        };
+        assert!(north_edge(min_z) < north_edge(max_z));
+
+        // The layer's zoom range still bounds the chosen levels
+        assert!(map.covering_tiles(256, 9, 10).iter().all(|tile| (9..=10).contains(&tile.z)));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_pixel_ratio_scales_buffer_and_requests_retina_tiles() {
+        let mut map = crate::RustyleafMap::new(800, 600);
+        let layer_id = map.insert_tile_layer(crate::TileLayer {
+            subdomains: vec!["a".to_string()],
+            ..crate::TileLayer::new("https://{s}.tiles.example.com/{z}/{x}/{y}{r}.png")
+        });
+        let coord = crate::TileCoord { x: 1, y: 2, z: 3 };
+        assert_eq!(map.tile_url(&map.tile_layers[0], &coord), "https://a.tiles.example.com/3/1/2.png");
+        assert_eq!(map.tile_layers[0].id, layer_id);
+
+        map.set_pixel_ratio(2.0).unwrap();
+        assert_eq!(map.drawing_buffer_size(), (1600, 1200));
+        assert_eq!(map.tile_url(&map.tile_layers[0], &coord), "https://a.tiles.example.com/3/1/2@2x.png");
+
+        // Layout stays in CSS pixels
+        map.center_lat = 0.0;
+        map.center_lng = 0.0;
+        assert_eq!(map.lat_lng_to_screen(0.0, 0.0), (400.0, 300.0));
+
+        // Lines are quads their width in CSS pixels across, so they cover
+        // twice the device pixels at a pixel ratio of 2
+        let mut vertices = Vec::new();
+        crate::push_line_segment(&mut vertices, (10.0, 20.0), (30.0, 20.0), 4.0, [1.0, 0.0, 0.0, 1.0]);
+        let corners: Vec<(f32, f32)> = vertices.chunks(6).map(|vertex| (vertex[0], vertex[1])).collect();
+        assert_eq!(corners, [(10.0, 22.0), (10.0, 18.0), (30.0, 22.0), (30.0, 22.0), (10.0, 18.0), (30.0, 18.0)]);
+        crate::push_line_segment(&mut vertices, (5.0, 5.0), (5.0, 5.0), 4.0, [1.0; 4]);
+        assert_eq!(vertices.len(), 36);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_tile_layer_options_shape_urls_and_coverage() {
+        let mut map = crate::RustyleafMap::new(800, 600);
+        map.center_lat = 0.0;
+        map.center_lng = 0.0;
+        map.zoom = 10.0;
+        let coord = crate::TileCoord { x: 3, y: 5, z: 3 };
+
+        // TMS rows count from the south; {q} is the quadkey of the tile
+        let layer = crate::TileLayer {
+            subdomains: vec!["t0".to_string(), "t1".to_string()],
+            tms: true,
+            ..crate::TileLayer::new("https://{s}.example.com/{z}/{x}/{y}?q={q}")
+        };
+        assert_eq!(map.tile_url(&layer, &coord), "https://t0.example.com/3/3/2?q=213");
+
+        // 512px tiles come from one level coarser; zoomOffset -1 numbers them by that level
+        let mut layer = crate::TileLayer { tile_size: 512, ..crate::TileLayer::new("https://example.com/{z}/{x}/{y}.png") };
+        assert_eq!(map.tile_url(&layer, &coord), "https://example.com/4/3/5.png");
+        layer.zoom_offset = -1;
+        assert_eq!(map.tile_url(&layer, &coord), "https://example.com/3/3/5.png");
+        let tiles = map.layer_covering_tiles(&layer);
+        assert!(tiles.iter().all(|(tile, _)| tile.z == 9));
+        assert_eq!(tiles.len(), 4);
+
+        // Nothing is requested outside the coverage area
+        layer.bounds = Some([-10.0, 0.0, 10.0, 10.0]);
+        let tiles = map.layer_covering_tiles(&layer);
+        assert!(!tiles.is_empty());
+        assert!(tiles.iter().all(|(tile, _)| tile.x >= 256));
+        layer.bounds = Some([40.0, 40.0, 50.0, 50.0]);
+        assert!(map.layer_covering_tiles(&layer).is_empty());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_wms_layer_requests_tile_bbox() {
+        use crate::wms::{WmsParams, WmsVersion};
+
+        let mut map = crate::RustyleafMap::new(800, 600);
+        let mut wms = WmsParams::new("topp:states,topp:roads");
+        wms.format = "image/png".to_string();
+        wms.transparent = true;
+        wms.extra.push(("CQL_FILTER".to_string(), "STATE_ABBR='TX'".to_string()));
+        let layer = crate::TileLayer { wms: Some(wms), ..crate::TileLayer::new("https://example.com/geoserver/wms?") };
+
+        // The north-east quarter of the world at zoom 1
+        let url = map.tile_url(&layer, &crate::TileCoord { x: 1, y: 0, z: 1 });
+        assert_eq!(
+            url,
+            "https://example.com/geoserver/wms?SERVICE=WMS&REQUEST=GetMap&VERSION=1.1.1\
+             &LAYERS=topp%3Astates%2Ctopp%3Aroads&STYLES=&FORMAT=image%2Fpng&TRANSPARENT=TRUE\
+             &SRS=EPSG%3A3857&BBOX=0%2C0%2C20037508.342789244%2C20037508.342789244&WIDTH=256&HEIGHT=256\
+             &CQL_FILTER=STATE_ABBR%3D'TX'"
+        );
+
+        // WMS 1.3.0 puts latitude first for EPSG:4326
+        map.projection = crate::projection::projection_from_code("EPSG:4326").unwrap();
+        map.pixel_ratio = 2.0;
+        let mut wms = WmsParams::new("dem");
+        wms.version = WmsVersion::V1_3_0;
+        let layer = crate::TileLayer { wms: Some(wms), ..crate::TileLayer::new("https://example.com/wms?map=dem") };
+        let url = map.tile_url(&layer, &crate::TileCoord { x: 0, y: 1, z: 2 });
+        assert!(url.starts_with("https://example.com/wms?map=dem&SERVICE=WMS"));
+        assert!(url.contains("&CRS=EPSG%3A4326&BBOX=0%2C-180%2C90%2C-90&WIDTH=512&HEIGHT=512"));
+
+        // The world fills the middle half of the zoom 0 tile, so its box
+        // reaches past the poles rather than being squashed onto them
+        let url = map.tile_url(&layer, &crate::TileCoord { x: 0, y: 0, z: 0 });
+        assert!(url.contains("&BBOX=-180%2C-180%2C180%2C180&"));
+
+        // Tiles wholly beyond the poles aren't requested
+        map.zoom = 2.0;
+        let rows: std::collections::HashSet<i32> = map.layer_covering_tiles(&layer).iter().map(|(tile, _)| tile.y).collect();
+        assert_eq!(rows, [1, 2].into_iter().collect());
+    }
+
+    const WMTS_CAPABILITIES: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Capabilities xmlns="http://www.opengis.net/wmts/1.0" xmlns:ows="http://www.opengis.net/ows/1.1"
+    xmlns:xlink="http://www.w3.org/1999/xlink" version="1.0.0">
+  <ows:OperationsMetadata>
+    <ows:Operation name="GetTile">
+      <ows:DCP><ows:HTTP>
+        <ows:Get xlink:href="https://maps.example.org/wmts?">
+          <ows:Constraint name="GetEncoding"><ows:AllowedValues><ows:Value>KVP</ows:Value></ows:AllowedValues></ows:Constraint>
+        </ows:Get>
+      </ows:HTTP></ows:DCP>
+    </ows:Operation>
+  </ows:OperationsMetadata>
+  <Contents>
+    <Layer>
+      <ows:Identifier>ortho</ows:Identifier>
+      <ows:WGS84BoundingBox><ows:LowerCorner>5.5 47.0</ows:LowerCorner><ows:UpperCorner>15.5 55.0</ows:UpperCorner></ows:WGS84BoundingBox>
+      <Style><ows:Identifier>plain</ows:Identifier></Style>
+      <Style isDefault="true"><ows:Identifier>default</ows:Identifier></Style>
+      <Format>image/jpeg</Format>
+      <Dimension><ows:Identifier>Time</ows:Identifier><Default>2024</Default><Value>2023</Value><Value>2024</Value></Dimension>
+      <TileMatrixSetLink><TileMatrixSet>WGS84</TileMatrixSet></TileMatrixSetLink>
+      <TileMatrixSetLink><TileMatrixSet>WebMercatorQuad</TileMatrixSet></TileMatrixSetLink>
+      <ResourceURL format="image/jpeg" resourceType="tile"
+        template="https://maps.example.org/ortho/{Style}/{Time}/{TileMatrixSet}/{TileMatrix}/{TileRow}/{TileCol}.jpg"/>
+    </Layer>
+    <Layer>
+      <ows:Identifier>roads</ows:Identifier>
+      <Format>image/png</Format>
+      <TileMatrixSetLink><TileMatrixSet>WebMercatorQuad</TileMatrixSet></TileMatrixSetLink>
+    </Layer>
+    <TileMatrixSet>
+      <ows:Identifier>WGS84</ows:Identifier>
+      <ows:SupportedCRS>urn:ogc:def:crs:EPSG::4326</ows:SupportedCRS>
+      <TileMatrix><ows:Identifier>0</ows:Identifier><ScaleDenominator>279541132.0143589</ScaleDenominator>
+        <TopLeftCorner>90 -180</TopLeftCorner><TileWidth>256</TileWidth><TileHeight>256</TileHeight>
+        <MatrixWidth>2</MatrixWidth><MatrixHeight>1</MatrixHeight></TileMatrix>
+    </TileMatrixSet>
+    <TileMatrixSet>
+      <ows:Identifier>WebMercatorQuad</ows:Identifier>
+      <ows:SupportedCRS>urn:ogc:def:crs:EPSG:6.18.3:3857</ows:SupportedCRS>
+      <TileMatrix><ows:Identifier>WMQ:5</ows:Identifier><ScaleDenominator>17471320.75089743</ScaleDenominator>
+        <TopLeftCorner>-20037508.3428 20037508.3428</TopLeftCorner><TileWidth>256</TileWidth><TileHeight>256</TileHeight>
+        <MatrixWidth>32</MatrixWidth><MatrixHeight>32</MatrixHeight></TileMatrix>
+      <TileMatrix><ows:Identifier>WMQ:6</ows:Identifier><ScaleDenominator>8735660.375448715</ScaleDenominator>
+        <TopLeftCorner>-20037508.3428 20037508.3428</TopLeftCorner><TileWidth>256</TileWidth><TileHeight>256</TileHeight>
+        <MatrixWidth>64</MatrixWidth><MatrixHeight>64</MatrixHeight></TileMatrix>
+      <TileMatrix><ows:Identifier>WMQ:9</ows:Identifier><ScaleDenominator>1091957.546931089</ScaleDenominator>
+        <TopLeftCorner>-20037508.3428 20037508.3428</TopLeftCorner><TileWidth>256</TileWidth><TileHeight>256</TileHeight>
+        <MatrixWidth>512</MatrixWidth><MatrixHeight>512</MatrixHeight></TileMatrix>
+    </TileMatrixSet>
+  </Contents>
+</Capabilities>"#;
+
+    #[wasm_bindgen_test]
+    fn test_wmts_capabilities_pick_web_mercator_matrices() {
+        use crate::wmts::{WmtsOptions, WmtsSource};
+
+        // RESTful template, default style and dimension; the EPSG:4326 set is skipped
+        let wmts = WmtsSource::from_capabilities(WMTS_CAPABILITIES, &WmtsOptions::default()).unwrap();
+        assert_eq!((wmts.min_level, wmts.max_level(), wmts.tile_size), (5, 6, 256));
+        assert_eq!(
+            wmts.tile_url(&crate::TileCoord { x: 17, y: 10, z: 5 }),
+            "https://maps.example.org/ortho/default/2024/WebMercatorQuad/WMQ:5/10/17.jpg"
+        );
+
+        let mut layer = crate::TileLayer::new("");
+        layer.set_wmts(wmts);
+        assert_eq!((layer.min_zoom, layer.max_zoom), (5, 6));
+        assert_eq!(layer.bounds, Some([47.0, 5.5, 55.0, 15.5]));
+
+        // Without a template the GetTile KVP endpoint is used
+        let options = WmtsOptions { layer: Some("roads".to_string()), ..Default::default() };
+        let wmts = WmtsSource::from_capabilities(WMTS_CAPABILITIES, &options).unwrap();
+        assert_eq!(
+            wmts.tile_url(&crate::TileCoord { x: 33, y: 21, z: 6 }),
+            "https://maps.example.org/wmts?SERVICE=WMTS&REQUEST=GetTile&VERSION=1.0.0&LAYER=roads&STYLE=default\
+             &FORMAT=image%2Fpng&TILEMATRIXSET=WebMercatorQuad&TILEMATRIX=WMQ%3A6&TILEROW=21&TILECOL=33"
+        );
+
+        let options = WmtsOptions { tile_matrix_set: Some("WGS84".to_string()), ..Default::default() };
+        assert!(WmtsSource::from_capabilities(WMTS_CAPABILITIES, &options).is_err());
+        let options = WmtsOptions { layer: Some("missing".to_string()), ..Default::default() };
+        assert!(WmtsSource::from_capabilities(WMTS_CAPABILITIES, &options).is_err());
+    }
+
+    // Protobuf encoding for hand-built vector tiles
+    fn mvt_varint(mut value: u64, out: &mut Vec<u8>) {
+        while value >= 0x80 {
+            out.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        out.push(value as u8);
+    }
+
+    fn mvt_field(field: u64, data: &[u8], out: &mut Vec<u8>) {
+        mvt_varint(field << 3 | 2, out);
+        mvt_varint(data.len() as u64, out);
+        out.extend_from_slice(data);
+    }
+
+    fn mvt_packed(values: &[u32]) -> Vec<u8> {
+        let mut out = Vec::new();
+        for &value in values {
+            mvt_varint(value as u64, &mut out);
+        }
+        out
+    }
+
+    // Feature of `geometry_type` whose paths are given in absolute tile units
+    fn mvt_feature(id: u64, geometry_type: u64, tags: &[u32], paths: &[&[(i32, i32)]], close: bool) -> Vec<u8> {
+        let zigzag = |n: i32| ((n << 1) ^ (n >> 31)) as u32;
+        let mut commands = Vec::new();
+        let mut cursor = (0, 0);
+        for path in paths {
+            for (i, &(x, y)) in path.iter().enumerate() {
+                match i {
+                    0 => commands.push(1 | 1 << 3),
+                    1 => commands.push(2 | (path.len() as u32 - 1) << 3),
+                    _ => {}
+                }
+                commands.extend([zigzag(x - cursor.0), zigzag(y - cursor.1)]);
+                cursor = (x, y);
+            }
+            if close {
+                commands.push(7 | 1 << 3);
+            }
+        }
+        let mut feature = Vec::new();
+        mvt_varint(1 << 3, &mut feature);
+        mvt_varint(id, &mut feature);
+        mvt_field(2, &mvt_packed(tags), &mut feature);
+        mvt_varint(3 << 3, &mut feature);
+        mvt_varint(geometry_type, &mut feature);
+        mvt_field(4, &mvt_packed(&commands), &mut feature);
+        feature
+    }
+
+    #[wasm_bindgen_test]
+    fn test_vector_tiles_decode_clip_style_and_hit_test() {
+        use crate::mvt::{self, Geometry};
+        use crate::projection::{Projection, WebMercator};
+        use std::rc::Rc;
+
+        // A "water" layer: a lake hanging off the top-left corner, a road
+        // crossing the tile, a point inside and one in the buffer past the edge
+        let mut layer = Vec::new();
+        mvt_field(1, b"water", &mut layer);
+        let lake: &[(i32, i32)] = &[(-1000, -1000), (2048, -1000), (2048, 2048), (-1000, 2048)];
+        mvt_field(2, &mvt_feature(1, 3, &[0, 0], &[lake], true), &mut layer);
+        mvt_field(2, &mvt_feature(2, 2, &[1, 1], &[&[(-2048, 1024), (6144, 1024)]], false), &mut layer);
+        mvt_field(2, &mvt_feature(3, 1, &[1, 1], &[&[(1024, 3072)]], false), &mut layer);
+        mvt_field(2, &mvt_feature(4, 1, &[1, 1], &[&[(5000, 100)]], false), &mut layer);
+        mvt_field(3, b"class", &mut layer);
+        mvt_field(3, b"rank", &mut layer);
+        let mut lake_value = Vec::new();
+        mvt_field(1, b"lake", &mut lake_value);
+        mvt_field(4, &lake_value, &mut layer);
+        mvt_field(4, &[5 << 3, 3], &mut layer);
+        let mut tile = Vec::new();
+        mvt_field(3, &layer, &mut tile);
+
+        let layers = mvt::decode(&tile, 1.0).unwrap();
+        assert_eq!(layers.len(), 1);
+        assert_eq!(layers[0].name, "water");
+        let features = &layers[0].features;
+        assert_eq!(features.iter().map(|f| f.id.unwrap()).collect::<Vec<_>>(), [1, 2, 3]);
+        assert_eq!(features[0].properties["class"], "lake");
+        assert_eq!(features[1].properties["rank"], 3);
+
+        // The lake is cut to the tile; its outline leaves out the tile's edges
+        match &features[0].geometry {
+            Geometry::Polygons { triangles, outlines } => {
+                let area: f64 = triangles
+                    .chunks(3)
+                    .map(|t| ((t[1][0] - t[0][0]) * (t[2][1] - t[0][1]) - (t[2][0] - t[0][0]) * (t[1][1] - t[0][1])).abs() / 2.0)
+                    .sum();
+                assert!((area - 0.25).abs() < 1e-6);
+                assert_eq!(outlines, &vec![vec![[0.5, 0.0], [0.5, 0.5], [0.0, 0.5]]]);
+            }
+            other => panic!("expected a polygon, got {:?}", other),
+        }
+        assert_eq!(features[1].geometry, Geometry::Lines(vec![vec![[0.0, 0.25], [1.0, 0.25]]]));
+        assert_eq!(features[2].geometry, Geometry::Points(vec![[0.25, 0.75]]));
+
+        // Lakes are filled and rank-3 features outlined; the point has no color
+        let style = crate::parse_vector_tile_style(&serde_json::json!([
+            { "sourceLayer": "water", "filter": { "class": ["lake", "pond"] }, "polygonColor": "#0000ff" },
+            { "filter": { "rank": 3.0 }, "lineColor": "#ff0000" }
+        ]))
+        .unwrap();
+        assert_eq!(style[0].polygon_color, Some([0.0, 0.0, 1.0, 1.0]));
+        assert_eq!(style[0].line_color, None);
+        assert!(crate::parse_vector_tile_style(&serde_json::json!({ "lineColor": "red" })).is_err());
+
+        let mut map = crate::RustyleafMap::new(800, 600);
+        map.zoom = 1.0;
+        let mut vector_layer = crate::TileLayer::new("https://tiles.example.com/{z}/{x}/{y}.pbf");
+        vector_layer.vector = Some(style);
+        let layer_id = map.insert_tile_layer(vector_layer);
+        let coord = crate::TileCoord { x: 0, y: 0, z: 0 };
+        let mut features = crate::vector_tile_features(layers, &coord, &WebMercator);
+        // A rank-3 road running diagonally across the lake
+        let lat_lng = |x: f64, y: f64| {
+            let (lat, lng) = WebMercator.unproject(x, y);
+            [lat, lng]
+        };
+        features.push(crate::VectorTileFeature {
+            source_layer: "roads".to_string(),
+            id: Some(5),
+            properties: serde_json::json!({ "rank": 3 }),
+            geometry: Geometry::Lines(vec![vec![lat_lng(0.05, 0.05), lat_lng(0.45, 0.45)]]),
+        });
+        assert!(map.tile_layers[0].vector_style(&features[2]).is_some_and(|rule| rule.point_color.is_none()));
+        crate::VECTOR_TILES.with(|store| store.borrow_mut().insert(coord.key(layer_id), Rc::new(features), 0));
+        map.update_spatial_index();
+
+        let screen = |x: f64, y: f64| {
+            let (lat, lng) = WebMercator.unproject(x, y);
+            map.lat_lng_to_screen(lat, lng)
+        };
+        let (x, y) = screen(0.25, 0.4);
+        let hit = map.hit_test(x, y).expect("inside the lake");
+        assert_eq!(hit["layer_type"], "vector_tile");
+        assert_eq!(hit["source_layer"], "water");
+        assert_eq!(hit["original_meta"]["class"], "lake");
+        let (x, y) = screen(0.75, 0.25);
+        assert_eq!(map.hit_test(x, y).expect("on the road")["feature_id"], 2);
+        let (x, y) = screen(0.25, 0.75);
+        assert!(map.hit_test(x, y).is_none());
+
+        // Inside the diagonal road's bounding box but away from the road is
+        // the lake beneath; on the road is the road
+        let (x, y) = screen(0.4, 0.1);
+        assert_eq!(map.hit_test(x, y).expect("beside the road")["feature_id"], 1);
+        let (x, y) = screen(0.3, 0.3);
+        assert_eq!(map.hit_test(x, y).expect("on the diagonal road")["feature_id"], 5);
+
+        // Tiles are styled once, filling the lake and drawing both rank-3
+        // roads, and the styled geometry is dropped with its tile
+        let key = coord.key(layer_id);
+        let features = crate::VECTOR_TILES.with(|store| store.borrow().peek(&key).cloned()).unwrap();
+        let geometry = crate::vector_tile_geometry(&map.tile_layers[0], &features);
+        assert_eq!(geometry.fills.len(), 1);
+        assert_eq!(geometry.lines.len(), 2);
+        assert!(geometry.points.is_empty());
+        crate::VECTOR_TILE_GEOMETRY.with(|cache| cache.borrow_mut().insert(key.clone(), Rc::new(geometry)));
+        map.set_tile_cache_limits(0, 0.0).unwrap();
+        assert!(!crate::VECTOR_TILE_GEOMETRY.with(|cache| cache.borrow().contains_key(&key)));
+        map.set_tile_cache_limits(crate::DEFAULT_TILE_CACHE_TILES as u32, crate::DEFAULT_TILE_CACHE_BYTES as f64).unwrap();
+    }
+
+    // PMTiles directory of (tile_id, run_length, offset, length) entries,
+    // uncompressed
+    fn pmtiles_directory(entries: &[(u64, u64, u64, u64)]) -> Vec<u8> {
+        let mut out = Vec::new();
+        mvt_varint(entries.len() as u64, &mut out);
+        let mut last_id = 0;
+        for &(tile_id, _, _, _) in entries {
+            mvt_varint(tile_id - last_id, &mut out);
+            last_id = tile_id;
+        }
+        for &(_, run_length, _, _) in entries {
+            mvt_varint(run_length, &mut out);
+        }
+        for &(_, _, _, length) in entries {
+            mvt_varint(length, &mut out);
+        }
+        for (i, &(_, _, offset, _)) in entries.iter().enumerate() {
+            // Entries following straight on from the previous one store 0
+            let contiguous = i > 0 && offset == entries[i - 1].2 + entries[i - 1].3;
+            mvt_varint(if contiguous { 0 } else { offset + 1 }, &mut out);
+        }
+        out
+    }
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        use std::io::Write;
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[wasm_bindgen_test]
+    fn test_pmtiles_archive_resolves_tiles_through_leaf_directories() {
+        use crate::pmtiles::{self, TileType};
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        // Levels come one after another, each in Hilbert curve order
+        assert_eq!(pmtiles::tile_id(0, 0, 0), 0);
+        let level_1: Vec<u64> = [(0, 0), (0, 1), (1, 1), (1, 0)].iter().map(|&(x, y)| pmtiles::tile_id(1, x, y)).collect();
+        assert_eq!(level_1, [1, 2, 3, 4]);
+        assert_eq!(pmtiles::tile_id(2, 0, 0), 5);
+        assert_eq!(pmtiles::tile_id(12, 3423, 1763), 19078479);
+
+        // Gzipped PNG tiles: the world, one tile shared by the two eastern
+        // tiles of level 1, and a level 2 tile listed in a leaf directory
+        let (world, east, deep) = (gzip(b"world"), gzip(b"east"), gzip(b"deep"));
+        let (world_len, east_len, deep_len) = (world.len() as u64, east.len() as u64, deep.len() as u64);
+        let leaf = pmtiles_directory(&[(pmtiles::tile_id(2, 3, 3), 1, world_len + east_len, deep_len)]);
+        let root = pmtiles_directory(&[(0, 1, 0, world_len), (3, 2, world_len, east_len), (5, 0, 0, leaf.len() as u64)]);
+        let parsed = pmtiles::parse_directory(&root).unwrap();
+        assert_eq!(parsed[1], pmtiles::Entry { tile_id: 3, offset: world_len, length: east_len, run_length: 2 });
+        assert_eq!(parsed[2].offset, 0);
+
+        // An entry following on from one at the very end of the address
+        // space has no offset
+        let mut overflowing = Vec::new();
+        for value in [2, 0, 1, 1, 1, 10, 10, u64::MAX, 0] {
+            mvt_varint(value, &mut overflowing);
+        }
+        assert!(pmtiles::parse_directory(&overflowing).is_err());
+
+        let mut header = vec![0u8; pmtiles::HEADER_LENGTH];
+        header[0..7].copy_from_slice(b"PMTiles");
+        header[7] = 3;
+        let leaf_offset = (pmtiles::HEADER_LENGTH + root.len()) as u64;
+        let data_offset = leaf_offset + leaf.len() as u64;
+        for (at, value) in [(8, pmtiles::HEADER_LENGTH as u64), (16, root.len() as u64), (40, leaf_offset), (56, data_offset)] {
+            header[at..at + 8].copy_from_slice(&value.to_le_bytes());
+        }
+        header[97..102].copy_from_slice(&[1, 2, 2, 0, 2]);
+        for (at, degrees) in [(102, -10i32), (106, 40), (110, 20), (114, 60)] {
+            header[at..at + 4].copy_from_slice(&(degrees * 10_000_000).to_le_bytes());
+        }
+        let archive_bytes: Vec<u8> = [header, root, leaf, world, east, deep].concat();
+
+        // The in-memory archive answers at once
+        let archive = pmtiles::Archive::new(Box::new(archive_bytes));
+        assert_eq!(archive.header(), None);
+        let tiles = Rc::new(RefCell::new(Vec::new()));
+        for (z, x, y) in [(0, 0, 0), (1, 1, 0), (1, 1, 1), (2, 3, 3), (2, 0, 0), (1, 0, 0)] {
+            let tiles = tiles.clone();
+            archive.get_tile(z, x, y, Box::new(move |tile| tiles.borrow_mut().push(tile.unwrap())));
+        }
+        let expected: Vec<Option<Vec<u8>>> = vec![
+            Some(b"world".to_vec()),
+            Some(b"east".to_vec()),
+            Some(b"east".to_vec()),
+            Some(b"deep".to_vec()),
+            None,
+            None,
+        ];
+        assert_eq!(*tiles.borrow(), expected);
+
+        let header = archive.header().unwrap();
+        assert_eq!(header.tile_type, TileType::Png);
+        assert_eq!((header.min_zoom, header.max_zoom), (0, 2));
+        assert_eq!(header.bounds, [40.0, -10.0, 60.0, 20.0]);
+
+        // A raster archive narrows the layer's zoom range and coverage to its own
+        let mut layer = crate::TileLayer::new("https://tiles.example.com/world.pmtiles");
+        layer.set_source(Rc::new(archive));
+        assert!(!layer.is_vector());
+        assert_eq!(layer.zoom_range(), (0, 2));
+        assert_eq!(layer.coverage_bounds(), Some([40.0, -10.0, 60.0, 20.0]));
+
+        // Errors reach the caller
+        let failed = Rc::new(RefCell::new(None));
+        let failed_clone = failed.clone();
+        pmtiles::Archive::new(Box::new(b"not an archive".to_vec())).get_tile(0, 0, 0, Box::new(move |tile| *failed_clone.borrow_mut() = tile.err()));
+        assert_eq!(failed.borrow().as_deref(), Some("Not a PMTiles archive"));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_mbtiles_source_serves_rows_by_xyz_coordinates() {
+        use crate::tile_source::{MemoryTileSource, TileData, TileFormat, TileSource, UrlTemplateSource};
+        use std::cell::RefCell;
+        use std::collections::HashMap;
+        use std::rc::Rc;
+
+        // MBTiles rows count from the south; vector tiles may be gzipped
+        let metadata: HashMap<String, String> = [("format", "pbf"), ("minzoom", "0"), ("maxzoom", "1"), ("bounds", "-10,40,20,60")]
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        let rows = vec![(0, 0, 0, b"world".to_vec()), (1, 1, 0, gzip(b"south-east"))];
+        let source = MemoryTileSource::from_mbtiles(rows, &metadata).unwrap();
+        assert_eq!(source.format(), Some(TileFormat::Mvt));
+        assert_eq!(source.zoom_range(), Some((0, 1)));
+        assert_eq!(source.bounds(), Some([40.0, -10.0, 60.0, 20.0]));
+
+        let tiles = Rc::new(RefCell::new(Vec::new()));
+        for (z, x, y) in [(0, 0, 0), (1, 1, 1), (1, 1, 0), (1, -1, 0)] {
+            let tiles = tiles.clone();
+            source.load(&crate::TileCoord { x, y, z }, Box::new(move |tile| tiles.borrow_mut().push(tile.unwrap())));
+        }
+        let expected = vec![Some(TileData::Bytes(b"world".to_vec())), Some(TileData::Bytes(b"south-east".to_vec())), None, None];
+        assert_eq!(*tiles.borrow(), expected);
+
+        assert!(MemoryTileSource::from_mbtiles(vec![(1, 0, 2, Vec::new())], &HashMap::new()).is_err());
+        let tiff = [("format".to_string(), "tiff".to_string())].into_iter().collect();
+        assert!(MemoryTileSource::from_mbtiles(Vec::new(), &tiff).is_err());
+
+        // A layer takes its kind, zoom range and coverage from its source
+        let mut layer = crate::TileLayer::new("");
+        layer.set_source(Rc::new(source));
+        assert!(layer.is_vector());
+        assert_eq!(layer.zoom_range(), (0, 1));
+        layer.bounds = Some([0.0, 0.0, 1.0, 1.0]);
+        assert_eq!(layer.coverage_bounds(), Some([0.0, 0.0, 1.0, 1.0]));
+
+        // Template URLs come from the template source
+        let template = UrlTemplateSource {
+            template: "https://{s}.example.com/{z}/{x}/{y}{r}.png".to_string(),
+            subdomains: vec!["a".to_string(), "b".to_string()],
+            tms: false,
+            zoom_offset: 0,
+            retina: true,
+            format: TileFormat::Image,
+        };
+        let url = Rc::new(RefCell::new(None));
+        let url_clone = url.clone();
+        template.load(&crate::TileCoord { x: 1, y: 2, z: 3 }, Box::new(move |tile| *url_clone.borrow_mut() = tile.unwrap()));
+        assert_eq!(*url.borrow(), Some(TileData::Url("https://b.example.com/3/1/2@2x.png".to_string())));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_seeding_fills_tile_store_for_region() {
+        use crate::tile_store::{BytesCallback, MemoryTileStore, SeedJob, SeedProgress, TileStore};
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        // Tiles covering a region, per level
+        let map = crate::RustyleafMap::new(800, 600);
+        let layer = crate::TileLayer::new("https://tiles.example.com/{z}/{x}/{y}.png");
+        let bounds = [-10.0, -10.0, 10.0, 10.0];
+        let ranges = map.region_tile_ranges(&layer, bounds, 0, 2);
+        assert_eq!(ranges, vec![(0, [0, 0, 0, 0]), (1, [0, 0, 1, 1]), (2, [1, 1, 2, 2])]);
+        assert_eq!(crate::RustyleafMap::region_tile_count(&ranges), 9);
+        let tiles = map.region_tiles(&layer, &ranges);
+        assert_eq!(tiles[0].0, "https://tiles.example.com/0/0/0.png");
+        assert_eq!(tiles[8].1, crate::TileCoord { x: 2, y: 2, z: 2 });
+
+        // 512px tiles come a level coarser; the layer's bounds narrow the region
+        let mut large = crate::TileLayer::new("https://tiles.example.com/{z}/{x}/{y}.png");
+        large.tile_size = 512;
+        assert_eq!(map.region_tile_ranges(&large, bounds, 0, 2), vec![(0, [0, 0, 0, 0]), (1, [0, 0, 1, 1])]);
+        large.bounds = Some([20.0, 20.0, 30.0, 30.0]);
+        assert!(map.region_tile_ranges(&large, bounds, 0, 2).is_empty());
+
+        // Stored tiles and tiles the server lacks are skipped
+        let store = Rc::new(MemoryTileStore::default());
+        store.put("0/0/0", vec![0; 4], Box::new(|stored| stored.unwrap()));
+        let keys = ["0/0/0", "1/0/0", "1/1/0", "1/0/1", "1/1/1"];
+        let region: Vec<_> = keys.iter().map(|key| (key.to_string(), crate::TileCoord { x: 0, y: 0, z: 0 })).collect();
+        let reports = Rc::new(RefCell::new(Vec::new()));
+        let on_progress = {
+            let reports = reports.clone();
+            Rc::new(move |progress: &SeedProgress| reports.borrow_mut().push(progress.clone()))
+        };
+        let fetch = Box::new(|key: &str, _: &crate::TileCoord, done: BytesCallback| match key {
+            "1/1/0" => done(Ok(None)),
+            "1/0/1" => done(Err("HTTP 500".to_string())),
+            _ => done(Ok(Some(vec![1; 10]))),
+        });
+        let job = SeedJob::start(region.clone(), store.clone(), fetch, on_progress, 2);
+        let expected = SeedProgress { total: 5, stored: 2, skipped: 2, failed: 1, bytes: 20, finished: true, cancelled: false };
+        assert_eq!(job.progress(), expected);
+        assert_eq!(reports.borrow().len(), 6);
+        assert_eq!(reports.borrow().last(), Some(&expected));
+        let stored = Rc::new(RefCell::new(Vec::new()));
+        for key in keys {
+            let stored = stored.clone();
+            store.get(key, Box::new(move |tile| stored.borrow_mut().push(tile.unwrap().is_some())));
+        }
+        assert_eq!(*stored.borrow(), vec![true, true, false, false, true]);
+
+        // Cancelling stops a seed between downloads
+        let pending: Rc<RefCell<Vec<BytesCallback>>> = Rc::new(RefCell::new(Vec::new()));
+        let fetch = {
+            let pending = pending.clone();
+            Box::new(move |_: &str, _: &crate::TileCoord, done: BytesCallback| pending.borrow_mut().push(done))
+        };
+        let store = Rc::new(MemoryTileStore::default());
+        let job = SeedJob::start(region, store.clone(), fetch, Rc::new(|_: &SeedProgress| {}), 2);
+        assert_eq!(pending.borrow().len(), 2);
+        let done = pending.borrow_mut().remove(0);
+        done(Ok(Some(vec![1; 10])));
+        assert_eq!(pending.borrow().len(), 2);
+        job.cancel();
+        for done in pending.borrow_mut().drain(..).collect::<Vec<_>>() {
+            done(Ok(Some(vec![1; 10])));
+        }
+        let progress = job.progress();
+        assert!(progress.cancelled && progress.finished);
+        assert_eq!((progress.stored, progress.bytes), (1, 10));
+        assert!(pending.borrow().is_empty());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_raster_adjustments_shader_uniforms() {
+        let apply = |matrix: [f32; 9], rgb: [f32; 3]| {
+            [0, 1, 2].map(|row| (0..3).map(|column| matrix[column * 3 + row] * rgb[column]).sum::<f32>())
+        };
+        let close = |a: [f32; 3], b: [f32; 3]| a.iter().zip(b).all(|(a, b)| (a - b).abs() < 1e-5);
+
+        // The defaults leave colors unchanged
+        let mut adjustments = crate::RasterAdjustments::default();
+        assert_eq!(adjustments.saturation_factor(), 0.0);
+        assert_eq!(adjustments.contrast_factor(), 1.0);
+        assert!(close(apply(adjustments.hue_matrix(), [0.2, 0.4, 0.6]), [0.2, 0.4, 0.6]));
+
+        // A third of a turn takes red to green; greys never change
+        adjustments.hue_rotate = 120.0;
+        assert!(close(apply(adjustments.hue_matrix(), [1.0, 0.0, 0.0]), [0.0, 1.0, 0.0]));
+        assert!(close(apply(adjustments.hue_matrix(), [0.5, 0.5, 0.5]), [0.5, 0.5, 0.5]));
+
+        // Inverted colors are turned back round to their own hue; the shader
+        // clamps the result
+        adjustments.hue_rotate = 0.0;
+        adjustments.invert = true;
+        let inverted_red = [0.0, 1.0, 1.0];
+        assert!(close(apply(adjustments.hue_matrix(), inverted_red), [4.0 / 3.0, 1.0 / 3.0, 1.0 / 3.0]));
+
+        // Grayscale moves colors all the way to their grey level
+        adjustments.saturation = -1.0;
+        assert_eq!(adjustments.saturation_factor(), 1.0);
+        adjustments.saturation = 0.5;
+        assert!(adjustments.saturation_factor() < 0.0);
+        adjustments.contrast = -1.0;
+        assert_eq!(adjustments.contrast_factor(), 0.0);
+        adjustments.contrast = 0.5;
+        assert!(adjustments.contrast_factor() > 1.0);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_terrain_tiles_decode_shade_and_answer_elevation() {
+        use crate::terrain::{meters_per_pixel, ElevationTile, HillshadeStyle, TerrainEncoding, TerrainStyle};
+
+        // Both encodings put 0 m and 1000 m where expected
+        assert_eq!(TerrainEncoding::TerrainRgb.decode(1, 134, 160), 0.0);
+        assert_eq!(TerrainEncoding::Terrarium.decode(128, 0, 0), 0.0);
+        assert_eq!(TerrainEncoding::Terrarium.decode(131, 232, 0), 1000.0);
+        assert_eq!(TerrainEncoding::parse("terrarium"), Some(TerrainEncoding::Terrarium));
+        assert_eq!(TerrainEncoding::parse("png"), None);
+
+        // A 2x2 Terrarium tile rising to the east: 0 m and 1000 m columns
+        let rgba = [128, 0, 0, 255, 131, 232, 0, 255, 128, 0, 0, 255, 131, 232, 0, 255];
+        let tile = ElevationTile::decode(&rgba, 2, 2, 0, 0, TerrainEncoding::Terrarium).unwrap();
+        assert_eq!(tile.sample(0.25, 0.5), 0.0);
+        assert_eq!(tile.sample(0.5, 0.5), 500.0);
+        assert_eq!(tile.sample(1.0, 0.0), 1000.0);
+        assert!(ElevationTile::decode(&rgba[..8], 2, 2, 0, 0, TerrainEncoding::Terrarium).is_err());
+        assert!((meters_per_pixel(0, 0, 256) - 156_543.03).abs() < 0.1);
+
+        // Slopes facing the sun are lit, those facing away are shaded
+        let style = HillshadeStyle { azimuth: 90.0, ..HillshadeStyle::default() };
+        assert!((style.illumination(0.0, 0.0) - 1.0).abs() < 1e-6);
+        assert!(style.illumination(-0.5, 0.0) > 1.0);
+        assert!(style.illumination(0.5, 0.0) < 1.0);
+        let ramp = HillshadeStyle {
+            altitude: 90.0,
+            color_ramp: vec![(0.0, [0.0, 0.0, 1.0, 1.0]), (1000.0, [1.0, 0.0, 0.0, 1.0])],
+            ..HillshadeStyle::default()
+        };
+        assert_eq!(ramp.ramp_color(250.0), [0.25, 0.0, 0.75, 1.0]);
+        assert_eq!(ramp.ramp_color(-50.0), [0.0, 0.0, 1.0, 1.0]);
+        let flat = ElevationTile::decode(&[128, 0, 0, 255], 1, 1, 0, 0, TerrainEncoding::Terrarium).unwrap();
+        assert_eq!(ramp.shade(&flat), vec![0, 0, 255, 255]);
+        assert_eq!(HillshadeStyle::default().shade(&flat), vec![255, 255, 255, 255]);
+        // An even slope away from the sun is shaded alike at the tile's edges
+        // and inside it
+        let rising: Vec<u8> = (0..3).flat_map(|_| [[128, 20, 0, 255], [128, 10, 0, 255], [128, 0, 0, 255]]).flatten().collect();
+        let tile = ElevationTile::decode(&rising, 3, 3, 20, 1 << 19, TerrainEncoding::Terrarium).unwrap();
+        let shaded = HillshadeStyle::default().shade(&tile);
+        assert!(shaded[..12] != [255; 12]);
+        assert!(shaded.chunks(4).all(|pixel| pixel == &shaded[16..20]));
+
+        // Elevation comes from the finest cached tile of a terrain layer
+        let mut map = crate::RustyleafMap::new(800, 600);
+        let mut layer = crate::TileLayer::new("https://terrain.example.com/{z}/{x}/{y}.png");
+        layer.id = 7;
+        layer.max_zoom = 2;
+        layer.terrain = Some(TerrainStyle { encoding: TerrainEncoding::Terrarium, hillshade: HillshadeStyle::default() });
+        map.tile_layers.push(layer);
+        assert_eq!(map.elevation_at(10.0, 10.0), None);
+        let level = |height: u8| ElevationTile::decode(&[128 + height, 0, 0, 255], 1, 1, 0, 0, TerrainEncoding::Terrarium).unwrap();
+        crate::ELEVATION_TILES.with(|tiles| {
+            let mut tiles = tiles.borrow_mut();
+            tiles.insert(crate::TileCoord { x: 0, y: 0, z: 0 }.key(7), level(1));
+            tiles.insert(crate::TileCoord { x: 2, y: 1, z: 2 }.key(7), level(2));
+        });
+        assert_eq!(map.elevation_at(10.0, 10.0), Some(512.0));
+        assert_eq!(map.elevation_at(-50.0, -100.0), Some(256.0));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_image_overlays_place_corners_and_stack_with_tile_layers() {
+        use crate::{ImageOverlay, OverlayImage, StackedLayer};
+        use wasm_bindgen::JsValue;
+
+        // A box gives the corners clockwise from the top-left
+        let corners = ImageOverlay::corners_from_bounds([40.0, -10.0, 50.0, 20.0]);
+        assert_eq!(corners, [(50.0, -10.0), (50.0, 20.0), (40.0, 20.0), (40.0, -10.0)]);
+
+        // Parallelograms need no correction; a trapezoid's corners are
+        // weighted by how far they lie from where the diagonals cross, so the
+        // image doesn't bend along the diagonal
+        let close = |a: [f64; 4], b: [f64; 4]| a.iter().zip(b).all(|(a, b)| (a - b).abs() < 1e-9);
+        assert!(close(ImageOverlay::texture_weights([(0.0, 0.0), (2.0, 0.0), (2.0, 2.0), (0.0, 2.0)]), [2.0; 4]));
+        assert!(close(ImageOverlay::texture_weights([(1.0, 0.0), (3.0, 0.0), (2.0, 2.0), (0.0, 2.0)]), [2.0; 4]));
+        let trapezoid = ImageOverlay::texture_weights([(1.0, 0.0), (3.0, 0.0), (4.0, 3.0), (0.0, 3.0)]);
+        assert!(close(trapezoid, [1.5, 1.5, 3.0, 3.0]));
+        // Crossed corners fall back to plain texture coordinates
+        assert_eq!(ImageOverlay::texture_weights([(0.0, 0.0), (2.0, 2.0), (2.0, 0.0), (0.0, 2.0)]), [1.0; 4]);
+
+        // Overlays are placed by a lat/lng box or by four [lat, lng] corners
+        let numbers = |values: &[f64]| JsValue::from(values.iter().map(|&value| JsValue::from_f64(value)).collect::<js_sys::Array>());
+        let pairs = |corners: &[(f64, f64)]| JsValue::from(corners.iter().map(|&(lat, lng)| numbers(&[lat, lng])).collect::<js_sys::Array>());
+        let plan = JsValue::from_str("plan.png");
+        let skewed_corners = [(51.0, -9.0), (50.0, 21.0), (39.0, 19.0), (40.0, -11.0)];
+        let mut map = crate::RustyleafMap::new(800, 600);
+        let base = map.add_tile_layer("https://base.example.com/{z}/{x}/{y}.png").unwrap();
+        let boxed = map.add_image_overlay(&plan, &numbers(&[40.0, -10.0, 50.0, 20.0])).unwrap();
+        let labels = map.add_tile_layer("https://labels.example.com/{z}/{x}/{y}.png").unwrap();
+        let skewed = map.add_image_overlay(&plan, &pairs(&skewed_corners)).unwrap();
+        assert!(matches!(&map.image_overlays[0].image, OverlayImage::Url(url) if url == "plan.png"));
+        assert_eq!(map.image_overlays[0].corners, corners);
+        assert_eq!(map.image_overlays[1].corners, skewed_corners);
+
+        // Corners past a pole, a missing corner, or an inside-out box are rejected
+        assert!(map.add_image_overlay(&plan, &pairs(&[(91.0, -9.0), (50.0, 21.0), (39.0, 19.0), (40.0, -11.0)])).is_err());
+        assert!(map.add_image_overlay(&plan, &pairs(&skewed_corners[..3])).is_err());
+        assert!(map.add_image_overlay(&plan, &numbers(&[50.0, -10.0, 40.0, 20.0])).is_err());
+        assert_eq!(map.image_overlays.len(), 2);
+
+        // Overlays and tile layers share ids and stack by z-index, then by
+        // the order they were added
+        map.set_image_overlay_z_index(skewed, -1).unwrap();
+        let order: Vec<(bool, u32)> = map
+            .stacked_layers()
+            .iter()
+            .map(|layer| match layer {
+                StackedLayer::Tile(layer) => (false, layer.id),
+                StackedLayer::Overlay(overlay) => (true, overlay.id),
+            })
+            .collect();
+        assert_eq!(order, [(true, skewed), (false, base), (true, boxed), (false, labels)]);
+
+        map.set_image_overlay_z_index(boxed, 5).unwrap();
+        map.set_image_overlay_opacity(boxed, 0.5).unwrap();
+        assert!(map.set_image_overlay_opacity(boxed, 2.0).is_err());
+        assert!(map.set_image_overlay_visible(base, false).is_err());
+        map.set_image_overlay_visible(skewed, false).unwrap();
+        let top = map.stacked_layers().pop();
+        assert!(matches!(top, Some(StackedLayer::Overlay(ImageOverlay { id, z_index: 5, .. })) if id == boxed));
+        map.remove_image_overlay(skewed).unwrap();
+        assert_eq!(map.image_overlays.len(), 1);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_tile_fallbacks_use_ancestors_and_children() {
+        use std::collections::HashSet;
+        let tile = crate::TileCoord { x: 5, y: 6, z: 3 };
+        let mut layer = crate::TileLayer::new("https://tiles.example.com/{z}/{x}/{y}.png");
+
+        // Exact tile wins
+        let cached: HashSet<&str> = ["0/3/5/6", "0/1/1/1"].into_iter().collect();
+        let draws = crate::RustyleafMap::tile_fallbacks(&layer, &tile, 5, |key| cached.contains(key));
+        assert_eq!(draws.len(), 1);
+        assert_eq!(draws[0].key, "0/3/5/6");
+
+        // A grandparent is cropped to the tile's quarter of a quarter
+        let cached: HashSet<&str> = ["0/1/1/1"].into_iter().collect();
+        let draws = crate::RustyleafMap::tile_fallbacks(&layer, &tile, 5, |key| cached.contains(key));
+        assert_eq!(draws.len(), 1);
+        assert_eq!(draws[0].key, "0/1/1/1");
+        assert_eq!(draws[0].region, tile);
+        assert_eq!(draws[0].uv, [0.25, 0.5, 0.5, 0.75]);
+
+        // Nothing above the layer's minimum zoom is used
+        layer.min_zoom = 2;
+        assert!(crate::RustyleafMap::tile_fallbacks(&layer, &tile, 5, |key| cached.contains(key)).is_empty());
+        layer.min_zoom = 0;
+
+        // Partial children are drawn over the ancestor; a full set replaces it
+        let cached: HashSet<&str> = ["0/1/1/1", "0/4/10/12"].into_iter().collect();
+        let draws = crate::RustyleafMap::tile_fallbacks(&layer, &tile, 5, |key| cached.contains(key));
+        let keys: Vec<&str> = draws.iter().map(|draw| draw.key.as_str()).collect();
+        assert_eq!(keys, ["0/1/1/1", "0/4/10/12"]);
+        let cached: HashSet<&str> = ["0/1/1/1", "0/4/10/12", "0/4/11/12", "0/4/10/13", "0/4/11/13"].into_iter().collect();
+        let draws = crate::RustyleafMap::tile_fallbacks(&layer, &tile, 5, |key| cached.contains(key));
+        assert_eq!(draws.len(), 4);
+        assert!(draws.iter().all(|draw| draw.region.z == 4 && draw.uv == [0.0, 0.0, 1.0, 1.0]));
+
+        // World copies draw the canonical texture at the unwrapped position
+        let copy = crate::TileCoord { x: 13, y: 6, z: 3 };
+        let draws = crate::RustyleafMap::tile_fallbacks(&layer, &copy, 5, |key| cached.contains(key));
+        assert_eq!(draws[0].key, "0/4/10/12");
+        assert_eq!(draws[0].region, crate::TileCoord { x: 26, y: 12, z: 4 });
+    }
+
+    #[wasm_bindgen_test]
+    fn test_tile_layers_stack_by_z_index_with_separate_caches() {
+        use crate::tile_cache::TileCache;
+
+        let mut map = crate::RustyleafMap::new(800, 600);
+        let base = map.add_tile_layer("https://base.example.com/{z}/{x}/{y}.png").unwrap();
+        let labels = map.add_tile_layer("https://labels.example.com/{z}/{x}/{y}.png").unwrap();
+        let hillshade = map.add_tile_layer("https://shade.example.com/{z}/{x}/{y}.png").unwrap();
+        assert_ne!(base, labels);
+
+        // Equal z-indexes keep insertion order; a higher one draws on top
+        map.set_tile_layer_z_index(labels, 10).unwrap();
+        let order: Vec<u32> = map.tile_layers_in_draw_order().iter().map(|layer| layer.id).collect();
+        assert_eq!(order, [base, hillshade, labels]);
+
+        map.set_tile_layer_opacity(hillshade, 0.4).unwrap();
+        map.set_tile_layer_visible(base, false).unwrap();
+        map.remove_tile_layer(labels).unwrap();
+        let layers: Vec<(u32, f32, bool)> = map.tile_layers.iter().map(|layer| (layer.id, layer.opacity, layer.visible)).collect();
+        assert_eq!(layers, [(base, 1.0, false), (hillshade, 0.4, true)]);
+
+        // The same tile of two layers gets two cache entries
+        let coord = crate::TileCoord { x: 1, y: 2, z: 3 };
+        let mut cache: TileCache<u32> = TileCache::new(10, 1000);
+        cache.insert(coord.key(base), 1, 100);
+        cache.insert(coord.key(hillshade), 2, 100);
+        cache.insert(crate::TileCoord { x: 0, y: 0, z: 0 }.key(hillshade), 3, 100);
+        assert_eq!(cache.len(), 3);
+        let mut removed: Vec<u32> = cache.remove_prefixed(&format!("{}/", hillshade)).into_iter().map(|(_, value)| value).collect();
+        removed.sort();
+        assert_eq!(removed, [2, 3]);
+        assert_eq!(cache.peek(&coord.key(base)), Some(&1));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_tile_cache_lru_eviction_and_pinning() {
+        use crate::tile_cache::TileCache;
+        use std::collections::HashSet;
+
+        let mut cache: TileCache<u32> = TileCache::new(3, 1000);
+        cache.insert("0/0/0".to_string(), 0, 100);
+        cache.insert("1/0/0".to_string(), 1, 100);
+        cache.insert("1/1/0".to_string(), 2, 100);
+        cache.insert("1/0/1".to_string(), 3, 100);
+
+        // Using the oldest tile makes the next one the eviction candidate
+        assert_eq!(cache.get("0/0/0"), Some(&0));
+        assert_eq!(cache.get("2/0/0"), None);
+        let evicted = cache.evict();
+        assert_eq!(evicted, vec![("1/0/0".to_string(), 1)]);
+        assert_eq!((cache.len(), cache.bytes()), (3, 300));
+
+        // Pinned tiles survive even when the byte budget is blown
+        cache.set_pinned(["1/1/0".to_string()].into_iter().collect::<HashSet<_>>());
+        cache.set_limits(3, 150);
+        let evicted: Vec<String> = cache.evict().into_iter().map(|(key, _)| key).collect();
+        assert_eq!(evicted, ["1/0/1", "0/0/0"]);
+        assert!(cache.contains("1/1/0"));
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.evictions), (1, 1, 3));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_tile_scheduler_priority_cancel_and_retry() {
+        use crate::tile_scheduler::{TileRequestState, TileScheduler};
+        use crate::TileCoord;
+
+        let wanted = |keys: &[(&str, f64)]| -> Vec<(String, TileCoord, f64)> {
+            keys.iter().map(|&(key, priority)| (key.to_string(), TileCoord { x: 0, y: 0, z: 0 }, priority)).collect()
+        };
+        let mut scheduler: TileScheduler<u32> = TileScheduler::new(2, 3);
+
+        // The two most central tiles start first
+        let plan = scheduler.plan(wanted(&[("a", 300.0), ("b", 10.0), ("c", 50.0)]), 0.0);
+        let started: Vec<String> = plan.start.into_iter().map(|(key, _)| key).collect();
+        assert_eq!(started, ["b", "c"]);
+        scheduler.started("b".to_string(), 1);
+        scheduler.started("c".to_string(), 2);
+
+        // Panning "c" out of view aborts it and frees its slot for "a"
+        let plan = scheduler.plan(wanted(&[("a", 300.0), ("b", 10.0)]), 0.0);
+        assert_eq!(plan.cancel, vec![("c".to_string(), 2)]);
+        assert_eq!(plan.start.len(), 1);
+        scheduler.started("a".to_string(), 3);
+
+        // A failure backs off 1s, then 2s, and gives up after three attempts
+        scheduler.succeeded("b");
+        scheduler.failed("a", 0.0);
+        assert_eq!(scheduler.state("a"), TileRequestState::Retrying { attempts: 1, retry_at: 1000.0 });
+        assert!(scheduler.plan(wanted(&[("a", 0.0)]), 500.0).start.is_empty());
+        assert_eq!(scheduler.plan(wanted(&[("a", 0.0)]), 1000.0).start.len(), 1);
+        scheduler.started("a".to_string(), 3);
+        scheduler.failed("a", 1000.0);
+        assert_eq!(scheduler.state("a"), TileRequestState::Retrying { attempts: 2, retry_at: 3000.0 });
+        scheduler.failed("a", 3000.0);
+        assert_eq!(scheduler.state("a"), TileRequestState::Failed { attempts: 3 });
+        assert!(scheduler.plan(wanted(&[("a", 0.0)]), 100_000.0).start.is_empty());
+        assert_eq!(scheduler.state("b"), TileRequestState::Idle);
+
+        // Failures outlive their tile leaving the view, so panning back
+        // doesn't retry it, and are forgotten ten minutes after the last attempt
+        scheduler.plan(wanted(&[("b", 0.0)]), 3000.0);
+        assert_eq!(scheduler.state("a"), TileRequestState::Failed { attempts: 3 });
+        assert!(scheduler.plan(wanted(&[("a", 0.0)]), 4000.0).start.is_empty());
+        scheduler.plan(wanted(&[("b", 0.0)]), 3000.0 + 600_000.0);
+        assert_eq!(scheduler.state("a"), TileRequestState::Idle);
+    }
+}
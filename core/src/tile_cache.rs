@@ -0,0 +1,159 @@
+// Least-recently-used tile cache
+//
+// Entries are keyed by "layer/z/x/y" and carry an estimated size in bytes. The cache
+// never frees anything itself: `evict` hands back what has to go so the owner
+// can release the GPU texture behind each entry right away.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Running counters since the cache was created
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+}
+
+struct Entry<T> {
+    value: T,
+    bytes: usize,
+    // Position in `recency`; larger is more recent
+    last_used: u64,
+}
+
+pub struct TileCache<T> {
+    entries: HashMap<String, Entry<T>>,
+    // Keys ordered from least to most recently used
+    recency: BTreeMap<u64, String>,
+    clock: u64,
+    bytes: usize,
+    max_tiles: usize,
+    max_bytes: usize,
+    // Keys that must survive eviction, e.g. the tiles on screen
+    pinned: HashSet<String>,
+    stats: CacheStats,
+}
+
+impl<T> TileCache<T> {
+    pub fn new(max_tiles: usize, max_bytes: usize) -> Self {
+        TileCache {
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            clock: 0,
+            bytes: 0,
+            max_tiles,
+            max_bytes,
+            pinned: HashSet::new(),
+            stats: CacheStats::default(),
+        }
+    }
+
+    /// Looks up a tile, counting a hit or a miss and marking it most recently used
+    pub fn get(&mut self, key: &str) -> Option<&T> {
+        if !self.entries.contains_key(key) {
+            self.stats.misses += 1;
+            return None;
+        }
+        self.stats.hits += 1;
+        self.touch(key);
+        self.entries.get(key).map(|entry| &entry.value)
+    }
+
+    /// Looks up a tile without touching its recency or the stats
+    pub fn peek(&self, key: &str) -> Option<&T> {
+        self.entries.get(key).map(|entry| &entry.value)
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    /// Stores a tile as the most recently used. A value already under the
+    /// key is replaced and returned.
+    pub fn insert(&mut self, key: String, value: T, bytes: usize) -> Option<T> {
+        let replaced = self.remove(&key);
+        self.clock += 1;
+        self.recency.insert(self.clock, key.clone());
+        self.bytes += bytes;
+        self.entries.insert(key, Entry { value, bytes, last_used: self.clock });
+        replaced
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<T> {
+        let entry = self.entries.remove(key)?;
+        self.recency.remove(&entry.last_used);
+        self.bytes -= entry.bytes;
+        Some(entry.value)
+    }
+
+    /// Removes every tile whose key starts with `prefix`, e.g. all tiles of a
+    /// layer, returning them. Not counted as evictions.
+    pub fn remove_prefixed(&mut self, prefix: &str) -> Vec<(String, T)> {
+        let keys: Vec<String> = self.entries.keys().filter(|key| key.starts_with(prefix)).cloned().collect();
+        keys.into_iter()
+            .filter_map(|key| self.remove(&key).map(|value| (key, value)))
+            .collect()
+    }
+
+    /// Replaces the set of tiles protected from eviction
+    pub fn set_pinned(&mut self, keys: HashSet<String>) {
+        self.pinned = keys;
+    }
+
+    pub fn set_limits(&mut self, max_tiles: usize, max_bytes: usize) {
+        self.max_tiles = max_tiles;
+        self.max_bytes = max_bytes;
+    }
+
+    /// Removes least recently used, unpinned tiles until the cache is within
+    /// both limits, returning them. Pinned tiles may keep it over budget.
+    pub fn evict(&mut self) -> Vec<(String, T)> {
+        let mut over_budget: Vec<String> = Vec::new();
+        let (mut tiles, mut bytes) = (self.entries.len(), self.bytes);
+        for key in self.recency.values() {
+            if tiles <= self.max_tiles && bytes <= self.max_bytes {
+                break;
+            }
+            if self.pinned.contains(key) {
+                continue;
+            }
+            tiles -= 1;
+            bytes -= self.entries[key].bytes;
+            over_budget.push(key.clone());
+        }
+
+        self.stats.evictions += over_budget.len() as u64;
+        over_budget
+            .into_iter()
+            .filter_map(|key| self.remove(&key).map(|value| (key, value)))
+            .collect()
+    }
+
+    /// Empties the cache, returning every tile. Not counted as evictions.
+    pub fn clear(&mut self) -> Vec<(String, T)> {
+        self.recency.clear();
+        self.bytes = 0;
+        self.entries.drain().map(|(key, entry)| (key, entry.value)).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+
+    fn touch(&mut self, key: &str) {
+        if let Some(entry) = self.entries.get_mut(key) {
+            self.recency.remove(&entry.last_used);
+            self.clock += 1;
+            entry.last_used = self.clock;
+            self.recency.insert(self.clock, key.to_string());
+        }
+    }
+}
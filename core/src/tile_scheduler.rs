@@ -0,0 +1,135 @@
+// Tile request scheduling
+//
+// The map tells the scheduler which missing tiles it wants, each with a
+// priority (lower loads first) and whatever it needs to start the request.
+// The scheduler decides which requests to start within the concurrency cap,
+// which in-flight requests to abort because their tile left the view, and
+// when a failed tile may be retried.
+
+use std::collections::{HashMap, HashSet};
+
+// Failed tiles are retried after 1s, 2s, 4s... up to this delay
+const MAX_RETRY_DELAY_MS: f64 = 30_000.0;
+const BASE_RETRY_DELAY_MS: f64 = 1_000.0;
+// Failures are remembered this long after the last attempt, whether or not
+// their tile is in view, so a tile that keeps 404ing isn't retried each time
+// it is panned back in, yet the record doesn't outlive a session's panning
+const FAILURE_MEMORY_MS: f64 = 10.0 * 60_000.0;
+
+/// Where a tile's request stands
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TileRequestState {
+    /// Not requested, or loaded
+    Idle,
+    Loading,
+    /// Failed `attempts` times; may be requested again from `retry_at` (ms)
+    Retrying { attempts: u32, retry_at: f64 },
+    /// Failed too often to try again
+    Failed { attempts: u32 },
+}
+
+struct Failure {
+    attempts: u32,
+    retry_at: f64,
+    failed_at: f64,
+}
+
+/// What the map should do after `plan`
+pub struct RequestPlan<R, H> {
+    /// Tiles to start loading, most important first
+    pub start: Vec<(String, R)>,
+    /// Requests for tiles no longer wanted, with their handles to abort
+    pub cancel: Vec<(String, H)>,
+}
+
+pub struct TileScheduler<H> {
+    // Handle of each request in flight, e.g. the image element loading it
+    in_flight: HashMap<String, H>,
+    failures: HashMap<String, Failure>,
+    max_concurrent: usize,
+    max_attempts: u32,
+}
+
+impl<H> TileScheduler<H> {
+    pub fn new(max_concurrent: usize, max_attempts: u32) -> Self {
+        TileScheduler {
+            in_flight: HashMap::new(),
+            failures: HashMap::new(),
+            max_concurrent,
+            max_attempts,
+        }
+    }
+
+    pub fn set_max_concurrent(&mut self, max_concurrent: usize) {
+        self.max_concurrent = max_concurrent;
+    }
+
+    /// Picks requests to start from `wanted` (key, request, priority), and
+    /// requests to cancel because their tile is no longer wanted. Started
+    /// requests must be reported back with `started`. Failures older than
+    /// `FAILURE_MEMORY_MS` are forgotten, so those tiles are tried afresh.
+    pub fn plan<R>(&mut self, wanted: Vec<(String, R, f64)>, now: f64) -> RequestPlan<R, H> {
+        self.failures.retain(|_, failure| now - failure.failed_at < FAILURE_MEMORY_MS);
+        let wanted_keys: HashSet<&str> = wanted.iter().map(|(key, _, _)| key.as_str()).collect();
+        let stale: Vec<String> = self.in_flight.keys()
+            .filter(|key| !wanted_keys.contains(key.as_str()))
+            .cloned()
+            .collect();
+        let cancel = stale
+            .into_iter()
+            .filter_map(|key| self.in_flight.remove(&key).map(|handle| (key, handle)))
+            .collect();
+
+        let mut candidates: Vec<(String, R, f64)> = wanted
+            .into_iter()
+            .filter(|(key, _, _)| !self.in_flight.contains_key(key))
+            .filter(|(key, _, _)| match self.failures.get(key) {
+                Some(failure) => failure.attempts < self.max_attempts && failure.retry_at <= now,
+                None => true,
+            })
+            .collect();
+        candidates.sort_by(|a, b| a.2.total_cmp(&b.2));
+
+        let free = self.max_concurrent.saturating_sub(self.in_flight.len());
+        let start = candidates.into_iter().take(free).map(|(key, request, _)| (key, request)).collect();
+        RequestPlan { start, cancel }
+    }
+
+    /// Records a request that has begun loading
+    pub fn started(&mut self, key: String, handle: H) {
+        self.in_flight.insert(key, handle);
+    }
+
+    /// Records a tile that loaded, clearing any earlier failures
+    pub fn succeeded(&mut self, key: &str) {
+        self.in_flight.remove(key);
+        self.failures.remove(key);
+    }
+
+    /// Records a failed request and schedules its retry with exponential backoff
+    pub fn failed(&mut self, key: &str, now: f64) {
+        self.in_flight.remove(key);
+        let failure = self.failures.entry(key.to_string()).or_insert(Failure { attempts: 0, retry_at: now, failed_at: now });
+        failure.attempts += 1;
+        failure.failed_at = now;
+        let delay = BASE_RETRY_DELAY_MS * 2f64.powi(failure.attempts as i32 - 1);
+        failure.retry_at = now + delay.min(MAX_RETRY_DELAY_MS);
+    }
+
+    /// Cancels everything in flight and forgets all failures
+    pub fn reset(&mut self) -> Vec<(String, H)> {
+        self.failures.clear();
+        self.in_flight.drain().collect()
+    }
+
+    pub fn state(&self, key: &str) -> TileRequestState {
+        if self.in_flight.contains_key(key) {
+            return TileRequestState::Loading;
+        }
+        match self.failures.get(key) {
+            Some(failure) if failure.attempts >= self.max_attempts => TileRequestState::Failed { attempts: failure.attempts },
+            Some(failure) => TileRequestState::Retrying { attempts: failure.attempts, retry_at: failure.retry_at },
+            None => TileRequestState::Idle,
+        }
+    }
+}
@@ -0,0 +1,178 @@
+// Where a tile layer's tiles come from
+//
+// A source answers a tile request with the encoded tile, an image URL, or
+// nothing if it has no such tile. Answers come through a callback, which
+// sources holding their tiles in memory call right away. Tiles are addressed
+// z/x/y with rows from the north, whatever the source's own numbering.
+
+use std::collections::HashMap;
+
+use crate::pmtiles::{decompress, Compression};
+use crate::TileCoord;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TileFormat {
+    /// PNG, JPEG, WebP or any other image the browser decodes
+    Image,
+    /// Mapbox Vector Tile protobuf
+    Mvt,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TileData {
+    /// An image file or vector tile protobuf
+    Bytes(Vec<u8>),
+    /// Where to download the tile from
+    Url(String),
+}
+
+pub type TileCallback = Box<dyn FnOnce(Result<Option<TileData>, String>)>;
+
+pub trait TileSource {
+    fn load(&self, coord: &TileCoord, done: TileCallback);
+
+    /// Format of the tiles, `None` while not yet known
+    fn format(&self) -> Option<TileFormat>;
+
+    /// Tile levels the source has tiles for, if limited
+    fn zoom_range(&self) -> Option<(u32, u32)> {
+        None
+    }
+
+    /// Area covered by the tiles as [sw_lat, sw_lng, ne_lat, ne_lng], if known
+    fn bounds(&self) -> Option<[f64; 4]> {
+        None
+    }
+}
+
+/// Tiles served at URLs built from a template with {s}, {r}, {q}, {z}, {x}
+/// and {y} placeholders
+#[derive(Clone, Debug, PartialEq)]
+pub struct UrlTemplateSource {
+    pub template: String,
+    /// Values for {s}, picked by tile position
+    pub subdomains: Vec<String>,
+    /// Rows numbered from the south, as in TMS services
+    pub tms: bool,
+    /// Added to tile levels to give the {z} in URLs
+    pub zoom_offset: i32,
+    /// Whether {r} asks for double-resolution tiles
+    pub retina: bool,
+    pub format: TileFormat,
+}
+
+impl UrlTemplateSource {
+    pub fn tile_url(&self, coord: &TileCoord) -> String {
+        let subdomain = self
+            .subdomains
+            .get((coord.x + coord.y).unsigned_abs() as usize % self.subdomains.len().max(1))
+            .map(String::as_str)
+            .unwrap_or("a");
+        let retina = if self.retina { "@2x" } else { "" };
+        let z = coord.z as i32 + self.zoom_offset;
+        let y = if self.tms { (1 << coord.z) - 1 - coord.y } else { coord.y };
+        self.template
+            .replace("{s}", subdomain)
+            .replace("{r}", retina)
+            .replace("{q}", &coord.quadkey())
+            .replace("{z}", &z.to_string())
+            .replace("{x}", &coord.x.to_string())
+            .replace("{y}", &y.to_string())
+    }
+}
+
+impl TileSource for UrlTemplateSource {
+    fn load(&self, coord: &TileCoord, done: TileCallback) {
+        done(Ok(Some(TileData::Url(self.tile_url(coord)))));
+    }
+
+    fn format(&self) -> Option<TileFormat> {
+        Some(self.format)
+    }
+}
+
+/// Tiles held in memory, such as the rows of an MBTiles file's `tiles` table
+/// handed over by the host
+#[derive(Clone, Debug, PartialEq)]
+pub struct MemoryTileSource {
+    // Keyed by (z, x, y), rows from the north
+    tiles: HashMap<(u32, u32, u32), Vec<u8>>,
+    format: TileFormat,
+    zoom_range: Option<(u32, u32)>,
+    bounds: Option<[f64; 4]>,
+}
+
+impl MemoryTileSource {
+    pub fn new(format: TileFormat) -> MemoryTileSource {
+        MemoryTileSource {
+            tiles: HashMap::new(),
+            format,
+            zoom_range: None,
+            bounds: None,
+        }
+    }
+
+    /// A source for an MBTiles tileset from its `tiles` rows as (zoom_level,
+    /// tile_column, tile_row, tile_data), rows numbered from the south, and
+    /// its `metadata` table. The format ("png", "jpg", "webp" or "pbf"),
+    /// zoom range and bounds are taken from the metadata, where given.
+    pub fn from_mbtiles(rows: Vec<(u32, u32, u32, Vec<u8>)>, metadata: &HashMap<String, String>) -> Result<MemoryTileSource, String> {
+        let format = match metadata.get("format").map(String::as_str) {
+            Some("pbf") | Some("mvt") => TileFormat::Mvt,
+            Some("png") | Some("jpg") | Some("jpeg") | Some("webp") | Some("avif") | None => TileFormat::Image,
+            Some(other) => return Err(format!("Unsupported MBTiles format {}", other)),
+        };
+        let mut source = MemoryTileSource::new(format);
+        for (z, x, tms_y, data) in rows {
+            if z >= 32 || tms_y >= 1 << z {
+                return Err(format!("Invalid MBTiles tile {}/{}/{}", z, x, tms_y));
+            }
+            source.insert(z, x, (1 << z) - 1 - tms_y, data);
+        }
+
+        let zoom = |name: &str| metadata.get(name).and_then(|value| value.trim().parse::<u32>().ok());
+        if let (Some(min_zoom), Some(max_zoom)) = (zoom("minzoom"), zoom("maxzoom")) {
+            source.zoom_range = Some((min_zoom, max_zoom));
+        }
+        // "left,bottom,right,top" in degrees
+        if let Some(bounds) = metadata.get("bounds") {
+            let values: Vec<f64> = bounds.split(',').filter_map(|value| value.trim().parse().ok()).collect();
+            if let [west, south, east, north] = values[..] {
+                source.bounds = Some([south, west, north, east]);
+            }
+        }
+        Ok(source)
+    }
+
+    pub fn insert(&mut self, z: u32, x: u32, y: u32, data: Vec<u8>) {
+        self.tiles.insert((z, x, y), data);
+    }
+}
+
+impl TileSource for MemoryTileSource {
+    fn load(&self, coord: &TileCoord, done: TileCallback) {
+        let (Ok(x), Ok(y)) = (u32::try_from(coord.x), u32::try_from(coord.y)) else {
+            return done(Ok(None));
+        };
+        let Some(data) = self.tiles.get(&(coord.z, x, y)) else {
+            return done(Ok(None));
+        };
+        // Vector tiles in MBTiles files are usually gzipped
+        if self.format == TileFormat::Mvt && data.starts_with(&[0x1f, 0x8b]) {
+            return done(decompress(data.clone(), Compression::Gzip).map(|data| Some(TileData::Bytes(data))));
+        }
+        done(Ok(Some(TileData::Bytes(data.clone()))));
+    }
+
+    fn format(&self) -> Option<TileFormat> {
+        Some(self.format)
+    }
+
+    fn zoom_range(&self) -> Option<(u32, u32)> {
+        self.zoom_range
+    }
+
+    fn bounds(&self) -> Option<[f64; 4]> {
+        self.bounds
+    }
+}
@@ -0,0 +1,209 @@
+// Persistent tile storage and region seeding
+//
+// Tiles fetched ahead of time for a region are kept in a `TileStore`, keyed
+// by tile URL, so they can still be drawn without a connection; tile layers
+// look there before going to the network. Stores answer through callbacks,
+// like tile sources, and in the browser the host backs them with IndexedDB.
+
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::rc::Rc;
+
+use crate::TileCoord;
+
+pub type BytesCallback = Box<dyn FnOnce(Result<Option<Vec<u8>>, String>)>;
+pub type PutCallback = Box<dyn FnOnce(Result<(), String>)>;
+
+pub trait TileStore {
+    /// Calls `done` with the tile stored under `key`, `None` if there is none
+    fn get(&self, key: &str, done: BytesCallback);
+    fn put(&self, key: &str, data: Vec<u8>, done: PutCallback);
+}
+
+/// A store that lasts as long as the page, answering at once
+#[derive(Default)]
+pub struct MemoryTileStore {
+    tiles: RefCell<HashMap<String, Vec<u8>>>,
+}
+
+impl TileStore for MemoryTileStore {
+    fn get(&self, key: &str, done: BytesCallback) {
+        let tile = self.tiles.borrow().get(key).cloned();
+        done(Ok(tile));
+    }
+
+    fn put(&self, key: &str, data: Vec<u8>, done: PutCallback) {
+        self.tiles.borrow_mut().insert(key.to_string(), data);
+        done(Ok(()));
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SeedProgress {
+    pub total: usize,
+    /// Tiles fetched and stored
+    pub stored: usize,
+    /// Tiles already in the store, or that the server doesn't have
+    pub skipped: usize,
+    pub failed: usize,
+    /// Bytes stored so far
+    pub bytes: usize,
+    pub finished: bool,
+    pub cancelled: bool,
+}
+
+pub type SeedFetch = Box<dyn Fn(&str, &TileCoord, BytesCallback)>;
+
+struct SeedState {
+    // Store key and coordinates of the tiles not yet started
+    pending: VecDeque<(String, TileCoord)>,
+    in_flight: usize,
+    progress: SeedProgress,
+    // Set while `pump` is starting tiles, some of which may finish at once
+    pumping: bool,
+    pump_again: bool,
+}
+
+/// Fetches a list of tiles into a store, a few at a time, skipping tiles
+/// already stored. Clones refer to the same job.
+#[derive(Clone)]
+pub struct SeedJob {
+    state: Rc<RefCell<SeedState>>,
+    store: Rc<dyn TileStore>,
+    // Fetches the tile with the given store key and coordinates; `None`
+    // means the server has no such tile
+    fetch: Rc<SeedFetch>,
+    on_progress: Rc<dyn Fn(&SeedProgress)>,
+    max_requests: usize,
+}
+
+impl SeedJob {
+    pub fn start(
+        tiles: Vec<(String, TileCoord)>,
+        store: Rc<dyn TileStore>,
+        fetch: SeedFetch,
+        on_progress: Rc<dyn Fn(&SeedProgress)>,
+        max_requests: usize,
+    ) -> SeedJob {
+        let job = SeedJob {
+            state: Rc::new(RefCell::new(SeedState {
+                progress: SeedProgress { total: tiles.len(), ..SeedProgress::default() },
+                pending: tiles.into(),
+                in_flight: 0,
+                pumping: false,
+                pump_again: false,
+            })),
+            store,
+            fetch: Rc::new(fetch),
+            on_progress,
+            max_requests: max_requests.max(1),
+        };
+        job.pump();
+        job
+    }
+
+    pub fn progress(&self) -> SeedProgress {
+        self.state.borrow().progress.clone()
+    }
+
+    /// Stops starting tiles; those already requested are left to finish but
+    /// no longer counted
+    pub fn cancel(&self) {
+        let progress = {
+            let mut state = self.state.borrow_mut();
+            if state.progress.finished {
+                return;
+            }
+            state.pending.clear();
+            state.progress.cancelled = true;
+            state.progress.finished = true;
+            state.progress.clone()
+        };
+        (self.on_progress)(&progress);
+    }
+
+    // Starts pending tiles up to the request limit, and reports the end
+    fn pump(&self) {
+        {
+            let mut state = self.state.borrow_mut();
+            if state.pumping {
+                state.pump_again = true;
+                return;
+            }
+            state.pumping = true;
+        }
+        loop {
+            let next = {
+                let mut state = self.state.borrow_mut();
+                let next = if state.in_flight < self.max_requests { state.pending.pop_front() } else { None };
+                if next.is_some() {
+                    state.in_flight += 1;
+                } else if state.pump_again {
+                    state.pump_again = false;
+                    continue;
+                }
+                next
+            };
+            match next {
+                Some((key, coord)) => self.seed_tile(key, coord),
+                None => break,
+            }
+        }
+
+        let finished = {
+            let mut state = self.state.borrow_mut();
+            state.pumping = false;
+            let done = state.pending.is_empty() && state.in_flight == 0 && !state.progress.finished;
+            if done {
+                state.progress.finished = true;
+            }
+            done.then(|| state.progress.clone())
+        };
+        if let Some(progress) = finished {
+            (self.on_progress)(&progress);
+        }
+    }
+
+    fn seed_tile(&self, key: String, coord: TileCoord) {
+        let job = self.clone();
+        let stored_key = key.clone();
+        self.store.get(&stored_key, Box::new(move |stored| {
+            if let Ok(Some(_)) = stored {
+                return job.finish_tile(Ok(None));
+            }
+            let fetched_job = job.clone();
+            let fetched_key = key.clone();
+            (job.fetch)(&fetched_key, &coord, Box::new(move |data| match data {
+                Ok(Some(data)) => {
+                    let bytes = data.len();
+                    let stored_job = fetched_job.clone();
+                    fetched_job.store.put(&key, data, Box::new(move |stored| stored_job.finish_tile(stored.map(|_| Some(bytes)))));
+                }
+                Ok(None) => fetched_job.finish_tile(Ok(None)),
+                Err(error) => fetched_job.finish_tile(Err(error)),
+            }));
+        }));
+    }
+
+    // Counts a tile that was stored with its size, skipped, or failed
+    fn finish_tile(&self, outcome: Result<Option<usize>, String>) {
+        let progress = {
+            let mut state = self.state.borrow_mut();
+            state.in_flight -= 1;
+            if state.progress.cancelled {
+                return;
+            }
+            match outcome {
+                Ok(Some(bytes)) => {
+                    state.progress.stored += 1;
+                    state.progress.bytes += bytes;
+                }
+                Ok(None) => state.progress.skipped += 1,
+                Err(_) => state.progress.failed += 1,
+            }
+            state.progress.clone()
+        };
+        (self.on_progress)(&progress);
+        self.pump();
+    }
+}
@@ -0,0 +1,117 @@
+// WMS GetMap requests
+//
+// A WMS layer is drawn like any tile layer: each tile becomes one GetMap
+// request for the tile's bounding box in the map's CRS, so WMS layers share
+// tile scheduling and the texture cache with template layers.
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WmsVersion {
+    V1_1_1,
+    V1_3_0,
+}
+
+impl WmsVersion {
+    pub fn parse(version: &str) -> Option<WmsVersion> {
+        match version {
+            "1.1.1" => Some(WmsVersion::V1_1_1),
+            "1.3.0" => Some(WmsVersion::V1_3_0),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            WmsVersion::V1_1_1 => "1.1.1",
+            WmsVersion::V1_3_0 => "1.3.0",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct WmsParams {
+    /// Comma-separated layer names
+    pub layers: String,
+    pub styles: String,
+    pub format: String,
+    pub transparent: bool,
+    pub version: WmsVersion,
+    /// Vendor parameters appended as given, e.g. CQL_FILTER
+    pub extra: Vec<(String, String)>,
+}
+
+impl WmsParams {
+    pub fn new(layers: &str) -> WmsParams {
+        WmsParams {
+            layers: layers.to_string(),
+            styles: String::new(),
+            format: "image/jpeg".to_string(),
+            transparent: false,
+            version: WmsVersion::V1_1_1,
+            extra: Vec::new(),
+        }
+    }
+
+    /// GetMap URL for the box `[min_x, min_y, max_x, max_y]` in `crs` units,
+    /// rendered at `width` x `height` pixels
+    pub fn get_map_url(&self, base_url: &str, crs: &str, bbox: [f64; 4], width: u32, height: u32) -> String {
+        let [min_x, min_y, max_x, max_y] = bbox;
+        // WMS 1.3.0 follows the CRS's axis order, which is lat/lng for EPSG:4326
+        let bbox = if self.version == WmsVersion::V1_3_0 && crs.eq_ignore_ascii_case("EPSG:4326") {
+            [min_y, min_x, max_y, max_x]
+        } else {
+            bbox
+        };
+        let crs_param = match self.version {
+            WmsVersion::V1_1_1 => "SRS",
+            WmsVersion::V1_3_0 => "CRS",
+        };
+
+        let mut params: Vec<(&str, String)> = vec![
+            ("SERVICE", "WMS".to_string()),
+            ("REQUEST", "GetMap".to_string()),
+            ("VERSION", self.version.as_str().to_string()),
+            ("LAYERS", self.layers.clone()),
+            ("STYLES", self.styles.clone()),
+            ("FORMAT", self.format.clone()),
+            ("TRANSPARENT", if self.transparent { "TRUE" } else { "FALSE" }.to_string()),
+            (crs_param, crs.to_string()),
+            ("BBOX", bbox.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(",")),
+            ("WIDTH", width.to_string()),
+            ("HEIGHT", height.to_string()),
+        ];
+        params.extend(self.extra.iter().map(|(key, value)| (key.as_str(), value.clone())));
+        with_query(base_url, &params)
+    }
+}
+
+/// Appends encoded query parameters to a service URL, which may already
+/// carry a query of its own
+pub fn with_query(base_url: &str, params: &[(&str, String)]) -> String {
+    let query = params
+        .iter()
+        .map(|(key, value)| format!("{}={}", encode_component(key), encode_component(value)))
+        .collect::<Vec<_>>()
+        .join("&");
+    let separator = if !base_url.contains('?') {
+        "?"
+    } else if base_url.ends_with('?') || base_url.ends_with('&') {
+        ""
+    } else {
+        "&"
+    };
+    format!("{}{}{}", base_url, separator, query)
+}
+
+// Percent-encodes a query value the way encodeURIComponent does
+fn encode_component(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'!' | b'~' | b'*' | b'\'' | b'(' | b')' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
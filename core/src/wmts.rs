@@ -0,0 +1,269 @@
+// WMTS layers from a GetCapabilities document
+//
+// Only tile matrix sets that line up with the map's Web Mercator tile pyramid
+// are used: the top-left corner at the world's north-west corner and matrices
+// that halve in scale from one level to the next. A layer's tiles are then
+// addressed like any other tile layer's, with TileMatrix/TileRow/TileCol
+// standing in for z/y/x.
+
+use std::collections::BTreeMap;
+
+use roxmltree::{Document, Node};
+
+use crate::projection::MERCATOR_HALF_EXTENT;
+use crate::wms::with_query;
+use crate::TileCoord;
+
+// Pixel size in meters that scale denominators assume (0.28mm)
+const STANDARD_PIXEL_SIZE: f64 = 0.00028;
+
+// How far a tile matrix's top-left corner may be off the world corner, in meters
+const CORNER_TOLERANCE: f64 = 1.0;
+
+/// Which layer and options to take from a capabilities document; anything
+/// left unset falls back to the layer's defaults
+#[derive(Clone, Debug, Default)]
+pub struct WmtsOptions {
+    pub layer: Option<String>,
+    pub style: Option<String>,
+    pub tile_matrix_set: Option<String>,
+    pub format: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum TileRequest {
+    /// ResourceURL template with {TileMatrix}, {TileRow}, {TileCol}...
+    Rest(String),
+    /// GetTile endpoint taking key-value parameters
+    Kvp(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct WmtsSource {
+    layer: String,
+    style: String,
+    format: String,
+    tile_matrix_set: String,
+    request: TileRequest,
+    // Default values of the layer's dimensions, e.g. ("Time", "2024-01-01")
+    dimensions: Vec<(String, String)>,
+    // TileMatrix identifier of each level from `min_level` up
+    matrices: Vec<String>,
+    pub min_level: u32,
+    pub tile_size: u32,
+    /// The layer's WGS84 bounding box as [sw_lat, sw_lng, ne_lat, ne_lng]
+    pub bounds: Option<[f64; 4]>,
+}
+
+impl WmtsSource {
+    pub fn from_capabilities(xml: &str, options: &WmtsOptions) -> Result<WmtsSource, String> {
+        let document = Document::parse(xml).map_err(|e| format!("Invalid WMTS capabilities: {}", e))?;
+        let root = document.root_element();
+        let contents = child(root, "Contents").ok_or("WMTS capabilities have no Contents")?;
+
+        let layer = match &options.layer {
+            Some(id) => children(contents, "Layer")
+                .find(|layer| child_text(*layer, "Identifier") == Some(id.as_str()))
+                .ok_or_else(|| format!("WMTS layer {} not found", id))?,
+            None => child(contents, "Layer").ok_or("WMTS capabilities list no layers")?,
+        };
+        let layer_id = child_text(layer, "Identifier").ok_or("WMTS layer has no identifier")?.to_string();
+
+        let style = options.style.clone().unwrap_or_else(|| {
+            children(layer, "Style")
+                .find(|style| style.attribute("isDefault") == Some("true"))
+                .or_else(|| child(layer, "Style"))
+                .and_then(|style| child_text(style, "Identifier"))
+                .unwrap_or("default")
+                .to_string()
+        });
+
+        // RESTful templates for tiles, by format
+        let templates: Vec<(&str, &str)> = children(layer, "ResourceURL")
+            .filter(|resource| resource.attribute("resourceType") == Some("tile"))
+            .filter_map(|resource| Some((resource.attribute("format")?, resource.attribute("template")?)))
+            .collect();
+        let format = match &options.format {
+            Some(format) => format.clone(),
+            None => templates
+                .first()
+                .map(|(format, _)| *format)
+                .or_else(|| child_text(layer, "Format"))
+                .ok_or_else(|| format!("WMTS layer {} lists no formats", layer_id))?
+                .to_string(),
+        };
+        let request = match templates.iter().find(|(template_format, _)| *template_format == format) {
+            Some((_, template)) => TileRequest::Rest(template.to_string()),
+            None => TileRequest::Kvp(
+                get_tile_kvp_url(root).ok_or_else(|| format!("WMTS layer {} has no tile URL for {}", layer_id, format))?,
+            ),
+        };
+
+        // The first linked matrix set that fits the map's tile pyramid
+        let linked: Vec<&str> = children(layer, "TileMatrixSetLink")
+            .filter_map(|link| child_text(link, "TileMatrixSet"))
+            .filter(|id| options.tile_matrix_set.as_deref().is_none_or(|wanted| wanted == *id))
+            .collect();
+        let (tile_matrix_set, (min_level, tile_size, matrices)) = linked
+            .iter()
+            .filter_map(|id| {
+                let set = children(contents, "TileMatrixSet").find(|set| child_text(*set, "Identifier") == Some(*id))?;
+                Some((id.to_string(), web_mercator_matrices(set)?))
+            })
+            .next()
+            .ok_or_else(|| match &options.tile_matrix_set {
+                Some(id) => format!("Tile matrix set {} of WMTS layer {} is not compatible with Web Mercator", id, layer_id),
+                None => format!("WMTS layer {} has no Web Mercator tile matrix set", layer_id),
+            })?;
+
+        let dimensions = children(layer, "Dimension")
+            .filter_map(|dimension| {
+                let default = child_text(dimension, "Default").or_else(|| child_text(dimension, "Value"))?;
+                Some((child_text(dimension, "Identifier")?.to_string(), default.to_string()))
+            })
+            .collect();
+
+        let bounds = child(layer, "WGS84BoundingBox").and_then(|bbox| {
+            let (west, south) = parse_pair(child_text(bbox, "LowerCorner")?)?;
+            let (east, north) = parse_pair(child_text(bbox, "UpperCorner")?)?;
+            Some([south, west, north, east])
+        });
+
+        Ok(WmtsSource {
+            layer: layer_id,
+            style,
+            format,
+            tile_matrix_set,
+            request,
+            dimensions,
+            matrices,
+            min_level,
+            tile_size,
+            bounds,
+        })
+    }
+
+    /// Highest tile level the matrix set provides
+    pub fn max_level(&self) -> u32 {
+        self.min_level + self.matrices.len() as u32 - 1
+    }
+
+    pub fn tile_url(&self, coord: &TileCoord) -> String {
+        let index = (coord.z.saturating_sub(self.min_level) as usize).min(self.matrices.len() - 1);
+        let matrix = &self.matrices[index];
+        match &self.request {
+            TileRequest::Rest(template) => {
+                let mut url = template
+                    .replace("{TileMatrixSet}", &self.tile_matrix_set)
+                    .replace("{TileMatrix}", matrix)
+                    .replace("{TileRow}", &coord.y.to_string())
+                    .replace("{TileCol}", &coord.x.to_string())
+                    .replace("{Style}", &self.style);
+                for (name, value) in &self.dimensions {
+                    url = url.replace(&format!("{{{}}}", name), value);
+                }
+                url
+            }
+            TileRequest::Kvp(base_url) => {
+                let mut params: Vec<(&str, String)> = vec![
+                    ("SERVICE", "WMTS".to_string()),
+                    ("REQUEST", "GetTile".to_string()),
+                    ("VERSION", "1.0.0".to_string()),
+                    ("LAYER", self.layer.clone()),
+                    ("STYLE", self.style.clone()),
+                    ("FORMAT", self.format.clone()),
+                    ("TILEMATRIXSET", self.tile_matrix_set.clone()),
+                    ("TILEMATRIX", matrix.clone()),
+                    ("TILEROW", coord.y.to_string()),
+                    ("TILECOL", coord.x.to_string()),
+                ];
+                params.extend(self.dimensions.iter().map(|(name, value)| (name.as_str(), value.clone())));
+                with_query(base_url, &params)
+            }
+        }
+    }
+}
+
+// Tile level, tile size and matrix identifiers of a Web Mercator matrix set,
+// keeping the run of consecutive levels from the coarsest one
+fn web_mercator_matrices(set: Node) -> Option<(u32, u32, Vec<String>)> {
+    if !is_web_mercator(child_text(set, "SupportedCRS")?) {
+        return None;
+    }
+
+    let mut levels: BTreeMap<u32, String> = BTreeMap::new();
+    let mut tile_size = None;
+    for matrix in children(set, "TileMatrix") {
+        let (Some(id), Some(scale), Some(width), Some(height), Some((left, top))) = (
+            child_text(matrix, "Identifier"),
+            child_text(matrix, "ScaleDenominator").and_then(|v| v.trim().parse::<f64>().ok()),
+            child_text(matrix, "TileWidth").and_then(|v| v.trim().parse::<u32>().ok()),
+            child_text(matrix, "TileHeight").and_then(|v| v.trim().parse::<u32>().ok()),
+            child_text(matrix, "TopLeftCorner").and_then(parse_pair),
+        ) else {
+            continue;
+        };
+        let corner_matches = (left + MERCATOR_HALF_EXTENT).abs() < CORNER_TOLERANCE
+            && (top - MERCATOR_HALF_EXTENT).abs() < CORNER_TOLERANCE;
+        if width != height || !width.is_power_of_two() || tile_size.is_some_and(|size| size != width) || !corner_matches {
+            continue;
+        }
+
+        // Number of tiles across the world at this scale, as a power of two
+        let level = (2.0 * MERCATOR_HALF_EXTENT / (width as f64 * scale * STANDARD_PIXEL_SIZE)).log2();
+        if level < -0.01 || (level - level.round()).abs() > 0.01 {
+            continue;
+        }
+        levels.entry(level.round() as u32).or_insert_with(|| id.to_string());
+        tile_size = Some(width);
+    }
+
+    let min_level = *levels.keys().next()?;
+    let matrices = levels
+        .into_iter()
+        .enumerate()
+        .take_while(|(i, (level, _))| *level == min_level + *i as u32)
+        .map(|(_, (_, id))| id)
+        .collect();
+    Some((min_level, tile_size?, matrices))
+}
+
+// EPSG:3857 and its aliases, in any of the URN or URL spellings
+fn is_web_mercator(crs: &str) -> bool {
+    let code = crs.rsplit([':', '/']).next().unwrap_or("");
+    matches!(code, "3857" | "900913" | "3785" | "102100" | "102113")
+}
+
+// GetTile URL from OperationsMetadata, preferring one that accepts KVP
+fn get_tile_kvp_url(root: Node) -> Option<String> {
+    let get_tile = children(child(root, "OperationsMetadata")?, "Operation").find(|op| op.attribute("name") == Some("GetTile"))?;
+    let gets: Vec<Node> = get_tile
+        .descendants()
+        .filter(|node| node.is_element() && node.tag_name().name() == "Get")
+        .collect();
+    let accepts_kvp = |get: &Node| get.descendants().any(|node| node.is_element() && node.tag_name().name() == "Value" && node.text() == Some("KVP"));
+    let get = gets.iter().find(|get| accepts_kvp(get)).or_else(|| gets.first())?;
+    get.attributes().find(|attribute| attribute.name() == "href").map(|href| href.value().to_string())
+}
+
+// Element children by local name, ignoring namespaces
+fn children<'a, 'input: 'a>(node: Node<'a, 'input>, name: &'a str) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+    node.children().filter(move |child| child.is_element() && child.tag_name().name() == name)
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|child| child.is_element() && child.tag_name().name() == name)
+}
+
+fn child_text<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    child(node, name)?.text().map(str::trim)
+}
+
+// "x y" pair of numbers, as in corners
+fn parse_pair(text: &str) -> Option<(f64, f64)> {
+    let mut values = text.split_whitespace().map(|value| value.parse::<f64>());
+    match (values.next(), values.next()) {
+        (Some(Ok(x)), Some(Ok(y))) => Some((x, y)),
+        _ => None,
+    }
+}
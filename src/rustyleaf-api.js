@@ -135,7 +135,12 @@ class Map {
     }
 
     // Initialize WASM map
-    this.wasmMap = new RustyleafMap(this.width, this.height);
+    this.wasmMap = options.projection
+      ? RustyleafMap.with_projection(this.width, this.height, options.projection)
+      : new RustyleafMap(this.width, this.height);
+    // The drawing buffer is sized in device pixels; everything else stays in CSS pixels
+    this.pixelRatio = options.pixelRatio;
+    this.wasmMap.set_pixel_ratio(this.pixelRatio || window.devicePixelRatio || 1);
     try {
       this.wasmMap.init_canvas(this.canvas.id);
     } catch (error) {
@@ -151,7 +156,9 @@ class Map {
     }
 
     // Set initial view
-    const center = options.center || [48.8566, 2.3522]; // Paris by default
+    // Paris by default; other projections start wherever the core placed them
+    const defaultCenter = options.projection ? Array.from(this.wasmMap.get_center()) : [48.8566, 2.3522];
+    const center = options.center || defaultCenter;
     const zoom = options.zoom || 12;
 
     // Validate center coordinates
@@ -168,7 +175,29 @@ class Map {
       throw new Error('Invalid zoom level: must be a number between 0 and 24');
     }
 
+    if (options.minZoom !== undefined) {
+      this.wasmMap.set_min_zoom(options.minZoom);
+    }
+    if (options.maxZoom !== undefined) {
+      this.wasmMap.set_max_zoom(options.maxZoom);
+    }
+    if (options.renderWorldCopies !== undefined) {
+      this.wasmMap.set_render_world_copies(!!options.renderWorldCopies);
+    }
+    if (options.maxBoundsViscosity !== undefined) {
+      this.wasmMap.set_max_bounds_viscosity(options.maxBoundsViscosity);
+    }
+    if (options.maxBounds) {
+      this.setMaxBounds(options.maxBounds);
+    }
+
     this.wasmMap.set_view(center[0], center[1], zoom);
+    if (options.bearing !== undefined) {
+      this.wasmMap.set_bearing(options.bearing);
+    }
+    if (options.pitch !== undefined) {
+      this.wasmMap.set_pitch(options.pitch);
+    }
 
     // Set up event handlers
     this._setupEventHandlers();
@@ -213,6 +242,24 @@ class Map {
     this.wasmMap.zoom_out();
     return this;
   }
+
+  flyTo(latlng, zoom = this.getZoom(), options = {}) {
+    this.wasmMap.fly_to(latlng[0], latlng[1], zoom, this._animationOptions(options));
+    return this;
+  }
+
+  easeTo(latlng, zoom = this.getZoom(), options = {}) {
+    this.wasmMap.ease_to(latlng[0], latlng[1], zoom, this._animationOptions(options));
+    return this;
+  }
+
+  // Leaflet-style durations are in seconds; the core expects milliseconds
+  _animationOptions(options) {
+    return {
+      duration: options.duration !== undefined ? options.duration * 1000 : undefined,
+      easing: options.easing
+    };
+  }
   
   // Get WebGL support information
   getWebGLSupport() {
@@ -237,6 +284,92 @@ class Map {
     this.wasmMap.set_max_zoom(maxZoom);
     return this;
   }
+
+  setBearing(bearing) {
+    this.wasmMap.set_bearing(bearing);
+    return this;
+  }
+
+  getBearing() {
+    return this.wasmMap.get_bearing();
+  }
+
+  // Fixes the device pixel ratio; pass null to follow window.devicePixelRatio again
+  setPixelRatio(ratio) {
+    this.pixelRatio = ratio;
+    this.wasmMap.set_pixel_ratio(ratio || window.devicePixelRatio || 1);
+    return this;
+  }
+
+  getPixelRatio() {
+    return this.wasmMap.get_pixel_ratio();
+  }
+
+  // Tile texture cache budget; least recently used tiles are freed first
+  setTileCacheLimits(maxTiles, maxBytes) {
+    this.wasmMap.set_tile_cache_limits(maxTiles, maxBytes);
+    return this;
+  }
+
+  getTileCacheStats() {
+    return this.wasmMap.get_tile_cache_stats();
+  }
+
+  // Persistent store tiles are read from before downloading and that
+  // TileLayer.seed fills: an IndexedDbTileStore, any object with get(key) and
+  // put(key, bytes), 'memory' for the session only, or null for none
+  setTileStore(store) {
+    this.wasmMap.set_tile_store(store);
+    return this;
+  }
+
+  // Concurrent tile downloads; the most central missing tiles load first
+  setMaxTileRequests(count) {
+    this.wasmMap.set_max_tile_requests(count);
+    return this;
+  }
+
+  // Tilt in degrees, from 0 (straight down) to 60
+  setPitch(pitch) {
+    this.wasmMap.set_pitch(pitch);
+    return this;
+  }
+
+  getPitch() {
+    return this.wasmMap.get_pitch();
+  }
+
+  // Corners of the visible area: a rotated quad under a bearing, a trapezoid under pitch
+  getBoundsQuad() {
+    return Array.from(this.wasmMap.get_bounds_quad(), (corner) => [corner[0], corner[1]]);
+  }
+
+  getProjection() {
+    return this.wasmMap.get_projection();
+  }
+
+  getMinZoom() {
+    return this.wasmMap.get_min_zoom();
+  }
+
+  getMaxZoom() {
+    return this.wasmMap.get_max_zoom();
+  }
+
+  setRenderWorldCopies(enabled) {
+    this.wasmMap.set_render_world_copies(!!enabled);
+    return this;
+  }
+
+  setMaxBounds(bounds) {
+    // Passing null or undefined removes the restriction
+    const flatBounds = bounds ? [
+      bounds[0][0], bounds[0][1], // sw_lat, sw_lng
+      bounds[1][0], bounds[1][1]  // ne_lat, ne_lng
+    ] : null;
+    this.wasmMap.set_max_bounds(flatBounds);
+    return this;
+  }
   
   getBounds() {
     const bounds = this.wasmMap.get_bounds();
@@ -246,16 +379,23 @@ class Map {
     ];
   }
   
-  fitBounds(bounds) {
+  fitBounds(bounds, options = {}) {
     // Convert bounds array to flat array for WASM
     const flatBounds = [
       bounds[0][0], bounds[0][1], // sw_lat, sw_lng
       bounds[1][0], bounds[1][1]  // ne_lat, ne_lng
     ];
-    this.wasmMap.fit_bounds(flatBounds);
+    // A bare number is shorthand for uniform padding
+    const fitOptions = typeof options === 'number' ? { padding: options } : options;
+    this.wasmMap.fit_bounds(flatBounds, fitOptions);
     return this;
   }
   
+  // Height in meters from the terrain layers' loaded tiles, or undefined
+  elevationAt(latlng) {
+    return this.wasmMap.elevation_at(latlng[0], latlng[1]);
+  }
+
   project(latlng) {
     const point = this.wasmMap.project(latlng);
     return [point[0], point[1]];
@@ -277,7 +417,8 @@ class Map {
       'contextmenu': 'on_contextmenu',
       'keydown': 'on_key_down',
       'keyup': 'on_key_up',
-      'dragend': 'on_dragend'
+      'dragend': 'on_dragend',
+      'moveend': 'on_moveend'
     };
     
     const wasmMethod = eventMap[event];
@@ -298,7 +439,8 @@ class Map {
       'contextmenu': 'off_contextmenu',
       'keydown': 'off_key_down',
       'keyup': 'off_key_up',
-      'dragend': 'off_dragend'
+      'dragend': 'off_dragend',
+      'moveend': 'off_moveend'
     };
     
     const wasmMethod = eventMap[event];
@@ -330,8 +472,8 @@ class Map {
         hasDragged = true;
         // Convert screen coordinates to canvas coordinates (accounting for scaling)
         const rect = this.canvas.getBoundingClientRect();
-        const scaleX = this.canvas.width / rect.width;
-        const scaleY = this.canvas.height / rect.height;
+        const scaleX = this.width / rect.width;
+        const scaleY = this.height / rect.height;
         const canvasX = (e.clientX - rect.left) * scaleX;
         const canvasY = (e.clientY - rect.top) * scaleY;
         this.wasmMap.on_mouse_move(canvasX, canvasY);
@@ -348,8 +490,8 @@ class Map {
 
         // Convert screen coordinates to canvas coordinates (accounting for scaling)
         const rect = this.canvas.getBoundingClientRect();
-        const scaleX = this.canvas.width / rect.width;
-        const scaleY = this.canvas.height / rect.height;
+        const scaleX = this.width / rect.width;
+        const scaleY = this.height / rect.height;
         const canvasX = (e.clientX - rect.left) * scaleX;
         const canvasY = (e.clientY - rect.top) * scaleY;
 
@@ -358,14 +500,38 @@ class Map {
       }
     };
 
+    // Right-drag, or Ctrl/Cmd + left-drag, rotates the map sideways and tilts it vertically
+    const handleRotateMove = (e) => {
+      const rect = this.canvas.getBoundingClientRect();
+      const scaleX = this.width / rect.width;
+      const scaleY = this.height / rect.height;
+      this.wasmMap.on_rotate_move((e.clientX - rect.left) * scaleX, (e.clientY - rect.top) * scaleY);
+    };
+
+    const handleRotateEnd = () => {
+      this.canvas.style.cursor = 'grab';
+      document.removeEventListener('mousemove', handleRotateMove);
+      document.removeEventListener('mouseup', handleRotateEnd);
+      this.wasmMap.handle_rotate_end();
+    };
+
     this.canvas.addEventListener('mousedown', (e) => {
-      if (e.button === 0) { // Left mouse button only
+      if (e.button === 2 || (e.button === 0 && (e.ctrlKey || e.metaKey))) {
+        e.preventDefault();
+        const rect = this.canvas.getBoundingClientRect();
+        const scaleX = this.width / rect.width;
+        const scaleY = this.height / rect.height;
+        this.canvas.style.cursor = 'ew-resize';
+        document.addEventListener('mousemove', handleRotateMove);
+        document.addEventListener('mouseup', handleRotateEnd);
+        this.wasmMap.handle_rotate_start((e.clientX - rect.left) * scaleX, (e.clientY - rect.top) * scaleY);
+      } else if (e.button === 0) { // Left mouse button only
         isDragging = true;
         hasDragged = false;
         // Convert screen coordinates to canvas coordinates (accounting for scaling)
         const rect = this.canvas.getBoundingClientRect();
-        const scaleX = this.canvas.width / rect.width;
-        const scaleY = this.canvas.height / rect.height;
+        const scaleX = this.width / rect.width;
+        const scaleY = this.height / rect.height;
         dragStartX = (e.clientX - rect.left) * scaleX;
         dragStartY = (e.clientY - rect.top) * scaleY;
         this.canvas.style.cursor = 'move';
@@ -396,7 +562,15 @@ class Map {
 
     this.canvas.addEventListener('wheel', (e) => {
       e.preventDefault();
-      this.wasmMap.on_wheel(e.deltaY, e.clientX, e.clientY);
+      // Zoom is anchored at the cursor, so pass canvas coordinates
+      const rect = this.canvas.getBoundingClientRect();
+      const scaleX = this.width / rect.width;
+      const scaleY = this.height / rect.height;
+      const canvasX = (e.clientX - rect.left) * scaleX;
+      const canvasY = (e.clientY - rect.top) * scaleY;
+      // Normalize line-based deltas (Firefox) to pixels
+      const deltaY = e.deltaMode === 1 ? e.deltaY * 40 : e.deltaY;
+      this.wasmMap.on_wheel(deltaY, canvasX, canvasY);
     });
 
     this.canvas.addEventListener('contextmenu', (e) => {
@@ -413,8 +587,10 @@ class Map {
     const rect = this.containerElement.getBoundingClientRect();
     this.width = rect.width;
     this.height = rect.height;
-    this.canvas.width = this.width;
-    this.canvas.height = this.height;
+    // devicePixelRatio changes with browser zoom and when moving between screens
+    if (!this.pixelRatio) {
+      this.wasmMap.set_pixel_ratio(window.devicePixelRatio || 1);
+    }
     this.wasmMap.resize(this.width, this.height);
   }
   
@@ -435,19 +611,346 @@ class TileLayer {
   constructor(urlTemplate, options = {}) {
     this.wasmTileLayer = new TileLayerApi(urlTemplate);
     this.options = options;
+    this.map = null;
+    this.layerId = null;
+    this.applyOptions(options);
+  }
+
+  applyOptions(options) {
+    // Whichever bound isn't given keeps its current value, which for WMTS
+    // layers comes from the capabilities
+    if (options.minZoom !== undefined || options.maxZoom !== undefined) {
+      this.wasmTileLayer.set_zoom_range(
+        options.minZoom ?? this.wasmTileLayer.get_min_zoom(),
+        options.maxZoom ?? this.wasmTileLayer.get_max_zoom()
+      );
+    }
+    if (options.opacity !== undefined) {
+      this.wasmTileLayer.set_opacity(options.opacity);
+    }
+    if (options.adjustments) {
+      this.wasmTileLayer.set_adjustments(options.adjustments);
+    }
+    if (options.zIndex !== undefined) {
+      this.wasmTileLayer.set_z_index(options.zIndex);
+    }
+    if (options.subdomains !== undefined) {
+      this.wasmTileLayer.set_subdomains(options.subdomains);
+    }
+    if (options.tms !== undefined) {
+      this.wasmTileLayer.set_tms(options.tms);
+    }
+    if (options.tileSize !== undefined) {
+      this.wasmTileLayer.set_tile_size(options.tileSize);
+    }
+    if (options.zoomOffset !== undefined) {
+      this.wasmTileLayer.set_zoom_offset(options.zoomOffset);
+    }
+    if (options.errorTileUrl) {
+      this.wasmTileLayer.set_error_tile_url(options.errorTileUrl);
+    }
+    if (options.bounds) {
+      const [[swLat, swLng], [neLat, neLng]] = options.bounds;
+      this.wasmTileLayer.set_bounds([swLat, swLng, neLat, neLng]);
+    }
   }
 
+  // Layers stack in the order they are added unless given a zIndex
   addTo(map) {
-    this.wasmTileLayer.add_to(map.wasmMap);
+    this.map = map;
+    this.layerId = this.wasmTileLayer.add_to(map.wasmMap);
     return this;
   }
-  
+
+  setOpacity(opacity) {
+    this.wasmTileLayer.set_opacity(opacity);
+    if (this.map) {
+      this.map.wasmMap.set_tile_layer_opacity(this.layerId, opacity);
+    }
+    return this;
+  }
+
+  // Brightness, contrast, saturation, hue and inversion of raster tiles,
+  // applied as they are drawn; null resets them
+  setAdjustments(adjustments) {
+    this.wasmTileLayer.set_adjustments(adjustments);
+    if (this.map) {
+      this.map.wasmMap.set_tile_layer_adjustments(this.layerId, adjustments);
+    }
+    return this;
+  }
+
+  setZIndex(zIndex) {
+    this.wasmTileLayer.set_z_index(zIndex);
+    if (this.map) {
+      this.map.wasmMap.set_tile_layer_z_index(this.layerId, zIndex);
+    }
+    return this;
+  }
+
+  setVisible(visible) {
+    this.wasmTileLayer.set_visible(visible);
+    if (this.map) {
+      this.map.wasmMap.set_tile_layer_visible(this.layerId, visible);
+    }
+    return this;
+  }
+
+  // 'loaded' | 'loading' | 'retrying' | 'failed' | 'idle'
+  getTileStatus(z, x, y) {
+    if (!this.map) {
+      return 'idle';
+    }
+    return this.map.wasmMap.get_tile_status(this.layerId, z, x, y);
+  }
+
+  // Downloads the layer's tiles covering bounds [[swLat, swLng], [neLat, neLng]]
+  // into the map's tile store for offline use, from options.minZoom (0) to
+  // options.maxZoom (the current zoom). Returns a job with cancel() and
+  // getProgress(); options.onProgress gets progress as tiles complete.
+  // Tiles are stored by URL, so seed {r} and WMS layers at the pixel ratio
+  // they will be viewed at.
+  seed(bounds, options = {}) {
+    if (!this.map) {
+      throw new Error('Tile layer must be added to a map before seeding');
+    }
+    const [[swLat, swLng], [neLat, neLng]] = bounds;
+    const maxZoom = options.maxZoom ?? Math.round(this.map.getZoom());
+    const job = this.map.wasmMap.seed_tiles(this.layerId, [swLat, swLng, neLat, neLng], options.minZoom ?? 0, maxZoom, options.onProgress ?? null);
+    return {
+      cancel: () => job.cancel(),
+      getProgress: () => job.get_progress()
+    };
+  }
+
+  // { tiles, bytes } a seed with the same bounds and options would download
+  estimateSeed(bounds, options = {}) {
+    if (!this.map) {
+      throw new Error('Tile layer must be added to a map before seeding');
+    }
+    const [[swLat, swLng], [neLat, neLng]] = bounds;
+    const maxZoom = options.maxZoom ?? Math.round(this.map.getZoom());
+    return this.map.wasmMap.estimate_seed(this.layerId, [swLat, swLng, neLat, neLng], options.minZoom ?? 0, maxZoom);
+  }
+
   remove() {
-    // Implementation for removing layer
+    if (this.map) {
+      this.map.wasmMap.remove_tile_layer(this.layerId);
+      this.map = null;
+      this.layerId = null;
+    }
+    return this;
+  }
+}
+
+// WMS layer: one GetMap request per tile, for the tile's bbox in the map's CRS
+class WmsLayer extends TileLayer {
+  constructor(baseUrl, options = {}) {
+    super(baseUrl, options);
+    this.wasmTileLayer.set_wms({
+      layers: options.layers,
+      styles: options.styles,
+      format: options.format,
+      transparent: options.transparent,
+      version: options.version,
+      params: options.params
+    });
+  }
+}
+
+// WMTS layer from a GetCapabilities XML document; tile size, zoom range and
+// bounds come from the capabilities unless given as options
+class WmtsLayer extends TileLayer {
+  constructor(capabilities, options = {}) {
+    super('', {});
+    this.wasmTileLayer.set_wmts(capabilities, {
+      layer: options.layer,
+      style: options.style,
+      tileMatrixSet: options.tileMatrixSet,
+      format: options.format
+    });
+    this.options = options;
+    this.applyOptions(options);
+  }
+}
+
+// Vector tile layer: Mapbox Vector Tiles from a {z}/{x}/{y} template, drawn
+// with style rules matched by source layer and feature properties
+class VectorTileLayer extends TileLayer {
+  constructor(urlTemplate, options = {}) {
+    super(urlTemplate, options);
+    this.wasmTileLayer.set_vector_style(options.style ?? null);
+  }
+
+  setStyle(style) {
+    this.wasmTileLayer.set_vector_style(style);
+    if (this.map) {
+      this.map.wasmMap.set_tile_layer_vector_style(this.layerId, style);
+    }
+    return this;
+  }
+}
+
+// Terrain layer: Terrain-RGB or Terrarium tiles (options.encoding) decoded
+// into elevation, which Map.elevationAt reads, and drawn hillshaded with a
+// sun at options.azimuth and options.altitude, colored by options.colorRamp
+class HillshadeLayer extends TileLayer {
+  constructor(urlTemplate, options = {}) {
+    super(urlTemplate, options);
+    this.wasmTileLayer.set_terrain(options);
+  }
+
+  // Reshades the tiles already loaded from their decoded heights; the
+  // encoding can't be changed this way, as tiles aren't decoded again
+  setHillshade(options) {
+    this.wasmTileLayer.set_terrain({ ...this.options, ...options });
+    this.options = { ...this.options, ...options };
+    if (this.map) {
+      this.map.wasmMap.set_tile_layer_hillshade(this.layerId, this.options);
+    }
     return this;
   }
 }
 
+// PMTiles layer: raster or vector tiles read from a single archive with HTTP
+// range requests, or with `options.fetch(offset, length)` if given. The zoom
+// range and bounds default to the archive's; vector tiles take `style`.
+class PmtilesLayer extends VectorTileLayer {
+  constructor(url, options = {}) {
+    super(url, options);
+    this.wasmTileLayer.set_pmtiles(options.fetch ?? null);
+  }
+}
+
+// MBTiles layer: a tileset held in memory, given as the rows of its SQLite
+// `tiles` table (e.g. read by the host with sql.js) and `options.metadata`
+class MbtilesLayer extends VectorTileLayer {
+  constructor(rows, options = {}) {
+    super('', options);
+    this.wasmTileLayer.set_mbtiles(rows, options.metadata ?? {});
+  }
+}
+
+// Image draped over the map, e.g. a scanned plan or a radar frame. `image` is
+// a URL or an ImageData; `bounds` is [[swLat, swLng], [neLat, neLng]], or four
+// [lat, lng] corners from the top-left, clockwise, for a rotated or skewed
+// placement. Overlays stack with tile layers by zIndex, and always draw below
+// point, line, polygon and GeoJSON layers.
+class ImageOverlay {
+  constructor(image, bounds, options = {}) {
+    this.image = image;
+    this.bounds = bounds;
+    this.options = options;
+    this.map = null;
+    this.overlayId = null;
+  }
+
+  addTo(map) {
+    this.map = map;
+    this.overlayId = map.wasmMap.add_image_overlay(this.image, ImageOverlay._corners(this.bounds));
+    if (this.options.opacity !== undefined) {
+      map.wasmMap.set_image_overlay_opacity(this.overlayId, this.options.opacity);
+    }
+    if (this.options.zIndex !== undefined) {
+      map.wasmMap.set_image_overlay_z_index(this.overlayId, this.options.zIndex);
+    }
+    return this;
+  }
+
+  setImage(image) {
+    this.image = image;
+    if (this.map) {
+      this.map.wasmMap.set_image_overlay_image(this.overlayId, image);
+    }
+    return this;
+  }
+
+  setBounds(bounds) {
+    this.bounds = bounds;
+    if (this.map) {
+      this.map.wasmMap.set_image_overlay_corners(this.overlayId, ImageOverlay._corners(bounds));
+    }
+    return this;
+  }
+
+  setOpacity(opacity) {
+    this.options = { ...this.options, opacity };
+    if (this.map) {
+      this.map.wasmMap.set_image_overlay_opacity(this.overlayId, opacity);
+    }
+    return this;
+  }
+
+  setZIndex(zIndex) {
+    this.options = { ...this.options, zIndex };
+    if (this.map) {
+      this.map.wasmMap.set_image_overlay_z_index(this.overlayId, zIndex);
+    }
+    return this;
+  }
+
+  setVisible(visible) {
+    if (this.map) {
+      this.map.wasmMap.set_image_overlay_visible(this.overlayId, visible);
+    }
+    return this;
+  }
+
+  remove() {
+    if (this.map) {
+      this.map.wasmMap.remove_image_overlay(this.overlayId);
+      this.map = null;
+      this.overlayId = null;
+    }
+    return this;
+  }
+
+  static _corners(bounds) {
+    if (bounds.length === 2) {
+      const [[swLat, swLng], [neLat, neLng]] = bounds;
+      return [swLat, swLng, neLat, neLng];
+    }
+    return bounds;
+  }
+}
+
+// Tile store backed by an IndexedDB database, so seeded tiles outlive the page
+class IndexedDbTileStore {
+  constructor(name = 'rustyleaf-tiles') {
+    this.name = name;
+    this.db = null;
+  }
+
+  get(key) {
+    return this._request('readonly', tiles => tiles.get(key));
+  }
+
+  put(key, data) {
+    return this._request('readwrite', tiles => tiles.put(data, key));
+  }
+
+  _open() {
+    if (!this.db) {
+      this.db = new Promise((resolve, reject) => {
+        const request = indexedDB.open(this.name, 1);
+        request.onupgradeneeded = () => request.result.createObjectStore('tiles');
+        request.onsuccess = () => resolve(request.result);
+        request.onerror = () => reject(request.error);
+      });
+    }
+    return this.db;
+  }
+
+  async _request(mode, operation) {
+    const db = await this._open();
+    return new Promise((resolve, reject) => {
+      const request = operation(db.transaction('tiles', mode).objectStore('tiles'));
+      request.onsuccess = () => resolve(request.result);
+      request.onerror = () => reject(request.error);
+    });
+  }
+}
+
 // PointLayer with Leaflet-style API  
 class PointLayer {
   constructor() {
@@ -1620,7 +2123,7 @@ class GeoJSONLayer {
 }
 
 // Export classes
-export { Map, TileLayer, PointLayer, LineLayer, PolygonLayer, GeoJSONLayer, Popup };
+export { Map, TileLayer, WmsLayer, WmtsLayer, VectorTileLayer, PmtilesLayer, MbtilesLayer, HillshadeLayer, ImageOverlay, IndexedDbTileStore, PointLayer, LineLayer, PolygonLayer, GeoJSONLayer, Popup };
 
 // Default export for compatibility
-export default { Map, TileLayer, PointLayer, LineLayer, PolygonLayer, GeoJSONLayer, Popup };
\ No newline at end of file
+export default { Map, TileLayer, WmsLayer, WmtsLayer, VectorTileLayer, PmtilesLayer, MbtilesLayer, HillshadeLayer, ImageOverlay, IndexedDbTileStore, PointLayer, LineLayer, PolygonLayer, GeoJSONLayer, Popup };
\ No newline at end of file
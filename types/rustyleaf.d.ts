@@ -14,6 +14,13 @@ export interface MapOptions {
   zoom?: number;
   minZoom?: number;
   maxZoom?: number;
+  maxBounds?: LatLngBounds;
+  maxBoundsViscosity?: number;
+  renderWorldCopies?: boolean;
+  pixelRatio?: number;
+  bearing?: number;
+  pitch?: number;
+  projection?: 'EPSG:3857' | 'EPSG:4326' | 'EPSG:3413' | 'EPSG:3031';
   zoomControl?: boolean;
   attributionControl?: boolean;
   doubleClickZoom?: boolean;
@@ -37,6 +44,51 @@ export interface MoveEvent extends MapEvent {
   zoom: number;
 }
 
+export type TileStatus = 'loaded' | 'loading' | 'retrying' | 'failed' | 'idle';
+
+export interface TileCacheStats {
+  hits: number;
+  misses: number;
+  evictions: number;
+  tiles: number;
+  bytes: number;
+}
+
+// Persistent tile storage, keyed by tile URL; get resolves to undefined for
+// tiles not stored. URLs of {r} templates and WMS layers include the pixel
+// ratio, so their tiles are only found at the ratio they were seeded at.
+export interface TileStore {
+  get(key: string): Uint8Array | undefined | Promise<Uint8Array | undefined>;
+  put(key: string, data: Uint8Array): void | Promise<unknown>;
+}
+
+export interface SeedProgress {
+  total: number;
+  stored: number;
+  skipped: number;
+  failed: number;
+  bytes: number;
+  finished: boolean;
+  cancelled: boolean;
+}
+
+export interface SeedOptions {
+  minZoom?: number;
+  maxZoom?: number;
+  onProgress?: (progress: SeedProgress) => void;
+}
+
+export interface SeedJob {
+  cancel(): void;
+  getProgress(): SeedProgress;
+}
+
+export interface MoveEndEvent extends MapEvent {
+  type: 'moveend';
+  center: LatLng;
+  zoom: number;
+}
+
 export interface ZoomEvent extends MapEvent {
   type: 'zoom';
   zoom: number;
@@ -61,6 +113,29 @@ export interface KeyboardEvent extends MapEvent {
   key: string;
 }
 
+// Camera animation options for flyTo/easeTo
+export interface AnimationOptions {
+  duration?: number; // seconds
+  easing?: 'linear' | 'easeIn' | 'easeOut' | 'easeInOut' | ((t: number) => number);
+}
+
+// Options for fitBounds; padding is in pixels
+export interface FitBoundsOptions {
+  padding?: number | [number, number, number, number] | { top?: number; right?: number; bottom?: number; left?: number };
+  maxZoom?: number;
+}
+
+// Color adjustments for raster tiles
+export interface RasterAdjustments {
+  brightnessMin?: number;
+  brightnessMax?: number;
+  contrast?: number; // -1 to 1
+  saturation?: number; // -1 to 1
+  grayscale?: boolean;
+  hueRotate?: number; // degrees
+  invert?: boolean; // flips lightness, for dark themes
+}
+
 // Tile layer options
 export interface TileLayerOptions {
   maxZoom?: number;
@@ -70,7 +145,10 @@ export interface TileLayerOptions {
   attribution?: string;
   zoomOffset?: number;
   tileSize?: number;
+  tms?: boolean;
+  bounds?: LatLngBounds;
   opacity?: number;
+  adjustments?: RasterAdjustments;
   zIndex?: number;
   unloadInvisibleTiles?: boolean;
   updateWhenIdle?: boolean;
@@ -78,6 +156,85 @@ export interface TileLayerOptions {
   crossOrigin?: boolean;
 }
 
+// WMS layer options
+export interface WmsLayerOptions extends TileLayerOptions {
+  layers: string;
+  styles?: string;
+  format?: string;
+  transparent?: boolean;
+  version?: '1.1.1' | '1.3.0';
+  params?: Record<string, string | number | boolean>;
+}
+
+// WMTS layer options; by default the first layer, its default style and its
+// first Web Mercator tile matrix set are used
+export interface WmtsLayerOptions extends TileLayerOptions {
+  layer?: string;
+  style?: string;
+  tileMatrixSet?: string;
+  format?: string;
+}
+
+// Vector tile style rule: the first rule whose source layer and property
+// filter match a feature draws it, in the colors the rule sets
+export interface VectorTileStyleRule {
+  sourceLayer?: string;
+  // Property values to match; an array matches any of its values
+  filter?: Record<string, string | number | boolean | Array<string | number | boolean>>;
+  pointColor?: string;
+  pointSize?: number;
+  lineColor?: string;
+  lineWidth?: number;
+  polygonColor?: string;
+}
+
+// Vector tile layer options; without a style every feature is drawn in the
+// default GeoJSON style
+export interface VectorTileLayerOptions extends TileLayerOptions {
+  style?: VectorTileStyleRule[];
+}
+
+// Hillshading of a terrain layer: sun azimuth in degrees clockwise from north
+// and altitude in degrees, slope exaggeration, and [elevation, color] stops
+// coloring the ground by height
+export interface HillshadeOptions {
+  azimuth?: number;
+  altitude?: number;
+  exaggeration?: number;
+  colorRamp?: [number, string][];
+}
+
+export interface HillshadeLayerOptions extends TileLayerOptions, HillshadeOptions {
+  encoding?: 'terrain-rgb' | 'terrarium';
+}
+
+// Image overlay options
+export interface ImageOverlayOptions {
+  opacity?: number;
+  zIndex?: number;
+}
+
+// [[swLat, swLng], [neLat, neLng]], or four [lat, lng] corners from the
+// top-left, clockwise
+export type ImageOverlayBounds = LatLngBounds | [LatLng, LatLng, LatLng, LatLng];
+
+// PMTiles layer options; `fetch` reads byte ranges of the archive in place of
+// HTTP range requests
+export interface PmtilesLayerOptions extends VectorTileLayerOptions {
+  fetch?: (offset: number, length: number) => ArrayBuffer | Uint8Array | Promise<ArrayBuffer | Uint8Array>;
+}
+
+// A row of an MBTiles `tiles` table; rows are numbered from the south
+export type MbtilesRow =
+  | { zoom_level: number; tile_column: number; tile_row: number; tile_data: Uint8Array | ArrayBuffer }
+  | [number, number, number, Uint8Array | ArrayBuffer];
+
+// MBTiles layer options; `metadata` holds the `metadata` table's values, of
+// which format, minzoom, maxzoom and bounds are used
+export interface MbtilesLayerOptions extends VectorTileLayerOptions {
+  metadata?: Record<string, string | number>;
+}
+
 // Point feature
 export interface PointFeature {
   lat: number;
@@ -209,22 +366,39 @@ export declare class Map {
   setZoom(zoom: number): this;
   getCenter(): LatLng;
   getZoom(): number;
+  getProjection(): string;
+  setBearing(bearing: number): this;
+  getBearing(): number;
+  setPitch(pitch: number): this;
+  getPitch(): number;
+  setPixelRatio(ratio: number | null): this;
+  getPixelRatio(): number;
+  setTileCacheLimits(maxTiles: number, maxBytes: number): this;
+  getTileCacheStats(): TileCacheStats;
+  setMaxTileRequests(count: number): this;
+  setTileStore(store: TileStore | 'memory' | null): this;
   getMinZoom(): number;
   getMaxZoom(): number;
   setMinZoom(zoom: number): this;
   setMaxZoom(zoom: number): this;
+  setMaxBounds(bounds: LatLngBounds | null): this;
+  setRenderWorldCopies(enabled: boolean): this;
   zoomIn(delta?: number): this;
   zoomOut(delta?: number): this;
-  fitBounds(bounds: LatLngBounds, padding?: number): this;
+  fitBounds(bounds: LatLngBounds, options?: number | FitBoundsOptions): this;
   getBounds(): LatLngBounds;
+  getBoundsQuad(): [LatLng, LatLng, LatLng, LatLng];
   
   // Projection methods
   project(latlng: LatLng): Point;
+  elevationAt(latlng: LatLng): number | undefined;
   unproject(point: Point): LatLng;
   
   // Pan methods
   panBy(offset: Point): this;
   panTo(center: LatLng): this;
+  flyTo(center: LatLng, zoom?: number, options?: AnimationOptions): this;
+  easeTo(center: LatLng, zoom?: number, options?: AnimationOptions): this;
   
   // Layer methods
   addLayer(layer: Layer): this;
@@ -239,6 +413,7 @@ export declare class Map {
   
   // Specific event methods
   on(type: 'move', handler: (event: MoveEvent) => void): this;
+  on(type: 'moveend', handler: (event: MoveEndEvent) => void): this;
   on(type: 'zoom', handler: (event: ZoomEvent) => void): this;
   on(type: 'click', handler: (event: ClickEvent) => void): this;
   on(type: 'mousedown', handler: (event: MouseEvent) => void): this;
@@ -299,12 +474,70 @@ export declare class TileLayer extends Layer {
   // Methods
   setUrl(url: string): this;
   setOpacity(opacity: number): this;
+  setAdjustments(adjustments: RasterAdjustments | null): this;
   setZIndex(zIndex: number): this;
+  setVisible(visible: boolean): this;
+  getTileStatus(z: number, x: number, y: number): TileStatus;
+  seed(bounds: LatLngBounds, options?: SeedOptions): SeedJob;
+  estimateSeed(bounds: LatLngBounds, options?: SeedOptions): { tiles: number; bytes: number };
   redraw(): this;
   getTileSize(): number;
   getAttribution(): string;
 }
 
+// WMS layer class
+export declare class WmsLayer extends TileLayer {
+  constructor(baseUrl: string, options: WmsLayerOptions);
+}
+
+// WMTS layer class
+export declare class WmtsLayer extends TileLayer {
+  constructor(capabilities: string, options?: WmtsLayerOptions);
+}
+
+// Vector tile layer class
+export declare class VectorTileLayer extends TileLayer {
+  constructor(urlTemplate: string, options?: VectorTileLayerOptions);
+  setStyle(style: VectorTileStyleRule[]): this;
+}
+
+// Hillshade layer class
+export declare class HillshadeLayer extends TileLayer {
+  constructor(urlTemplate: string, options?: HillshadeLayerOptions);
+  // Reshades loaded tiles from their decoded heights; the encoding is fixed
+  // when the layer is created
+  setHillshade(options: HillshadeOptions): this;
+}
+
+// PMTiles layer class
+export declare class PmtilesLayer extends VectorTileLayer {
+  constructor(url: string, options?: PmtilesLayerOptions);
+}
+
+// MBTiles layer class
+export declare class MbtilesLayer extends VectorTileLayer {
+  constructor(rows: MbtilesRow[], options?: MbtilesLayerOptions);
+}
+
+// Image overlay class. Overlays stack with tile layers by zIndex, and always
+// draw below point, line, polygon and GeoJSON layers.
+export declare class ImageOverlay {
+  constructor(image: string | ImageData, bounds: ImageOverlayBounds, options?: ImageOverlayOptions);
+  addTo(map: Map): this;
+  setImage(image: string | ImageData): this;
+  setBounds(bounds: ImageOverlayBounds): this;
+  setOpacity(opacity: number): this;
+  setZIndex(zIndex: number): this;
+  setVisible(visible: boolean): this;
+  remove(): this;
+}
+
+export declare class IndexedDbTileStore implements TileStore {
+  constructor(name?: string);
+  get(key: string): Promise<Uint8Array | undefined>;
+  put(key: string, data: Uint8Array): Promise<unknown>;
+}
+
 // Point layer class
 export declare class PointLayer extends Layer {
   constructor(options?: PointLayerOptions);
@@ -541,6 +774,14 @@ export default {
   Map,
   Layer,
   TileLayer,
+  WmsLayer,
+  WmtsLayer,
+  VectorTileLayer,
+  PmtilesLayer,
+  MbtilesLayer,
+  HillshadeLayer,
+  ImageOverlay,
+  IndexedDbTileStore,
   PointLayer,
   LineLayer,
   PolygonLayer,